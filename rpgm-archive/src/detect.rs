@@ -0,0 +1,277 @@
+use crate::exe::guess_game_kind_from_exe;
+use crate::game_ini::GameIni;
+use crate::GameKind;
+use anyhow::bail;
+use anyhow::Context;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::path::Path;
+
+/// A piece of evidence for the game kind of a game dir.
+#[derive(Debug)]
+struct Signal {
+    /// Where the evidence came from.
+    source: String,
+
+    /// The game kind it points to.
+    game_kind: GameKind,
+}
+
+/// The signals from one source of evidence.
+#[derive(Debug, Default)]
+struct Layer {
+    signals: Vec<Signal>,
+
+    /// Problems with the evidence, that are reported if detection fails.
+    notes: Vec<String>,
+}
+
+impl Layer {
+    /// Get the game kind if this layer has signals and they all agree.
+    fn conclusive_game_kind(&self) -> Option<GameKind> {
+        let first = self.signals.first()?;
+        self.signals
+            .iter()
+            .all(|signal| signal.game_kind == first.game_kind)
+            .then_some(first.game_kind)
+    }
+}
+
+/// Detect the game kind of a game dir.
+///
+/// This consults the exe, then the RGSS dlls named in the Game.ini file or present in the dir,
+/// then the extensions of data files, using the first source that is conclusive.
+/// If none are, detection fails and every signal is reported.
+pub fn detect_game_kind(path: &Path) -> anyhow::Result<GameKind> {
+    let layers = [
+        read_exe_layer(path)?,
+        read_dll_layer(path)?,
+        read_data_layer(path)?,
+    ];
+
+    choose_game_kind(&layers)
+}
+
+/// Choose the game kind from the first conclusive layer.
+fn choose_game_kind(layers: &[Layer]) -> anyhow::Result<GameKind> {
+    if let Some(game_kind) = layers.iter().find_map(Layer::conclusive_game_kind) {
+        return Ok(game_kind);
+    }
+
+    let mut message = if layers.iter().all(|layer| layer.signals.is_empty()) {
+        "failed to determine game type. Use --game to set it.".to_string()
+    } else {
+        "failed to determine game type, since the signals disagree. Use --game to set it."
+            .to_string()
+    };
+    for signal in layers.iter().flat_map(|layer| layer.signals.iter()) {
+        write!(
+            &mut message,
+            "\n  {}: {:?}",
+            signal.source, signal.game_kind
+        )?;
+    }
+    for note in layers.iter().flat_map(|layer| layer.notes.iter()) {
+        write!(&mut message, "\n  {note}")?;
+    }
+    bail!("{message}");
+}
+
+/// Guess the game kind from the exe.
+fn read_exe_layer(path: &Path) -> anyhow::Result<Layer> {
+    let mut layer = Layer::default();
+
+    let game_path = path.join("Game.exe");
+    match std::fs::read(&game_path) {
+        Ok(game_exe) => match guess_game_kind_from_exe(&game_exe) {
+            Ok(Some(game_kind)) => layer.signals.push(Signal {
+                source: "Game.exe".into(),
+                game_kind,
+            }),
+            Ok(None) => layer.notes.push("Game.exe: unknown game type".to_string()),
+            // The exe may be packed or replaced with a custom launcher.
+            Err(error) => layer.notes.push(format!("Game.exe: {error:#}")),
+        },
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+        Err(error) => {
+            return Err(error)
+                .with_context(|| format!("failed to read \"{}\"", game_path.display()));
+        }
+    }
+
+    Ok(layer)
+}
+
+/// Guess the game kind from the RGSS dll in the Game.ini file and the dlls in the dir.
+fn read_dll_layer(path: &Path) -> anyhow::Result<Layer> {
+    let mut layer = Layer::default();
+
+    if let Some(game_ini) = GameIni::load(path, None)? {
+        match game_ini.guess_game_kind() {
+            Some(game_kind) => layer.signals.push(Signal {
+                source: format!(
+                    "{} library \"{}\"",
+                    crate::game_ini::FILE_NAME,
+                    game_ini.library.as_deref().unwrap_or_default()
+                ),
+                game_kind,
+            }),
+            None => layer.notes.push(format!(
+                "{}: unknown library {:?}",
+                crate::game_ini::FILE_NAME,
+                game_ini.library
+            )),
+        }
+    }
+
+    // Newer games keep the dll in the "System" dir.
+    for dir_path in [path.to_path_buf(), path.join("System")] {
+        for file_name in read_file_names(&dir_path)? {
+            if !file_name.to_ascii_lowercase().ends_with(".dll") {
+                continue;
+            }
+            let Some(game_kind) = crate::game_ini::guess_game_kind_from_dll_name(&file_name) else {
+                continue;
+            };
+
+            let relative_path = dir_path.join(&file_name);
+            let relative_path = relative_path.strip_prefix(path)?;
+            layer.signals.push(Signal {
+                source: format!("dll \"{}\"", relative_path.display()),
+                game_kind,
+            });
+        }
+    }
+
+    Ok(layer)
+}
+
+/// Guess the game kind from the extensions of the data files.
+fn read_data_layer(path: &Path) -> anyhow::Result<Layer> {
+    let mut layer = Layer::default();
+
+    let mut extensions = BTreeSet::new();
+    for file_name in read_file_names(&path.join("Data"))? {
+        if let Some((_stem, extension)) = file_name.rsplit_once('.') {
+            extensions.insert(extension.to_ascii_lowercase());
+        }
+    }
+    for extension in extensions {
+        let game_kind = match extension.as_str() {
+            "rxdata" => GameKind::Xp,
+            "rvdata" => GameKind::Vx,
+            "rvdata2" => GameKind::VxAce,
+            _ => continue,
+        };
+
+        layer.signals.push(Signal {
+            source: format!("data files with the \"{extension}\" extension"),
+            game_kind,
+        });
+    }
+
+    Ok(layer)
+}
+
+/// Read the names of the files in a dir, in a stable order.
+///
+/// Returns an empty list if the dir does not exist.
+fn read_file_names(path: &Path) -> anyhow::Result<Vec<String>> {
+    let dir = match path.read_dir() {
+        Ok(dir) => dir,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => {
+            return Err(error).with_context(|| format!("failed to read \"{}\"", path.display()));
+        }
+    };
+
+    let mut file_names = Vec::new();
+    for dir_entry in dir {
+        let dir_entry = dir_entry?;
+        if !dir_entry.file_type()?.is_file() {
+            continue;
+        }
+
+        // Non-unicode names cannot be signals.
+        if let Ok(file_name) = dir_entry.file_name().into_string() {
+            file_names.push(file_name);
+        }
+    }
+    file_names.sort();
+
+    Ok(file_names)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn layer(game_kinds: &[GameKind]) -> Layer {
+        Layer {
+            signals: game_kinds
+                .iter()
+                .map(|game_kind| Signal {
+                    source: format!("{game_kind:?}"),
+                    game_kind: *game_kind,
+                })
+                .collect(),
+            notes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn exe_layer_wins() {
+        let layers = [
+            layer(&[GameKind::VxAce]),
+            layer(&[GameKind::Xp]),
+            layer(&[GameKind::Vx]),
+        ];
+        assert!(choose_game_kind(&layers).unwrap() == GameKind::VxAce);
+    }
+
+    #[test]
+    fn dll_layer_is_used_without_exe() {
+        let layers = [layer(&[]), layer(&[GameKind::Vx]), layer(&[GameKind::Xp])];
+        assert!(choose_game_kind(&layers).unwrap() == GameKind::Vx);
+    }
+
+    #[test]
+    fn data_layer_is_used_without_exe_or_dlls() {
+        // Conflicting dlls are not conclusive.
+        let layers = [
+            layer(&[]),
+            layer(&[GameKind::Xp, GameKind::VxAce]),
+            layer(&[GameKind::VxAce]),
+        ];
+        assert!(choose_game_kind(&layers).unwrap() == GameKind::VxAce);
+    }
+
+    #[test]
+    fn disagreement_reports_all_signals() {
+        let layers = [
+            layer(&[]),
+            layer(&[GameKind::Xp, GameKind::Vx]),
+            layer(&[GameKind::Vx, GameKind::VxAce]),
+        ];
+        let error = choose_game_kind(&layers).unwrap_err().to_string();
+        assert!(error.contains("disagree"));
+        for game_kind in ["Xp", "Vx", "VxAce"] {
+            assert!(error.contains(&format!("  {game_kind}: {game_kind}")));
+        }
+    }
+
+    #[test]
+    fn detect_from_dir() {
+        let path = std::env::temp_dir().join(format!("rpgm-archive-detect-{}", std::process::id()));
+        std::fs::create_dir_all(path.join("System")).unwrap();
+        std::fs::create_dir_all(path.join("Data")).unwrap();
+        std::fs::write(path.join("System").join("RGSS301.dll"), b"").unwrap();
+        std::fs::write(path.join("Data").join("Map001.rxdata"), b"").unwrap();
+
+        // The dll is preferred over the data files.
+        let game_kind = detect_game_kind(&path);
+
+        std::fs::remove_dir_all(&path).unwrap();
+        assert!(game_kind.unwrap() == GameKind::VxAce);
+    }
+}
@@ -0,0 +1,1204 @@
+use crate::GameKind;
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use object::pe::RT_VERSION;
+use object::LittleEndian as LE;
+use object::U16;
+use object::U32;
+
+/// The identity of an application manifest.
+#[derive(serde::Deserialize, Debug)]
+pub struct AssemblyIdentity {
+    /// The version, like "1.0.0.0".
+    #[serde(rename = "@version")]
+    pub version: String,
+
+    /// The processor architecture, like "X86".
+    #[serde(rename = "@processorArchitecture")]
+    pub processor_architecture: Option<String>,
+
+    /// The name of the application.
+    #[serde(rename = "@name")]
+    pub name: String,
+
+    /// The type of the assembly, which is always "win32".
+    #[serde(rename = "@type")]
+    pub type_: String,
+}
+
+/// An application manifest.
+#[derive(serde::Deserialize, Debug)]
+pub struct Assembly {
+    /// The identity of the application.
+    #[serde(rename = "assemblyIdentity")]
+    pub assembly_identity: Option<AssemblyIdentity>,
+
+    /// The description of the application.
+    pub description: Option<Description>,
+}
+
+/// The description of an application manifest.
+#[derive(serde::Deserialize, Debug)]
+pub struct Description {
+    /// The description text.
+    #[serde(rename = "$value")]
+    pub value: String,
+}
+
+/// A version info resource.
+#[derive(Debug)]
+pub struct VersionInfo {
+    /// The language-independent version info.
+    pub fixed_file_info: Option<FixedFileInfo>,
+
+    /// The version strings.
+    pub string_file_info: Option<StringFileInfo>,
+
+    /// The supported languages and code pages.
+    pub var_file_info: Option<VarFileInfo>,
+}
+
+impl VersionInfo {
+    /// See: https://learn.microsoft.com/en-us/windows/win32/menurc/vs-versioninfo
+    fn parse<'data, R>(reader: R, offset: &mut u64, expected_size: u64) -> anyhow::Result<Self>
+    where
+        R: object::read::ReadRef<'data>,
+    {
+        let start_offset = *offset;
+
+        let length: U16<LE> = *reader.read(offset).ok().context("failed to read length")?;
+        let length = u64::from(length.get(LE));
+        ensure!(length <= expected_size);
+
+        let value_length: U16<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read value length")?;
+
+        let type_: U16<LE> = *reader.read(offset).ok().context("failed to read type")?;
+        ensure!(type_.get(LE) == 0, "text version data is not supported");
+
+        let expected_key = "VS_VERSION_INFO\0";
+        let key: &[u16] = reader
+            .read_slice(offset, expected_key.len())
+            .ok()
+            .context("failed to read key")?;
+        let key = String::from_utf16(key)?;
+        ensure!(expected_key == key);
+
+        read_padding(reader, offset)?;
+
+        let value_length_u64 = u64::from(value_length.get(LE));
+        let fixed_file_info = if value_length_u64 != 0 {
+            ensure!(value_length.get(LE) == 52);
+            Some(FixedFileInfo::parse(reader, offset)?)
+        } else {
+            None
+        };
+
+        let mut string_file_info = None;
+        let mut var_file_info = None;
+        let string_file_info_key = "StringFileInfo\0";
+        let var_file_info_key = "VarFileInfo\0";
+        let key_peek_len = std::cmp::min(string_file_info_key.len(), var_file_info_key.len());
+        loop {
+            let read_size = *offset - start_offset;
+            ensure!(read_size <= length);
+            if read_size == length {
+                break;
+            }
+
+            read_padding(reader, offset)?;
+
+            let start_offset = *offset;
+
+            let length: U16<LE> = *reader.read(offset).ok().context("failed to read length")?;
+            let length = length.get(LE);
+
+            let value_length: U16<LE> = *reader
+                .read(offset)
+                .ok()
+                .context("failed to read value length")?;
+            ensure!(value_length.get(LE) == 0);
+
+            let type_: U16<LE> = *reader.read(offset).ok().context("failed to read type")?;
+            ensure!(type_.get(LE) == 1);
+
+            let key_bytes: &[u16] = reader
+                .read_slice(offset, key_peek_len)
+                .ok()
+                .context("failed to read key bytes")?;
+            let key = String::from_utf16(key_bytes)?;
+            if key == string_file_info_key[..key_peek_len] {
+                ensure!(string_file_info.is_none());
+
+                let remaining_key_bytes: &[u16] = reader
+                    .read_slice(offset, string_file_info_key.len() - key_peek_len)
+                    .ok()
+                    .context("failed to read remaining key bytes")?;
+                let remaining_key_bytes = String::from_utf16(remaining_key_bytes)?;
+                ensure!(string_file_info_key[key_peek_len..] == remaining_key_bytes);
+
+                read_padding(reader, offset)?;
+
+                let mut children = Vec::with_capacity(1);
+                loop {
+                    read_padding(reader, offset)?;
+
+                    let table = StringTable::parse(reader, offset)?;
+                    children.push(table);
+
+                    let current_length = *offset - start_offset;
+                    ensure!(current_length <= u64::from(length));
+                    if current_length == u64::from(length) {
+                        break;
+                    }
+                }
+
+                string_file_info = Some(StringFileInfo { children });
+            } else if key == var_file_info_key[..key_peek_len] {
+                // The peeked key is the entire key, so there is nothing left to read.
+                ensure!(var_file_info.is_none());
+
+                let mut translations = Vec::with_capacity(1);
+                loop {
+                    let current_length = *offset - start_offset;
+                    ensure!(current_length <= u64::from(length));
+                    if current_length == u64::from(length) {
+                        break;
+                    }
+
+                    read_padding(reader, offset)?;
+
+                    translations.extend(parse_translation_var(reader, offset)?);
+                }
+
+                var_file_info = Some(VarFileInfo { translations });
+            } else {
+                bail!("unknown key \"{key}\"");
+            }
+        }
+
+        Ok(Self {
+            fixed_file_info,
+            string_file_info,
+            var_file_info,
+        })
+    }
+
+    /// Serialize this version info into a version resource.
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let fixed_file_info = self
+            .fixed_file_info
+            .as_ref()
+            .map(|fixed_file_info| fixed_file_info.to_bytes())
+            .unwrap_or_default();
+
+        let mut buffer = Vec::new();
+        write_version_block(
+            &mut buffer,
+            "VS_VERSION_INFO",
+            u16::try_from(fixed_file_info.len())?,
+            0,
+            &fixed_file_info,
+            |buffer| {
+                if let Some(string_file_info) = self.string_file_info.as_ref() {
+                    write_version_block(buffer, "StringFileInfo", 0, 1, &[], |buffer| {
+                        for table in string_file_info.children.iter() {
+                            table.write(buffer)?;
+                        }
+                        Ok(())
+                    })?;
+                }
+
+                if let Some(var_file_info) = self.var_file_info.as_ref() {
+                    let mut value = Vec::with_capacity(var_file_info.translations.len() * 4);
+                    for translation in var_file_info.translations.iter() {
+                        value.extend(translation.language.to_le_bytes());
+                        value.extend(translation.code_page.to_le_bytes());
+                    }
+
+                    write_version_block(buffer, "VarFileInfo", 0, 1, &[], |buffer| {
+                        write_version_block(
+                            buffer,
+                            "Translation",
+                            u16::try_from(value.len())?,
+                            0,
+                            &value,
+                            |_buffer| Ok(()),
+                        )
+                    })?;
+                }
+
+                Ok(())
+            },
+        )?;
+
+        Ok(buffer)
+    }
+}
+
+/// Write a version info block, like VS_VERSIONINFO or String.
+///
+/// The length of the block does not include the padding after its last child,
+/// which matches what the resource compiler emits.
+fn write_version_block<F>(
+    buffer: &mut Vec<u8>,
+    key: &str,
+    value_length: u16,
+    type_: u16,
+    value: &[u8],
+    write_children: F,
+) -> anyhow::Result<()>
+where
+    F: FnOnce(&mut Vec<u8>) -> anyhow::Result<()>,
+{
+    write_padding(buffer);
+
+    let start = buffer.len();
+    buffer.extend(0_u16.to_le_bytes());
+    buffer.extend(value_length.to_le_bytes());
+    buffer.extend(type_.to_le_bytes());
+    for c in key.encode_utf16().chain(std::iter::once(0)) {
+        buffer.extend(c.to_le_bytes());
+    }
+    write_padding(buffer);
+    buffer.extend(value);
+
+    write_children(buffer)?;
+
+    let length = u16::try_from(buffer.len() - start)
+        .with_context(|| format!("version info block \"{key}\" is too large"))?;
+    buffer[start..start + 2].copy_from_slice(&length.to_le_bytes());
+
+    Ok(())
+}
+
+fn write_padding(buffer: &mut Vec<u8>) {
+    while !buffer.len().is_multiple_of(4) {
+        buffer.push(0);
+    }
+}
+
+/// Parse a "Translation" Var struct, returning the translations.
+///
+/// See: https://learn.microsoft.com/en-us/windows/win32/menurc/var-str
+fn parse_translation_var<'data, R>(reader: R, offset: &mut u64) -> anyhow::Result<Vec<Translation>>
+where
+    R: object::read::ReadRef<'data>,
+{
+    let start_offset = *offset;
+
+    let length: U16<LE> = *reader.read(offset).ok().context("failed to read length")?;
+    let length = length.get(LE);
+
+    let value_length: U16<LE> = *reader
+        .read(offset)
+        .ok()
+        .context("failed to read value length")?;
+    let value_length = value_length.get(LE);
+    ensure!(
+        value_length.is_multiple_of(4),
+        "translation value length {value_length} is not a multiple of 4"
+    );
+
+    let type_: U16<LE> = *reader.read(offset).ok().context("failed to read type")?;
+    ensure!(type_.get(LE) == 0);
+
+    let key = read_utf16_nul_string(reader, offset)?;
+    ensure!(key == "Translation\0", "unknown var key \"{key}\"");
+
+    read_padding(reader, offset)?;
+
+    let value: &[U16<LE>] = reader
+        .read_slice(offset, usize::from(value_length / 2))
+        .ok()
+        .context("failed to read value")?;
+    let translations = value
+        .chunks_exact(2)
+        .map(|chunk| Translation {
+            language: chunk[0].get(LE),
+            code_page: chunk[1].get(LE),
+        })
+        .collect();
+
+    ensure!(*offset - start_offset == u64::from(length));
+
+    Ok(translations)
+}
+
+/// The version strings of a version info resource.
+#[derive(Debug)]
+pub struct StringFileInfo {
+    /// The string tables, one for each language and code page.
+    pub children: Vec<StringTable>,
+}
+
+/// The language info of a version info resource.
+#[derive(Debug)]
+pub struct VarFileInfo {
+    /// The supported languages and code pages.
+    pub translations: Vec<Translation>,
+}
+
+/// A language and code page pair that the exe supports.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Translation {
+    /// The language id, see [`language_name`].
+    pub language: u16,
+
+    /// The code page, see [`code_page_name`].
+    pub code_page: u16,
+}
+
+fn read_padding<'data, R>(reader: R, offset: &mut u64) -> anyhow::Result<()>
+where
+    R: object::read::ReadRef<'data>,
+{
+    let padding_size = 4 - (*offset % 4);
+    if padding_size != 4 {
+        let padding = reader
+            .read_bytes(offset, padding_size)
+            .ok()
+            .context("failed to read padding")?;
+        ensure!(padding.iter().all(|b| *b == 0));
+    }
+
+    Ok(())
+}
+
+fn read_utf16_nul_string<'data, R>(reader: R, offset: &mut u64) -> anyhow::Result<String>
+where
+    R: object::read::ReadRef<'data>,
+{
+    let mut raw = Vec::new();
+    while raw.is_empty() || *raw.last().unwrap() != 0 {
+        let value: U16<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read wide char")?;
+        raw.push(value.get(LE));
+    }
+
+    let value = String::from_utf16(&raw)?;
+
+    Ok(value)
+}
+
+/// The language-independent part of a version info resource.
+///
+/// See: https://learn.microsoft.com/en-us/windows/win32/api/verrsrc/ns-verrsrc-vs_fixedfileinfo
+#[derive(Debug)]
+pub struct FixedFileInfo {
+    /// The version of this struct.
+    pub struct_version: u32,
+
+    /// The file version, see [`format_version`].
+    pub file_version: u64,
+
+    /// The product version, see [`format_version`].
+    pub product_version: u64,
+
+    /// The bits of the file flags that are valid.
+    pub file_flags_mask: u32,
+
+    /// The file flags, like `VS_FF_DEBUG`.
+    pub file_flags: u32,
+
+    /// The os the file was made for, like `VOS_NT_WINDOWS32`.
+    pub file_os: u32,
+
+    /// The type of the file, like `VFT_APP`.
+    pub file_type: u32,
+
+    /// The subtype of the file, used by drivers and fonts.
+    pub file_subtype: u32,
+
+    /// The creation date of the file, which is usually 0.
+    pub file_date: u64,
+}
+
+impl FixedFileInfo {
+    fn parse<'data, R>(reader: R, offset: &mut u64) -> anyhow::Result<Self>
+    where
+        R: object::read::ReadRef<'data>,
+    {
+        let signature: U32<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read signature")?;
+        ensure!(signature.get(LE) == 0xFEEF04BD);
+
+        let struct_version: U32<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read struct version")?;
+        let struct_version = struct_version.get(LE);
+
+        let file_version_ms: U32<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read file version ms")?;
+        let file_version_ls: U32<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read file version ls")?;
+        let file_version =
+            (u64::from(file_version_ms.get(LE)) << 32) | u64::from(file_version_ls.get(LE));
+
+        let product_version_ms: U32<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read product version ms")?;
+        let product_version_ls: U32<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read product version ls")?;
+        let product_version =
+            (u64::from(product_version_ms.get(LE)) << 32) | u64::from(product_version_ls.get(LE));
+
+        let file_flags_mask: U32<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read file flags mask")?;
+        let file_flags_mask = file_flags_mask.get(LE);
+
+        let file_flags: U32<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read file flags")?;
+        let file_flags = file_flags.get(LE);
+
+        let file_os: U32<LE> = *reader.read(offset).ok().context("failed to read file os")?;
+        let file_os = file_os.get(LE);
+
+        let file_type: U32<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read file type")?;
+        let file_type = file_type.get(LE);
+
+        let file_subtype: U32<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read file subtype")?;
+        let file_subtype = file_subtype.get(LE);
+
+        let file_date_ms: U32<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read file date ms")?;
+
+        let file_date_ls: U32<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read file date ls")?;
+        let file_date = (u64::from(file_date_ms.get(LE)) << 32) | u64::from(file_date_ls.get(LE));
+
+        Ok(Self {
+            struct_version,
+            file_version,
+            product_version,
+            file_flags_mask,
+            file_flags,
+            file_os,
+            file_type,
+            file_subtype,
+            file_date,
+        })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(52);
+        for value in [
+            0xFEEF04BD,
+            self.struct_version,
+            (self.file_version >> 32) as u32,
+            self.file_version as u32,
+            (self.product_version >> 32) as u32,
+            self.product_version as u32,
+            self.file_flags_mask,
+            self.file_flags,
+            self.file_os,
+            self.file_type,
+            self.file_subtype,
+            (self.file_date >> 32) as u32,
+            self.file_date as u32,
+        ] {
+            buffer.extend(u32::to_le_bytes(value));
+        }
+
+        buffer
+    }
+}
+
+/// The version strings for a language and code page.
+#[derive(Debug)]
+pub struct StringTable {
+    /// The language and code page, as 8 hex digits like "041104b0".
+    pub key: String,
+
+    /// The strings.
+    pub children: Vec<StringStruct>,
+}
+
+impl StringTable {
+    fn parse<'data, R>(reader: R, offset: &mut u64) -> anyhow::Result<Self>
+    where
+        R: object::read::ReadRef<'data>,
+    {
+        let start_offset = *offset;
+
+        let length: U16<LE> = *reader.read(offset).ok().context("failed to read length")?;
+        let length = length.get(LE);
+
+        let value_length: U16<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read value length")?;
+        ensure!(value_length.get(LE) == 0);
+
+        let type_: U16<LE> = *reader.read(offset).ok().context("failed to read type")?;
+        ensure!(type_.get(LE) == 1);
+
+        let key: &[u16] = reader
+            .read_slice(offset, 8)
+            .ok()
+            .context("failed to read key")?;
+        let key = String::from_utf16(key)?;
+        ensure!(key.bytes().all(|b| b.is_ascii_hexdigit()));
+        ensure!(key.len() == 8);
+
+        read_padding(reader, offset)?;
+
+        let mut children = Vec::new();
+        loop {
+            let string = StringStruct::parse(reader, offset)?;
+            children.push(string);
+
+            let current_length = *offset - start_offset;
+            ensure!(current_length <= u64::from(length));
+            if current_length == u64::from(length) {
+                break;
+            }
+
+            read_padding(reader, offset)?;
+        }
+
+        Ok(Self { key, children })
+    }
+
+    /// Get the language code
+    ///
+    /// # Returns
+    /// Returns `None` if the key is not 8 hex digits.
+    pub fn language(&self) -> Option<u16> {
+        self.parse_key_part(0..4)
+    }
+
+    /// Get the code page
+    ///
+    /// # Returns
+    /// Returns `None` if the key is not 8 hex digits.
+    pub fn code_page(&self) -> Option<u16> {
+        self.parse_key_part(4..8)
+    }
+
+    /// Parse a part of the key as a hex number.
+    fn parse_key_part(&self, range: std::ops::Range<usize>) -> Option<u16> {
+        if self.key.len() != 8 || !self.key.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        u16::from_str_radix(&self.key[range], 16).ok()
+    }
+
+    /// Set the value of a string, adding it if it does not exist.
+    pub fn set_value(&mut self, key: &str, value: &str) {
+        let value: Vec<u16> = value.encode_utf16().chain(std::iter::once(0)).collect();
+
+        match self
+            .children
+            .iter_mut()
+            .find(|string| string.key_str() == key)
+        {
+            Some(string) => string.value = value,
+            None => self.children.push(StringStruct {
+                key: format!("{key}\0"),
+                value,
+            }),
+        }
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) -> anyhow::Result<()> {
+        write_version_block(buffer, &self.key, 0, 1, &[], |buffer| {
+            for string in self.children.iter() {
+                let value: Vec<u8> = string
+                    .value
+                    .iter()
+                    .flat_map(|value| value.to_le_bytes())
+                    .collect();
+
+                write_version_block(
+                    buffer,
+                    string.key_str(),
+                    u16::try_from(string.value.len())?,
+                    1,
+                    &value,
+                    |_buffer| Ok(()),
+                )?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// A version string, like "FileDescription".
+#[derive(Debug)]
+pub struct StringStruct {
+    /// The name of the string, with a nul terminator.
+    pub key: String,
+
+    /// The UTF-16 value of the string, with a nul terminator.
+    pub value: Vec<u16>,
+}
+
+impl StringStruct {
+    fn parse<'data, R>(reader: R, offset: &mut u64) -> anyhow::Result<Self>
+    where
+        R: object::read::ReadRef<'data>,
+    {
+        let start_offset = *offset;
+
+        let length: U16<LE> = *reader.read(offset).ok().context("failed to read length")?;
+        let length = length.get(LE);
+
+        let value_length: U16<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read value length")?;
+        let value_length = value_length.get(LE);
+
+        let type_: U16<LE> = *reader
+            .read(offset)
+            .ok()
+            .context("failed to read value length")?;
+        let type_ = type_.get(LE);
+        ensure!(type_ == 1, "unsupported string struct type {type_}");
+
+        let key = read_utf16_nul_string(reader, offset)?;
+
+        read_padding(reader, offset)?;
+
+        let value: &[U16<LE>] = reader
+            .read_slice(offset, value_length.into())
+            .ok()
+            .context("failed to read value")?;
+        let value: Vec<u16> = value.iter().map(|value| value.get(LE)).collect();
+
+        ensure!(*offset - start_offset == u64::from(length));
+
+        Ok(Self { key, value })
+    }
+
+    /// Get the key, without the nul terminator.
+    pub fn key_str(&self) -> &str {
+        self.key.strip_suffix('\0').unwrap_or(&self.key)
+    }
+
+    /// Decode the value, without the nul terminator.
+    ///
+    /// Values are always stored as UTF-16,
+    /// regardless of the code page of the string table.
+    pub fn value_string(&self) -> anyhow::Result<String> {
+        let value = self.value.strip_suffix(&[0]).unwrap_or(&self.value);
+        let value = String::from_utf16(value)?;
+
+        Ok(value)
+    }
+}
+
+/// Format a version from a fixed file info, like "1.0.2.0".
+pub fn format_version(version: u64) -> String {
+    format!(
+        "{}.{}.{}.{}",
+        (version >> 48) & 0xFFFF,
+        (version >> 32) & 0xFFFF,
+        (version >> 16) & 0xFFFF,
+        version & 0xFFFF
+    )
+}
+
+/// Get the name of a language id.
+///
+/// See: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-lcid/70feba9f-294e-491e-b6eb-56532684c37f
+pub fn language_name(language: u16) -> Option<&'static str> {
+    match language {
+        0x0000 => Some("Language Neutral"),
+        0x0404 => Some("Chinese (Traditional)"),
+        0x0407 => Some("German (Germany)"),
+        0x0409 => Some("English (United States)"),
+        0x040C => Some("French (France)"),
+        0x0410 => Some("Italian (Italy)"),
+        0x0411 => Some("Japanese (Japan)"),
+        0x0412 => Some("Korean (Korea)"),
+        0x0416 => Some("Portuguese (Brazil)"),
+        0x0419 => Some("Russian (Russia)"),
+        0x0804 => Some("Chinese (Simplified)"),
+        0x0809 => Some("English (United Kingdom)"),
+        0x0C0A => Some("Spanish (Spain)"),
+        _ => None,
+    }
+}
+
+/// Get the name of a code page.
+///
+/// See: https://learn.microsoft.com/en-us/windows/win32/intl/code-page-identifiers
+pub fn code_page_name(code_page: u16) -> Option<&'static str> {
+    match code_page {
+        0 => Some("7-bit ASCII"),
+        932 => Some("Shift-JIS"),
+        936 => Some("GBK"),
+        949 => Some("Korean"),
+        950 => Some("Big5"),
+        1200 => Some("UTF-16LE"),
+        1250 => Some("Windows-1250"),
+        1251 => Some("Windows-1251"),
+        1252 => Some("Windows-1252"),
+        65001 => Some("UTF-8"),
+        _ => None,
+    }
+}
+
+/// Get the section table and resource directory of an exe.
+fn get_resource_directory(
+    game_exe: &[u8],
+) -> anyhow::Result<
+    Option<(
+        object::read::pe::SectionTable<'_>,
+        object::read::pe::ResourceDirectory<'_>,
+    )>,
+> {
+    use object::read::File;
+
+    let file = File::parse(game_exe)?;
+    let (section_table, data_directories) = match file {
+        File::Pe32(file) => (file.section_table(), file.data_directories()),
+        File::Pe64(file) => (file.section_table(), file.data_directories()),
+        _ => bail!("unknown object file format {:?}", file.format()),
+    };
+
+    let resource_directory = data_directories.resource_directory(game_exe, &section_table)?;
+
+    Ok(resource_directory.map(|resource_directory| (section_table, resource_directory)))
+}
+
+/// The name or id of a resource.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceId {
+    /// A numeric id.
+    Id(u16),
+
+    /// A name.
+    Name(String),
+}
+
+impl std::fmt::Display for ResourceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "#{id}"),
+            Self::Name(name) => write!(f, "\"{name}\""),
+        }
+    }
+}
+
+/// A resource in an exe.
+#[derive(Debug)]
+pub struct Resource<'data> {
+    /// The type of the resource, like `RT_ICON`.
+    pub kind: ResourceId,
+
+    /// The name or id of the resource.
+    pub id: ResourceId,
+
+    /// The language of the resource.
+    pub language: u16,
+
+    /// The data of the resource.
+    pub data: &'data [u8],
+}
+
+/// Read all resources of a type from an exe, like `RT_ICON`.
+///
+/// See: https://learn.microsoft.com/en-us/windows/win32/menurc/resource-types
+pub fn read_resources(game_exe: &[u8], kind: u16) -> anyhow::Result<Vec<Resource<'_>>> {
+    let kind = ResourceId::Id(kind);
+    let mut resources = read_all_resources(game_exe)?;
+    resources.retain(|resource| resource.kind == kind);
+
+    Ok(resources)
+}
+
+/// Read all resources from an exe, in the order of the resource directory.
+pub fn read_all_resources(game_exe: &[u8]) -> anyhow::Result<Vec<Resource<'_>>> {
+    let (section_table, resource_directory) = match get_resource_directory(game_exe)? {
+        Some(value) => value,
+        None => return Ok(Vec::new()),
+    };
+    let root = resource_directory.root()?;
+
+    let mut resources = Vec::new();
+    for kind_entry in root.entries.iter() {
+        let kind = parse_resource_id(kind_entry.name_or_id(), resource_directory)?;
+        let kind_table = kind_entry
+            .data(resource_directory)?
+            .table()
+            .with_context(|| format!("resource type {kind} data is not a table"))?;
+
+        for name_entry in kind_table.entries.iter() {
+            let id = parse_resource_id(name_entry.name_or_id(), resource_directory)?;
+            let language_table = name_entry
+                .data(resource_directory)?
+                .table()
+                .with_context(|| format!("resource {kind} {id} data is not a table"))?;
+
+            for language_entry in language_table.entries.iter() {
+                let language = language_entry
+                    .name_or_id()
+                    .id()
+                    .with_context(|| format!("resource {kind} {id} language is not an id"))?;
+                let data_entry = language_entry
+                    .data(resource_directory)?
+                    .data()
+                    .with_context(|| {
+                        format!("resource {kind} {id} language {language} is not data")
+                    })?;
+
+                let size = usize::try_from(data_entry.size.get(LE))?;
+                let data = section_table
+                    .pe_data_at(game_exe, data_entry.offset_to_data.get(LE))
+                    .with_context(|| format!("failed to get resource {kind} {id} bytes"))?
+                    .get(..size)
+                    .with_context(|| format!("resource {kind} {id} smaller than declared"))?;
+
+                resources.push(Resource {
+                    kind: kind.clone(),
+                    id: id.clone(),
+                    language,
+                    data,
+                });
+            }
+        }
+    }
+
+    Ok(resources)
+}
+
+/// Convert the name or id of a resource directory entry.
+fn parse_resource_id(
+    name_or_id: object::read::pe::ResourceNameOrId,
+    resource_directory: object::read::pe::ResourceDirectory<'_>,
+) -> anyhow::Result<ResourceId> {
+    use object::read::pe::ResourceNameOrId;
+
+    match name_or_id {
+        ResourceNameOrId::Id(id) => Ok(ResourceId::Id(id)),
+        ResourceNameOrId::Name(name) => {
+            Ok(ResourceId::Name(name.to_string_lossy(resource_directory)?))
+        }
+    }
+}
+
+/// Parse the data of a version info resource.
+pub fn parse_version_info(data: &[u8]) -> anyhow::Result<VersionInfo> {
+    VersionInfo::parse(data, &mut 0, u64::try_from(data.len())?)
+}
+
+/// Read the version info resource of an exe.
+///
+/// See: https://learn.microsoft.com/en-us/windows/win32/menurc/resource-types
+pub fn read_version_info(game_exe: &[u8]) -> anyhow::Result<Option<VersionInfo>> {
+    let (section_table, resource_directory) = match get_resource_directory(game_exe)? {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let root = resource_directory.root()?;
+
+    let entry = root
+        .entries
+        .iter()
+        .find(|entry| entry.name_or_id().id() == Some(RT_VERSION));
+    let entry = match entry {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+
+    let data = entry.data(resource_directory)?;
+    let table = data.table().context("object VERSION data is not a table")?;
+
+    let data = table
+        .entries
+        .first()
+        .context("object VERSION table missing entry 0")?
+        .data(resource_directory)?;
+    let table = data
+        .table()
+        .context("object VERSION table entry 0 is not a table")?;
+
+    let data = table
+        .entries
+        .first()
+        .context("object VERSION table entry 0 table missing entry 0")?
+        .data(resource_directory)?
+        .data()
+        .context("object VERSION table entry 0 table entry 0 is not data")?;
+    let offset = data.offset_to_data.get(LE);
+    let size = usize::try_from(data.size.get(LE))?;
+
+    let (offset, _) = section_table
+        .pe_file_range_at(offset)
+        .context("section missing version offset address")?;
+    let mut offset = u64::from(offset);
+    let version_info = VersionInfo::parse(game_exe, &mut offset, u64::try_from(size)?)?;
+
+    Ok(Some(version_info))
+}
+
+/// Read the manifest resource of an exe.
+pub fn read_manifest(game_exe: &[u8]) -> anyhow::Result<Option<Assembly>> {
+    use object::pe::RT_MANIFEST;
+
+    let (section_table, resource_directory) = match get_resource_directory(game_exe)? {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let root = resource_directory.root()?;
+
+    let manifest_entry = root
+        .entries
+        .iter()
+        .find(|entry| entry.name_or_id().id() == Some(RT_MANIFEST));
+    let manifest_entry = match manifest_entry {
+        Some(manifest_entry) => manifest_entry,
+        None => return Ok(None),
+    };
+
+    let manifest_entry_data = manifest_entry.data(resource_directory)?;
+    let manifest_entry_table = manifest_entry_data
+        .table()
+        .context("object MANIFEST data is not a table")?;
+
+    let manifest_entry_table_entry_data = manifest_entry_table
+        .entries
+        .first()
+        .context("object MANIFEST table missing entry 0")?
+        .data(resource_directory)?;
+    let manifest_entry_table_entry_data_table = manifest_entry_table_entry_data
+        .table()
+        .context("object MANIFEST table entry 0 is not a table")?;
+
+    let manifest_entry_table_entry_data_table_entry_data = manifest_entry_table_entry_data_table
+        .entries
+        .first()
+        .context("object MANIFEST table entry 0 table missing entry 0")?
+        .data(resource_directory)?
+        .data()
+        .context("object MANIFEST table entry 0 table entry 0 is not data")?;
+    let manifest_offset = manifest_entry_table_entry_data_table_entry_data
+        .offset_to_data
+        .get(LE);
+    let manifest_size = usize::try_from(
+        manifest_entry_table_entry_data_table_entry_data
+            .size
+            .get(LE),
+    )?;
+    let code_page = manifest_entry_table_entry_data_table_entry_data
+        .code_page
+        .get(LE);
+
+    let bytes = &section_table
+        .pe_data_at(game_exe, manifest_offset)
+        .context("failed to get object manifest bytes")?
+        .get(..manifest_size)
+        .context("object manifest smaller than declared")?;
+
+    let manifest_string = match code_page {
+        0 => {
+            // This isn't a real LCID from what I can tell,
+            // but rather a null value. Assume ASCII for now.
+            // TODO: Detect encoding dynamically?
+
+            std::str::from_utf8(bytes)?.to_string()
+        }
+        1252 => {
+            let (value, _encoding, malformed) = encoding_rs::WINDOWS_1252.decode(bytes);
+            ensure!(!malformed);
+
+            value.into()
+        }
+        _ => bail!("unknown MANIFEST LCID {code_page}"),
+    };
+
+    let manifest: Assembly =
+        quick_xml::de::from_str(&manifest_string).context("failed to parse manifest string")?;
+
+    Ok(Some(manifest))
+}
+
+fn guess_from_version_info(version_info: &VersionInfo) -> anyhow::Result<Option<GameKind>> {
+    let string_file_info = match version_info.string_file_info.as_ref() {
+        Some(string_file_info) => string_file_info,
+        None => return Ok(None),
+    };
+
+    for table in string_file_info.children.iter() {
+        for string in table.children.iter() {
+            if string.key_str() != "FileDescription" {
+                continue;
+            }
+
+            match string.value_string()?.as_str() {
+                "RGSS Player" => return Ok(Some(GameKind::Xp)),
+                "RGSS2 Player" => return Ok(Some(GameKind::Vx)),
+                "RGSS3 Player" => return Ok(Some(GameKind::VxAce)),
+                _ => {}
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn guess_from_manifest(manifest: &Assembly) -> Option<GameKind> {
+    if manifest
+        .assembly_identity
+        .as_ref()
+        .is_some_and(|assembly_identity| assembly_identity.name == "Enterbrain.RGSS.Game")
+        && manifest
+            .description
+            .as_ref()
+            .map(|description| description.value.as_str())
+            == Some("RGSS Player")
+    {
+        return Some(GameKind::Xp);
+    }
+
+    None
+}
+
+/// See: https://learn.microsoft.com/en-us/openspecs/office_standards/ms-oe376/6c085406-a698-4e12-9d4d-c3b0ee3dbc4a
+pub fn guess_game_kind_from_exe(game_exe: &[u8]) -> anyhow::Result<Option<GameKind>> {
+    if let Some(version_info) = read_version_info(game_exe)? {
+        if let Some(game_kind) = guess_from_version_info(&version_info)? {
+            return Ok(Some(game_kind));
+        }
+    }
+
+    if let Some(manifest) = read_manifest(game_exe)? {
+        if let Some(game_kind) = guess_from_manifest(&manifest) {
+            return Ok(Some(game_kind));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn version_info_round_trip() {
+        let mut string_table = StringTable {
+            key: "041104b0".to_string(),
+            children: Vec::new(),
+        };
+        string_table.set_value("FileDescription", "RGSS Player");
+        string_table.set_value("FileVersion", "1, 0, 2, 0");
+        string_table.set_value("ProductName", "");
+
+        let version_info = VersionInfo {
+            fixed_file_info: Some(FixedFileInfo {
+                struct_version: 0x10000,
+                file_version: 0x0001_0000_0002_0000,
+                product_version: 0x0001_0000_0002_0000,
+                file_flags_mask: 0x3F,
+                file_flags: 0,
+                file_os: 0x4,
+                file_type: 0x1,
+                file_subtype: 0,
+                file_date: 0,
+            }),
+            string_file_info: Some(StringFileInfo {
+                children: vec![string_table],
+            }),
+            var_file_info: Some(VarFileInfo {
+                translations: vec![Translation {
+                    language: 0x0411,
+                    code_page: 0x04b0,
+                }],
+            }),
+        };
+
+        let bytes = version_info.to_bytes().expect("failed to serialize");
+        let parsed = parse_version_info(&bytes).expect("failed to parse");
+
+        let fixed_file_info = parsed
+            .fixed_file_info
+            .as_ref()
+            .expect("missing fixed file info");
+        assert!(fixed_file_info.file_version == 0x0001_0000_0002_0000);
+        assert!(fixed_file_info.file_os == 0x4);
+
+        let string_file_info = parsed
+            .string_file_info
+            .as_ref()
+            .expect("missing string file info");
+        assert!(string_file_info.children.len() == 1);
+        let string_table = &string_file_info.children[0];
+        assert!(string_table.language() == Some(0x0411));
+        assert!(string_table.code_page() == Some(0x04b0));
+        let strings: Vec<(&str, String)> = string_table
+            .children
+            .iter()
+            .map(|string| (string.key_str(), string.value_string().unwrap()))
+            .collect();
+        assert!(
+            strings
+                == [
+                    ("FileDescription", "RGSS Player".to_string()),
+                    ("FileVersion", "1, 0, 2, 0".to_string()),
+                    ("ProductName", String::new()),
+                ]
+        );
+
+        let var_file_info = parsed
+            .var_file_info
+            .as_ref()
+            .expect("missing var file info");
+        assert!(
+            var_file_info.translations
+                == [Translation {
+                    language: 0x0411,
+                    code_page: 0x04b0,
+                }]
+        );
+
+        let bytes_again = parsed.to_bytes().expect("failed to serialize again");
+        assert!(bytes == bytes_again);
+        parse_version_info(&bytes_again).expect("failed to parse again");
+    }
+
+    #[test]
+    fn string_table_invalid_key() {
+        for key in ["0411", "041104b0\0", "+41104b0", "0411 4b0", ""] {
+            let string_table = StringTable {
+                key: key.to_string(),
+                children: Vec::new(),
+            };
+            assert!(string_table.language().is_none());
+            assert!(string_table.code_page().is_none());
+        }
+    }
+}
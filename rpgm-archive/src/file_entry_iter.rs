@@ -0,0 +1,474 @@
+use crate::detect_game_kind;
+use crate::GameKind;
+use anyhow::ensure;
+use anyhow::Context;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+use walkdir::WalkDir;
+
+/// A lending iter over the files of a game dir or archive.
+///
+/// Use [`FileEntryIter::next_file_entry`] to iterate over entries,
+/// or [`FileEntryIter::find_file_entry`] to look one up by its path.
+#[derive(Debug)]
+pub struct FileEntryIter {
+    inner: FileEntryIterInner,
+}
+
+#[derive(Debug)]
+enum FileEntryIterInner {
+    WalkDir {
+        input_path: PathBuf,
+        iter: walkdir::IntoIter,
+        game_kind: GameKind,
+    },
+    Rgssad {
+        reader: crate::rgssad::Reader<BufReader<File>>,
+        game_kind: GameKind,
+    },
+    Rgss3a {
+        reader: crate::rgss3a::Reader<BufReader<File>>,
+    },
+}
+
+impl FileEntryIter {
+    /// Create a new iter from a path.
+    ///
+    /// This will determine whether the path is a dir or an rgssad.
+    /// If a game kind is provided, it will be used instead of detecting it,
+    /// and it must match the archive format of a game dir.
+    /// If a key is provided, it will be used to decrypt archives instead of the default key.
+    pub fn new<P>(path: P, game_kind: Option<GameKind>, key: Option<u32>) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        if !path.is_dir() {
+            return Self::new_rgssad_path(path, game_kind, key);
+        }
+
+        let rgssad_path = path.join("Game.rgssad");
+        match File::open(&rgssad_path) {
+            Ok(file) => {
+                ensure!(
+                    game_kind != Some(GameKind::VxAce),
+                    "\"{}\" is an rgssad archive, which VX Ace does not use",
+                    rgssad_path.display()
+                );
+                return Self::new_rgssad_file(file, game_kind.unwrap_or(GameKind::Xp), key);
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("failed to open \"{}\"", rgssad_path.display()));
+            }
+        };
+
+        let rgssad_path = path.join("Game.rgss2a");
+        match File::open(&rgssad_path) {
+            Ok(file) => {
+                ensure!(
+                    game_kind != Some(GameKind::VxAce),
+                    "\"{}\" is an rgssad archive, which VX Ace does not use",
+                    rgssad_path.display()
+                );
+                return Self::new_rgssad_file(file, game_kind.unwrap_or(GameKind::Vx), key);
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("failed to open \"{}\"", rgssad_path.display()));
+            }
+        };
+
+        let rgssad_path = path.join("Game.rgss3a");
+        match File::open(&rgssad_path) {
+            Ok(file) => {
+                ensure!(
+                    matches!(game_kind, None | Some(GameKind::VxAce)),
+                    "\"{}\" is an rgss3a archive, which only VX Ace uses",
+                    rgssad_path.display()
+                );
+                return Self::new_rgss3a_file(file, key);
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("failed to open \"{}\"", rgssad_path.display()));
+            }
+        };
+
+        ensure!(
+            path.join("Data").exists(),
+            "Data directory is missing. Are you sure the input folder is correct?"
+        );
+        ensure!(
+            path.join("Graphics").exists(),
+            "Graphics directory is missing. Are you sure the input folder is correct?"
+        );
+
+        Self::new_walkdir_path(path, game_kind)
+    }
+
+    /// Create a new iter from the given dir path.
+    ///
+    /// If a game kind is not provided, it is detected from the files in the dir.
+    pub fn new_walkdir_path<P>(path: P, game_kind: Option<GameKind>) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        let game_kind = match game_kind {
+            Some(game_kind) => game_kind,
+            None => detect_game_kind(path)?,
+        };
+
+        let iter = WalkDir::new(path).into_iter();
+
+        Ok(Self {
+            inner: FileEntryIterInner::WalkDir {
+                input_path: path.into(),
+                iter,
+                game_kind,
+            },
+        })
+    }
+
+    /// Create a new iter from the given rgssad path.
+    ///
+    /// If a game kind is not provided, it is detected from the extension.
+    pub fn new_rgssad_path<P>(
+        path: P,
+        game_kind: Option<GameKind>,
+        key: Option<u32>,
+    ) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let game_kind = match game_kind {
+            Some(game_kind) => game_kind,
+            None => path
+                .extension()
+                .context("missing extension")?
+                .to_str()
+                .context("extension is not unicode")?
+                .parse()?,
+        };
+        let file = File::open(path)
+            .with_context(|| format!("failed to open input file from \"{}\"", path.display()))?;
+
+        match game_kind {
+            GameKind::Xp | GameKind::Vx => Self::new_rgssad_file(file, game_kind, key),
+            GameKind::VxAce => Self::new_rgss3a_file(file, key),
+        }
+    }
+
+    /// Create a new iter from the given rgssad file.
+    ///
+    /// If a key is not provided, the default key is used.
+    pub fn new_rgssad_file(
+        file: File,
+        game_kind: GameKind,
+        key: Option<u32>,
+    ) -> anyhow::Result<Self> {
+        let key = key.unwrap_or(crate::rgssad::DEFAULT_KEY);
+        let mut reader = crate::rgssad::Reader::new(BufReader::new(file), key);
+        reader
+            .read_header()
+            .context("failed to read rgssad header")?;
+
+        Ok(Self {
+            inner: FileEntryIterInner::Rgssad { reader, game_kind },
+        })
+    }
+
+    /// Create a new iter from the given rgss3a file.
+    ///
+    /// If a base key is not provided, the base key from the header is used.
+    pub fn new_rgss3a_file(file: File, base_key: Option<u32>) -> anyhow::Result<Self> {
+        let mut reader = crate::rgss3a::Reader::new(BufReader::new(file), base_key);
+        reader
+            .read_header()
+            .context("failed to read rgss3a header")?;
+
+        Ok(Self {
+            inner: FileEntryIterInner::Rgss3a { reader },
+        })
+    }
+
+    /// Get the next file entry.
+    pub fn next_file_entry(&mut self) -> anyhow::Result<Option<FileEntry<'_>>> {
+        match &mut self.inner {
+            FileEntryIterInner::WalkDir {
+                input_path, iter, ..
+            } => {
+                let entry = loop {
+                    let entry = match iter.next() {
+                        Some(Ok(entry)) => entry,
+                        Some(Err(error)) => return Err(error).context("failed to read dir entry"),
+                        None => return Ok(None),
+                    };
+
+                    // Rgssad archives only contain the "Data" and "Graphics" folders at the top level.
+                    // Only include these folders for parity with rgssad archives.
+                    if entry.depth() == 1
+                        && ![OsStr::new("Data"), OsStr::new("Graphics")]
+                            .contains(&entry.file_name())
+                    {
+                        if entry.file_type().is_dir() {
+                            iter.skip_current_dir();
+                        }
+                        continue;
+                    }
+
+                    // Filter out dir entries, to keep similar behavior with rgssad.
+                    if entry.file_type().is_dir() {
+                        continue;
+                    }
+
+                    break entry;
+                };
+                ensure!(!entry.path_is_symlink());
+
+                let file = File::open(entry.path())?;
+                let size = file.metadata()?.len();
+
+                let entry_path = entry.into_path();
+                let relative_path = entry_path.strip_prefix(input_path)?;
+                let relative_path = relative_path
+                    .to_str()
+                    .context("relative path is not utf8")?;
+
+                Ok(Some(FileEntry {
+                    inner: FileEntryInner::WalkDir {
+                        relative_path: relative_path.into(),
+                        file: BufReader::new(file),
+                        size,
+                    },
+                }))
+            }
+            FileEntryIterInner::Rgssad { reader, .. } => {
+                let file = match reader.read_file()? {
+                    Some(file) => file,
+                    None => return Ok(None),
+                };
+
+                Ok(Some(FileEntry {
+                    inner: FileEntryInner::Rgssad { file },
+                }))
+            }
+            FileEntryIterInner::Rgss3a { reader } => {
+                let file = match reader.read_file()? {
+                    Some(file) => file,
+                    None => return Ok(None),
+                };
+
+                Ok(Some(FileEntry {
+                    inner: FileEntryInner::Rgss3a { file },
+                }))
+            }
+        }
+    }
+
+    /// Go back to the first file entry.
+    pub fn rewind(&mut self) {
+        match &mut self.inner {
+            FileEntryIterInner::WalkDir {
+                input_path, iter, ..
+            } => {
+                *iter = WalkDir::new(&*input_path).into_iter();
+            }
+            FileEntryIterInner::Rgssad { reader, .. } => reader.rewind(),
+            FileEntryIterInner::Rgss3a { reader } => reader.rewind(),
+        }
+    }
+
+    /// Look up a file entry by its relative path, like "Data/Scripts.rxdata".
+    ///
+    /// Both `/` and `\` are accepted as separators,
+    /// and paths are compared ASCII case-insensitively, like on Windows.
+    /// Afterwards, iteration continues from an unspecified entry.
+    /// Use [`FileEntryIter::rewind`] to start over.
+    pub fn find_file_entry(
+        &mut self,
+        relative_path: &str,
+    ) -> anyhow::Result<Option<FileEntry<'_>>> {
+        // The entry cannot be returned while looping over entries,
+        // so find its index first and then seek to it.
+        self.rewind();
+        let mut found = None;
+        let mut index = 0;
+        while let Some(entry) = self.next_file_entry()? {
+            if relative_paths_match(entry.relative_path().as_str(), relative_path) {
+                found = Some(entry.relative_path().to_path_buf());
+                break;
+            }
+            index += 1;
+        }
+        let Some(found) = found else {
+            return Ok(None);
+        };
+
+        match &self.inner {
+            FileEntryIterInner::WalkDir { input_path, .. } => {
+                let file = File::open(input_path.join(&found))?;
+                let size = file.metadata()?.len();
+
+                Ok(Some(FileEntry {
+                    inner: FileEntryInner::WalkDir {
+                        relative_path: found,
+                        file: BufReader::new(file),
+                        size,
+                    },
+                }))
+            }
+            FileEntryIterInner::Rgssad { .. } | FileEntryIterInner::Rgss3a { .. } => {
+                self.rewind();
+                for _ in 0..index {
+                    self.next_file_entry()?;
+                }
+                self.next_file_entry()
+            }
+        }
+    }
+
+    /// Get the determined game kind
+    pub fn game_kind(&self) -> GameKind {
+        match &self.inner {
+            FileEntryIterInner::WalkDir { game_kind, .. } => *game_kind,
+            FileEntryIterInner::Rgssad { game_kind, .. } => *game_kind,
+            FileEntryIterInner::Rgss3a { .. } => GameKind::VxAce,
+        }
+    }
+}
+
+/// Returns true if two relative paths refer to the same file.
+fn relative_paths_match(a: &str, b: &str) -> bool {
+    let mut a = a
+        .split(['/', '\\'])
+        .filter(|component| !component.is_empty());
+    let mut b = b
+        .split(['/', '\\'])
+        .filter(|component| !component.is_empty());
+
+    loop {
+        match (a.next(), b.next()) {
+            (Some(a), Some(b)) if a.eq_ignore_ascii_case(b) => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// A file in a game dir or archive.
+///
+/// The file data is read with the [`Read`] impl.
+#[derive(Debug)]
+pub struct FileEntry<'a> {
+    inner: FileEntryInner<'a>,
+}
+
+#[derive(Debug)]
+enum FileEntryInner<'a> {
+    WalkDir {
+        relative_path: Utf8PathBuf,
+        file: BufReader<File>,
+        size: u64,
+    },
+    Rgssad {
+        file: crate::rgssad::File<'a, BufReader<File>>,
+    },
+    Rgss3a {
+        file: crate::rgss3a::File<'a, BufReader<File>>,
+    },
+}
+
+impl FileEntry<'_> {
+    /// Get the relative path of this entry.
+    pub fn relative_path(&self) -> &Utf8Path {
+        match &self.inner {
+            FileEntryInner::WalkDir { relative_path, .. } => relative_path,
+            FileEntryInner::Rgssad { file } => Utf8Path::new(file.name()),
+            FileEntryInner::Rgss3a { file } => Utf8Path::new(file.name()),
+        }
+    }
+
+    /// Get the size of this entry's data.
+    pub fn size(&self) -> u64 {
+        match &self.inner {
+            FileEntryInner::WalkDir { size, .. } => *size,
+            FileEntryInner::Rgssad { file } => file.size().into(),
+            FileEntryInner::Rgss3a { file } => file.size().into(),
+        }
+    }
+
+    /// Get the offset of this entry's data in the archive.
+    ///
+    /// # Returns
+    /// Returns `None` if this entry is not from an archive.
+    pub fn offset(&self) -> Option<u64> {
+        match &self.inner {
+            FileEntryInner::WalkDir { .. } => None,
+            FileEntryInner::Rgssad { file } => Some(file.offset()),
+            FileEntryInner::Rgss3a { file } => Some(file.offset().into()),
+        }
+    }
+}
+
+impl Read for FileEntry<'_> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        match &mut self.inner {
+            FileEntryInner::WalkDir { file, .. } => file.read(buffer),
+            FileEntryInner::Rgssad { file } => file.read(buffer),
+            FileEntryInner::Rgss3a { file } => file.read(buffer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn relative_paths_match_sanity() {
+        assert!(relative_paths_match(
+            "Data\\Scripts.rxdata",
+            "Data/Scripts.rxdata"
+        ));
+        assert!(relative_paths_match(
+            "Graphics/Pictures/Title.png",
+            "graphics/pictures/title.PNG"
+        ));
+        assert!(!relative_paths_match("Data/Scripts.rxdata", "Data"));
+        assert!(!relative_paths_match(
+            "Data/Map001.rxdata",
+            "Data/Map002.rxdata"
+        ));
+    }
+
+    #[test]
+    fn new_rejects_contradicting_game_kind() {
+        let dir = std::env::temp_dir().join("rpgm-archive-new-rejects-contradicting-game-kind");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        std::fs::write(dir.join("Game.rgss3a"), b"").unwrap();
+        assert!(FileEntryIter::new(&dir, Some(GameKind::Xp), None).is_err());
+        assert!(FileEntryIter::new(&dir, Some(GameKind::Vx), None).is_err());
+
+        std::fs::write(dir.join("Game.rgss2a"), b"").unwrap();
+        let error = FileEntryIter::new(&dir, Some(GameKind::VxAce), None).unwrap_err();
+        assert!(error.to_string().contains("VX Ace does not use"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,251 @@
+use anyhow::bail;
+use anyhow::Context;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// An abstraction of a file sink over dir and rgssad output formats.
+///
+/// Call [`FileSink::finish`] after writing all files.
+#[derive(Debug)]
+pub struct FileSink {
+    inner: FileSinkInner,
+}
+
+#[derive(Debug)]
+enum FileSinkInner {
+    Dir {
+        base_path: PathBuf,
+    },
+    Rgssad {
+        writer: crate::rgssad::Writer<BufWriter<File>>,
+    },
+    Rgss3a {
+        writer: crate::rgss3a::Writer<BufWriter<File>, File>,
+
+        /// This is after the writer, so the spill file is closed before it is deleted.
+        spill: TempFileGuard,
+    },
+}
+
+/// A temporary file that is deleted when dropped.
+///
+/// Errors are ignored when dropped, use [`TempFileGuard::remove`] to handle them.
+#[derive(Debug)]
+struct TempFileGuard {
+    path: PathBuf,
+    removed: bool,
+}
+
+impl TempFileGuard {
+    /// Delete the file.
+    fn remove(&mut self) -> std::io::Result<()> {
+        self.removed = true;
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+impl Drop for TempFileGuard {
+    fn drop(&mut self) {
+        if !self.removed {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl FileSink {
+    /// Create a new file sink for a directory
+    pub fn new_dir(path: &Path, overwrite: bool) -> anyhow::Result<Self> {
+        if path.try_exists()? {
+            if overwrite {
+                std::fs::remove_dir_all(path)?;
+            } else {
+                bail!("output path exists");
+            }
+        }
+
+        std::fs::create_dir_all(path)
+            .with_context(|| format!("failed to create dir at \"{}\"", path.display()))?;
+
+        // TODO: Maybe use a dir lock?
+
+        Ok(Self {
+            inner: FileSinkInner::Dir {
+                base_path: path.into(),
+            },
+        })
+    }
+
+    /// Create a new file sink for an rgssad file
+    ///
+    /// If a key is not provided, the default key is used.
+    pub fn new_rgssad(path: &Path, overwrite: bool, key: Option<u32>) -> anyhow::Result<Self> {
+        if path.try_exists()? {
+            if overwrite {
+                std::fs::remove_file(path)?;
+            } else {
+                bail!("output path exists");
+            }
+        }
+
+        // TODO: Lock the file?
+
+        let file = File::create_new(path)?;
+        let file = BufWriter::new(file);
+        let key = key.unwrap_or(crate::rgssad::DEFAULT_KEY);
+        let mut writer = crate::rgssad::Writer::new(file, key);
+        writer.write_header()?;
+
+        Ok(Self {
+            inner: FileSinkInner::Rgssad { writer },
+        })
+    }
+
+    /// Create a new file sink for an rgss3a file
+    ///
+    /// If a base key is not provided, the default base key is used.
+    pub fn new_rgss3a(path: &Path, overwrite: bool, base_key: Option<u32>) -> anyhow::Result<Self> {
+        if path.try_exists()? {
+            if overwrite {
+                std::fs::remove_file(path)?;
+            } else {
+                bail!("output path exists");
+            }
+        }
+
+        // TODO: Lock the file?
+
+        let file = File::create_new(path)?;
+        let file = BufWriter::new(file);
+
+        // The file table comes before the file data,
+        // so we need to hold the file data somewhere until we know the size of the table.
+        // A spill file left behind by a killed process is stale, so it can be replaced.
+        let spill_path = nd_util::with_push_extension(path, "temp");
+        if overwrite && spill_path.try_exists()? {
+            std::fs::remove_file(&spill_path)?;
+        }
+        let spill_file = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&spill_path)
+            .with_context(|| format!("failed to create \"{}\"", spill_path.display()))?;
+        let spill = TempFileGuard {
+            path: spill_path,
+            removed: false,
+        };
+
+        let base_key = base_key.unwrap_or(crate::rgss3a::DEFAULT_BASE_KEY);
+        let writer = crate::rgss3a::Writer::new(file, spill_file, base_key);
+
+        Ok(Self {
+            inner: FileSinkInner::Rgss3a { writer, spill },
+        })
+    }
+
+    /// Write a file.
+    ///
+    /// The file data is streamed, so the size does not need to be known up front.
+    pub fn write_file<R>(&mut self, path_components: &[&str], mut reader: R) -> anyhow::Result<()>
+    where
+        R: Read,
+    {
+        match &mut self.inner {
+            FileSinkInner::Dir { base_path } => {
+                let mut path = base_path.clone();
+                path.extend(path_components);
+
+                if let Some(parent_path) = path.parent() {
+                    std::fs::create_dir_all(parent_path)?;
+                }
+
+                // TODO: Temp paths?
+                let mut file = File::create_new(path)?;
+                std::io::copy(&mut reader, &mut file)?;
+                file.flush()?;
+                file.sync_all()?;
+            }
+            FileSinkInner::Rgssad { writer } => {
+                // Create a windows-style path.
+                let path = path_components.join("\\");
+
+                writer.write_file(&path, reader)?;
+            }
+            FileSinkInner::Rgss3a { writer, .. } => {
+                // Create a windows-style path.
+                let path = path_components.join("\\");
+
+                writer.write_file(&path, reader)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finish and close this file sink.
+    ///
+    /// For rgss3a archives, this also deletes the temporary spill file.
+    pub fn finish(&mut self) -> anyhow::Result<()> {
+        match &mut self.inner {
+            FileSinkInner::Dir { .. } => {}
+            FileSinkInner::Rgssad { writer } => {
+                let buf_writer = writer.get_mut();
+                buf_writer.flush()?;
+
+                let file = buf_writer.get_mut();
+                file.sync_all()?;
+            }
+            FileSinkInner::Rgss3a { writer, spill } => {
+                writer.finish()?;
+
+                let buf_writer = writer.get_mut();
+                buf_writer.flush()?;
+
+                let file = buf_writer.get_mut();
+                file.sync_all()?;
+
+                spill.remove().with_context(|| {
+                    format!(
+                        "failed to delete temporary file \"{}\"",
+                        spill.path.display()
+                    )
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rgss3a_finish_removes_spill() {
+        let dir = std::env::temp_dir().join("rpgm-archive-rgss3a-finish-removes-spill");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Game.rgss3a");
+        let spill_path = nd_util::with_push_extension(&path, "temp");
+
+        let mut file_sink = FileSink::new_rgss3a(&path, false, None).unwrap();
+        file_sink
+            .write_file(&["Data", "Scripts.rvdata2"], &b"data"[..])
+            .unwrap();
+        assert!(spill_path.exists());
+        file_sink.finish().unwrap();
+        assert!(!spill_path.exists());
+        drop(file_sink);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
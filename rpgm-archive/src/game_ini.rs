@@ -0,0 +1,237 @@
+use crate::GameKind;
+use anyhow::Context;
+use encoding_rs::Encoding;
+use std::path::Path;
+
+/// The name of the ini file of a game.
+pub const FILE_NAME: &str = "Game.ini";
+
+/// The section of the ini file that holds the game settings.
+const GAME_SECTION: &str = "Game";
+
+/// The parsed settings of a Game.ini file.
+#[derive(Debug)]
+pub struct GameIni {
+    /// The path of the RGSS dll, like "RGSS102E.dll" or "System\RGSS301.dll".
+    pub library: Option<String>,
+
+    /// The path of the scripts data file, like "Data\Scripts.rxdata".
+    pub scripts: Option<String>,
+
+    /// The title of the game.
+    pub title: Option<String>,
+
+    /// The encoding the file was decoded with.
+    pub encoding: &'static Encoding,
+
+    /// Whether the encoding was guessed from text that is also valid in another encoding.
+    ///
+    /// If true, the title may be decoded wrong and callers should ask for an explicit encoding.
+    pub encoding_is_ambiguous: bool,
+}
+
+impl GameIni {
+    /// Load the Game.ini file in a game dir.
+    ///
+    /// See [`GameIni::parse`] for how the encoding is used.
+    ///
+    /// # Returns
+    /// Returns `None` if the file does not exist.
+    pub fn load(
+        game_dir: &Path,
+        encoding: Option<&'static Encoding>,
+    ) -> anyhow::Result<Option<Self>> {
+        let path = game_dir.join(FILE_NAME);
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("failed to read \"{}\"", path.display()));
+            }
+        };
+
+        Ok(Some(Self::parse(&bytes, encoding)))
+    }
+
+    /// Parse a Game.ini file.
+    ///
+    /// The editor writes this file in the system code page.
+    /// If an encoding is provided, it is used unless the file is not valid in it,
+    /// in which case Windows-1252 is used.
+    /// Otherwise, non-UTF-8 files are decoded as Shift-JIS if possible and Windows-1252 otherwise.
+    pub fn parse(bytes: &[u8], encoding: Option<&'static Encoding>) -> Self {
+        let (text, encoding, encoding_is_ambiguous) = decode(bytes, encoding);
+
+        let mut library = None;
+        let mut scripts = None;
+        let mut title = None;
+
+        let mut in_game_section = false;
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(section) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                in_game_section = section.trim().eq_ignore_ascii_case(GAME_SECTION);
+                continue;
+            }
+
+            if !in_game_section {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let key = key.trim();
+            let value = value.trim().to_string();
+
+            if key.eq_ignore_ascii_case("Library") {
+                library = Some(value);
+            } else if key.eq_ignore_ascii_case("Scripts") {
+                scripts = Some(value);
+            } else if key.eq_ignore_ascii_case("Title") {
+                title = Some(value);
+            }
+        }
+
+        Self {
+            library,
+            scripts,
+            title,
+            encoding,
+            encoding_is_ambiguous,
+        }
+    }
+
+    /// Guess the game kind from the RGSS dll.
+    pub fn guess_game_kind(&self) -> Option<GameKind> {
+        let library = self.library.as_deref()?;
+        let file_name = library.rsplit(['\\', '/']).next().unwrap_or(library);
+
+        guess_game_kind_from_dll_name(file_name)
+    }
+
+    /// Get the path of the scripts data file, as components.
+    pub fn scripts_path_components(&self) -> Option<Vec<&str>> {
+        let scripts = self.scripts.as_deref()?;
+
+        Some(
+            scripts
+                .split(['\\', '/'])
+                .filter(|component| !component.is_empty())
+                .collect(),
+        )
+    }
+}
+
+/// Guess the game kind from the file name of an RGSS dll, like "RGSS102E.dll".
+pub fn guess_game_kind_from_dll_name(file_name: &str) -> Option<GameKind> {
+    let file_name = file_name.to_ascii_lowercase();
+
+    if file_name.starts_with("rgss1") {
+        Some(GameKind::Xp)
+    } else if file_name.starts_with("rgss2") {
+        Some(GameKind::Vx)
+    } else if file_name.starts_with("rgss3") {
+        Some(GameKind::VxAce)
+    } else {
+        None
+    }
+}
+
+/// Decode the text of an ini file.
+///
+/// This also returns whether the guessed encoding is ambiguous.
+fn decode(bytes: &[u8], encoding: Option<&'static Encoding>) -> (String, &'static Encoding, bool) {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (text, _malformed) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        return (text.into_owned(), encoding, false);
+    }
+
+    if let Some(encoding) = encoding {
+        if let Some(text) = encoding.decode_without_bom_handling_and_without_replacement(bytes) {
+            return (text.into_owned(), encoding, false);
+        }
+    } else {
+        if let Some(text) =
+            encoding_rs::UTF_8.decode_without_bom_handling_and_without_replacement(bytes)
+        {
+            return (text.into_owned(), encoding_rs::UTF_8, false);
+        }
+
+        // Windows-1252 can decode anything that Shift-JIS can.
+        if let Some(text) =
+            encoding_rs::SHIFT_JIS.decode_without_bom_handling_and_without_replacement(bytes)
+        {
+            return (text.into_owned(), encoding_rs::SHIFT_JIS, true);
+        }
+    }
+
+    let (text, _malformed) = encoding_rs::WINDOWS_1252.decode_without_bom_handling(bytes);
+    (text.into_owned(), encoding_rs::WINDOWS_1252, false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_sanity() {
+        let game_ini = GameIni::parse(
+            b"[Game]\r\nLibrary=RGSS104E.dll\r\nScripts=Data\\Scripts.rxdata\r\nTitle=Test\r\nRTP1=Standard\r\n",
+            None,
+        );
+        assert!(game_ini.library.as_deref() == Some("RGSS104E.dll"));
+        assert!(game_ini.title.as_deref() == Some("Test"));
+        assert!(game_ini.guess_game_kind() == Some(GameKind::Xp));
+        assert!(game_ini.scripts_path_components() == Some(vec!["Data", "Scripts.rxdata"]));
+
+        let game_ini = GameIni::parse(b"[Game]\r\nLibrary=System\\RGSS301.dll\r\n", None);
+        assert!(game_ini.guess_game_kind() == Some(GameKind::VxAce));
+
+        let game_ini = GameIni::parse(b"[Other]\r\nLibrary=RGSS202E.dll\r\n", None);
+        assert!(game_ini.guess_game_kind().is_none());
+    }
+
+    #[test]
+    fn parse_legacy_encoding() {
+        // "テスト" in Shift-JIS
+        let game_ini = GameIni::parse(b"[Game]\r\nTitle=\x83\x65\x83\x58\x83\x67\r\n", None);
+        assert!(game_ini.title.as_deref() == Some("テスト"));
+        assert!(game_ini.encoding == encoding_rs::SHIFT_JIS);
+        assert!(game_ini.encoding_is_ambiguous);
+
+        // "Café" in Windows-1252
+        let game_ini = GameIni::parse(b"[Game]\r\nTitle=Caf\xE9\r\n", None);
+        assert!(game_ini.title.as_deref() == Some("Café"));
+        assert!(game_ini.encoding == encoding_rs::WINDOWS_1252);
+        assert!(!game_ini.encoding_is_ambiguous);
+    }
+
+    #[test]
+    fn parse_explicit_encoding() {
+        // "Cafés" in Windows-1252, which is also valid Shift-JIS.
+        let bytes = b"[Game]\r\nTitle=Caf\xE9s\r\n";
+
+        let game_ini = GameIni::parse(bytes, None);
+        assert!(game_ini.encoding == encoding_rs::SHIFT_JIS);
+        assert!(game_ini.encoding_is_ambiguous);
+
+        let game_ini = GameIni::parse(bytes, Some(encoding_rs::WINDOWS_1252));
+        assert!(game_ini.title.as_deref() == Some("Cafés"));
+        assert!(game_ini.encoding == encoding_rs::WINDOWS_1252);
+        assert!(!game_ini.encoding_is_ambiguous);
+
+        // Files that are not valid in the provided encoding fall back to Windows-1252.
+        let game_ini = GameIni::parse(b"[Game]\r\nTitle=Caf\xE9\r\n", Some(encoding_rs::UTF_8));
+        assert!(game_ini.title.as_deref() == Some("Café"));
+        assert!(game_ini.encoding == encoding_rs::WINDOWS_1252);
+    }
+}
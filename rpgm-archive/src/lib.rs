@@ -0,0 +1,97 @@
+//! Read and write RPG Maker XP, VX, and VX Ace games and archives.
+//!
+//! Use [`FileEntryIter`] to open a game dir or archive and read its files,
+//! and [`FileSink`] to write files to a dir or archive.
+//! The kind of a game can be detected with [`detect_game_kind`],
+//! and the resources of its exe can be read with the exe functions, like [`read_version_info`].
+
+mod detect;
+pub(crate) mod exe;
+mod file_entry_iter;
+mod file_sink;
+pub mod game_ini;
+mod recover;
+pub(crate) mod rgss3a;
+pub(crate) mod rgssad;
+
+pub use self::detect::detect_game_kind;
+pub use self::exe::code_page_name;
+pub use self::exe::format_version;
+pub use self::exe::guess_game_kind_from_exe;
+pub use self::exe::language_name;
+pub use self::exe::parse_version_info;
+pub use self::exe::read_all_resources;
+pub use self::exe::read_manifest;
+pub use self::exe::read_resources;
+pub use self::exe::read_version_info;
+pub use self::exe::Assembly;
+pub use self::exe::AssemblyIdentity;
+pub use self::exe::Description;
+pub use self::exe::FixedFileInfo;
+pub use self::exe::Resource;
+pub use self::exe::ResourceId;
+pub use self::exe::StringFileInfo;
+pub use self::exe::StringStruct;
+pub use self::exe::StringTable;
+pub use self::exe::Translation;
+pub use self::exe::VarFileInfo;
+pub use self::exe::VersionInfo;
+pub use self::file_entry_iter::FileEntry;
+pub use self::file_entry_iter::FileEntryIter;
+pub use self::file_sink::FileSink;
+pub use self::recover::recover_key;
+use anyhow::bail;
+use std::str::FromStr;
+
+/// The game kind
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum GameKind {
+    /// RPG Maker XP, which uses rgssad archives.
+    Xp,
+
+    /// RPG Maker VX, which uses rgss2a archives.
+    Vx,
+
+    /// RPG Maker VX Ace, which uses rgss3a archives.
+    VxAce,
+}
+
+impl GameKind {
+    /// Returns true if this is xp.
+    pub fn is_xp(self) -> bool {
+        matches!(self, Self::Xp)
+    }
+
+    /// Returns true if this is vx.
+    pub fn is_vx(self) -> bool {
+        matches!(self, Self::Vx)
+    }
+
+    /// Returns true if this is vx ace.
+    pub fn is_vx_ace(self) -> bool {
+        matches!(self, Self::VxAce)
+    }
+}
+
+impl FromStr for GameKind {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        if input.eq_ignore_ascii_case("xp") || input.eq_ignore_ascii_case("rgssad") {
+            return Ok(Self::Xp);
+        }
+
+        if input.eq_ignore_ascii_case("vx") || input.eq_ignore_ascii_case("rgss2a") {
+            return Ok(Self::Vx);
+        }
+
+        if input.eq_ignore_ascii_case("vx-ace")
+            || input.eq_ignore_ascii_case("rgss3a")
+            || input.eq_ignore_ascii_case("ace")
+        {
+            return Ok(Self::VxAce);
+        }
+
+        bail!("\"{input}\" is not a valid game kind");
+    }
+}
@@ -0,0 +1,34 @@
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+
+/// Recover the key of an archive that may use a non-standard key.
+///
+/// For rgss3a archives, this is the base key.
+///
+/// # Returns
+/// Returns `None` if the key could not be recovered.
+pub fn recover_key<R>(mut reader: R) -> anyhow::Result<Option<u32>>
+where
+    R: Read + Seek,
+{
+    reader.seek(SeekFrom::Start(0))?;
+
+    let mut magic = [0; 8];
+    reader
+        .read_exact(&mut magic)
+        .context("failed to read magic")?;
+    ensure!(
+        &magic[..crate::rgssad::MAGIC.len()] == crate::rgssad::MAGIC,
+        "invalid magic"
+    );
+
+    match magic[crate::rgssad::MAGIC.len()] {
+        crate::rgssad::VERSION => crate::rgssad::recover_key(reader),
+        crate::rgss3a::VERSION => crate::rgss3a::recover_base_key(reader),
+        version => bail!("unsupported archive version {version}"),
+    }
+}
@@ -0,0 +1,145 @@
+mod reader;
+mod recover;
+mod writer;
+
+pub use self::reader::File;
+pub use self::reader::Reader;
+pub use self::recover::recover_base_key;
+pub use self::writer::Writer;
+
+/// The archive version for rgss3a files.
+pub const VERSION: u8 = 3;
+
+/// The base key used when writing archives, if one is not provided.
+///
+/// Stock tools pick a random base key, but a fixed one keeps output reproducible.
+pub const DEFAULT_BASE_KEY: u32 = 0xDEADCAFE;
+
+/// Derive the archive key from the base key stored in the header.
+pub fn derive_key(base_key: u32) -> u32 {
+    base_key.wrapping_mul(9).wrapping_add(3)
+}
+
+/// Encrypt or decrypt a file name in-place.
+pub fn crypt_name(key: u32, name: &mut [u8]) {
+    let key_bytes = key.to_le_bytes();
+    for (i, byte) in name.iter_mut().enumerate() {
+        *byte ^= key_bytes[i % 4];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write_archive(files: &[(&str, &[u8])], base_key: u32) -> Vec<u8> {
+        let mut archive = Vec::new();
+        let mut writer = Writer::new(&mut archive, std::io::Cursor::new(Vec::new()), base_key);
+        for (name, data) in files {
+            writer
+                .write_file(name, *data)
+                .expect("failed to write file");
+        }
+        writer.finish().expect("failed to finish archive");
+        archive
+    }
+
+    #[test]
+    fn unfinished_file_is_left_out() {
+        use std::io::Cursor;
+        use std::io::Read;
+        use std::io::Write;
+
+        let mut archive = Vec::new();
+        let mut writer = Writer::new(&mut archive, Cursor::new(Vec::new()), 0x12345678);
+        writer
+            .write_file("Data\\System.rvdata2", &b"system"[..])
+            .expect("failed to write file");
+        {
+            let mut file = writer.start_file("Data\\Broken.rvdata2").unwrap();
+            file.write_all(b"this data should not be in the archive")
+                .unwrap();
+        }
+        writer
+            .write_file("Data\\Map001.rvdata2", &b"map"[..])
+            .expect("failed to write file");
+        writer.finish().expect("failed to finish archive");
+
+        let expected = write_archive(
+            &[
+                ("Data\\System.rvdata2", b"system"),
+                ("Data\\Map001.rvdata2", b"map"),
+            ],
+            0x12345678,
+        );
+        // The keys of files after the unfinished file differ, so only compare the file data.
+        assert!(archive.len() == expected.len());
+
+        let mut reader = Reader::new(Cursor::new(archive), None);
+        reader.read_header().expect("failed to read header");
+        for (name, data) in [
+            ("Data\\System.rvdata2", &b"system"[..]),
+            ("Data\\Map001.rvdata2", &b"map"[..]),
+        ] {
+            let mut file = reader
+                .read_file()
+                .expect("failed to read file")
+                .expect("missing file");
+            assert!(file.name() == name);
+
+            let mut actual = Vec::new();
+            file.read_to_end(&mut actual).expect("failed to read data");
+            assert!(actual == data);
+        }
+        assert!(reader.read_file().expect("failed to read file").is_none());
+    }
+
+    #[test]
+    fn round_trip() {
+        use std::io::Cursor;
+        use std::io::Read;
+
+        let files: [(&str, &[u8]); 3] = [
+            ("Data\\Map001.rvdata2", b"map data"),
+            ("Graphics\\Pictures\\empty.png", b""),
+            ("Data\\Scripts.rvdata2", &[0xFF; 37]),
+        ];
+
+        let archive = write_archive(&files, 0x12345678);
+
+        let mut reader = Reader::new(Cursor::new(archive), None);
+        reader.read_header().expect("failed to read header");
+        for (name, data) in files {
+            let mut file = reader
+                .read_file()
+                .expect("failed to read file")
+                .expect("missing file");
+            assert!(file.name() == name);
+
+            let mut actual = Vec::new();
+            file.read_to_end(&mut actual).expect("failed to read data");
+            assert!(actual == data);
+        }
+        assert!(reader.read_file().expect("failed to read file").is_none());
+    }
+
+    #[test]
+    fn recover_base_key_sanity() {
+        use std::io::Cursor;
+
+        let files: [(&str, &[u8]); 2] = [
+            ("Graphics\\Pictures\\empty.png", b""),
+            ("Data\\Scripts.rvdata2", &[0xFF; 37]),
+        ];
+
+        for base_key in [0, 0x12345678, 0xFFFFFFFF] {
+            let mut archive = write_archive(&files, base_key);
+            // Scramble the header key to make sure it is not used.
+            archive[8..12].copy_from_slice(&[0xAA; 4]);
+
+            let recovered =
+                recover_base_key(Cursor::new(archive)).expect("failed to recover base key");
+            assert!(recovered == Some(base_key));
+        }
+    }
+}
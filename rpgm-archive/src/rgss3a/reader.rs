@@ -0,0 +1,189 @@
+use super::crypt_name;
+use super::derive_key;
+use super::VERSION;
+use crate::rgssad::FileCipher;
+use crate::rgssad::MAGIC;
+use crate::rgssad::MAX_FILE_NAME_LEN;
+use anyhow::ensure;
+use anyhow::Context;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+
+/// An entry in the rgss3a file table.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// The file name
+    pub name: String,
+
+    /// The absolute offset of the file data in the archive.
+    pub offset: u32,
+
+    /// The size of the file data.
+    pub size: u32,
+
+    /// The key used to decrypt the file data.
+    pub key: u32,
+}
+
+/// A reader for rgss3a archives.
+///
+/// Unlike v1 archives, the file table is stored at the start of the archive,
+/// so the entire table is read when the header is read.
+#[derive(Debug)]
+pub struct Reader<R> {
+    reader: R,
+    base_key: Option<u32>,
+    entries: Vec<Entry>,
+    position: usize,
+}
+
+impl<R> Reader<R>
+where
+    R: Read + Seek,
+{
+    /// Create a new reader.
+    ///
+    /// If a base key is provided, it is used instead of the base key stored in the header.
+    pub fn new(reader: R, base_key: Option<u32>) -> Self {
+        Self {
+            reader,
+            base_key,
+            entries: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Read the header and file table.
+    ///
+    /// This must be called before reading any files.
+    pub fn read_header(&mut self) -> anyhow::Result<()> {
+        let mut magic = [0; 8];
+        self.reader
+            .read_exact(&mut magic)
+            .context("failed to read magic")?;
+        ensure!(&magic[..MAGIC.len()] == MAGIC, "invalid magic");
+
+        let version = magic[MAGIC.len()];
+        ensure!(
+            version == VERSION,
+            "unsupported archive version {version}, expected {VERSION}"
+        );
+
+        let header_base_key = read_u32(&mut self.reader).context("failed to read key")?;
+        let key = derive_key(self.base_key.unwrap_or(header_base_key));
+
+        self.entries.clear();
+        self.position = 0;
+        loop {
+            let offset = read_u32(&mut self.reader).context("failed to read file offset")? ^ key;
+            if offset == 0 {
+                // The table is terminated by an entry with a zeroed offset.
+                // The rest of the terminating entry is garbage.
+                break;
+            }
+
+            let size = read_u32(&mut self.reader).context("failed to read file size")? ^ key;
+            let file_key = read_u32(&mut self.reader).context("failed to read file key")? ^ key;
+            let name_len =
+                read_u32(&mut self.reader).context("failed to read file name len")? ^ key;
+            ensure!(
+                name_len <= MAX_FILE_NAME_LEN,
+                "file name len {name_len} is too large"
+            );
+
+            let mut name = vec![0; usize::try_from(name_len)?];
+            self.reader
+                .read_exact(&mut name)
+                .context("failed to read file name")?;
+            crypt_name(key, &mut name);
+            let name = String::from_utf8(name).context("file name is not utf8")?;
+
+            self.entries.push(Entry {
+                name,
+                offset,
+                size,
+                key: file_key,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Go back to the first file.
+    pub fn rewind(&mut self) {
+        self.position = 0;
+    }
+
+    /// Read the next file.
+    ///
+    /// Files are returned in file table order.
+    pub fn read_file(&mut self) -> anyhow::Result<Option<File<'_, R>>> {
+        let entry = match self.entries.get(self.position) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        self.position += 1;
+
+        self.reader
+            .seek(SeekFrom::Start(entry.offset.into()))
+            .with_context(|| format!("failed to seek to \"{}\"", entry.name))?;
+
+        Ok(Some(File {
+            entry,
+            reader: (&mut self.reader).take(entry.size.into()),
+            cipher: FileCipher::new(entry.key),
+        }))
+    }
+}
+
+/// A file in an rgss3a archive.
+#[derive(Debug)]
+pub struct File<'a, R> {
+    entry: &'a Entry,
+    reader: std::io::Take<&'a mut R>,
+    cipher: FileCipher,
+}
+
+impl<R> File<'_, R> {
+    /// Get the file name.
+    pub fn name(&self) -> &str {
+        &self.entry.name
+    }
+
+    /// Get the file size.
+    pub fn size(&self) -> u32 {
+        self.entry.size
+    }
+
+    /// Get the offset of the file data in the archive.
+    pub fn offset(&self) -> u32 {
+        self.entry.offset
+    }
+}
+
+impl<R> Read for File<'_, R>
+where
+    R: Read,
+{
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.reader.read(buffer)?;
+        if n == 0 && !buffer.is_empty() && self.reader.limit() != 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "archive file data is truncated",
+            ));
+        }
+        self.cipher.apply(&mut buffer[..n]);
+        Ok(n)
+    }
+}
+
+fn read_u32<R>(reader: &mut R) -> std::io::Result<u32>
+where
+    R: Read,
+{
+    let mut buffer = [0; 4];
+    reader.read_exact(&mut buffer)?;
+    Ok(u32::from_le_bytes(buffer))
+}
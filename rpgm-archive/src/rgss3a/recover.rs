@@ -0,0 +1,81 @@
+use super::Reader;
+use super::VERSION;
+use crate::rgssad::KNOWN_NAME_PREFIXES;
+use crate::rgssad::MAGIC;
+use anyhow::ensure;
+use anyhow::Context;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+
+/// The size of the header and the fixed part of the first file table entry.
+const FIRST_NAME_OFFSET: usize = 8 + 4 + (4 * 4);
+
+/// The multiplicative inverse of 9, mod 2^32.
+const NINE_INVERSE: u32 = 0x38E38E39;
+
+/// Recover the base key of an rgss3a archive.
+///
+/// The first file name is used as known plaintext to find candidate keys.
+/// Each candidate is then validated by decoding the file table.
+/// Returns `None` if no key could be found.
+pub fn recover_base_key<R>(mut reader: R) -> anyhow::Result<Option<u32>>
+where
+    R: Read + Seek,
+{
+    let archive_len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    let mut header = [0; FIRST_NAME_OFFSET + 4];
+    reader
+        .read_exact(&mut header)
+        .context("failed to read header")?;
+    ensure!(&header[..MAGIC.len()] == MAGIC, "invalid magic");
+    let version = header[MAGIC.len()];
+    ensure!(
+        version == VERSION,
+        "unsupported archive version {version}, expected {VERSION}"
+    );
+    let name_start: [u8; 4] = header[FIRST_NAME_OFFSET..].try_into()?;
+
+    // Names are xored with the key 4 bytes at a time,
+    // so the first 4 bytes of a known prefix give the whole key.
+    for prefix in KNOWN_NAME_PREFIXES {
+        let prefix: [u8; 4] = prefix[..4].try_into()?;
+        let key = u32::from_le_bytes(name_start) ^ u32::from_le_bytes(prefix);
+        let base_key = key.wrapping_sub(3).wrapping_mul(NINE_INVERSE);
+
+        if validate_base_key(&mut reader, archive_len, base_key)? {
+            return Ok(Some(base_key));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Check if a base key can decode the file table.
+fn validate_base_key<R>(reader: &mut R, archive_len: u64, base_key: u32) -> anyhow::Result<bool>
+where
+    R: Read + Seek,
+{
+    reader.seek(SeekFrom::Start(0))?;
+    let mut reader = Reader::new(reader, Some(base_key));
+    if reader.read_header().is_err() {
+        return Ok(false);
+    }
+
+    let mut has_files = false;
+    while let Some(file) = reader.read_file()? {
+        if file.name().is_empty() || file.name().chars().any(|c| c.is_control()) {
+            return Ok(false);
+        }
+
+        if u64::from(file.offset()) + u64::from(file.size()) > archive_len {
+            return Ok(false);
+        }
+
+        has_files = true;
+    }
+
+    Ok(has_files)
+}
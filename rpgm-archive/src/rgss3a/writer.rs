@@ -0,0 +1,237 @@
+use super::crypt_name;
+use super::derive_key;
+use super::VERSION;
+use crate::rgssad::rotate_key;
+use crate::rgssad::FileCipher;
+use crate::rgssad::MAGIC;
+use crate::rgssad::MAX_FILE_NAME_LEN;
+use anyhow::ensure;
+use anyhow::Context;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+
+/// The size of the header, including the key.
+const HEADER_SIZE: u64 = 8 + 4;
+
+/// The size of a file table entry, excluding the name.
+const ENTRY_SIZE: u64 = 4 * 4;
+
+#[derive(Debug)]
+struct PendingEntry {
+    /// The encrypted name.
+    name: Vec<u8>,
+    offset: u32,
+    size: u32,
+    key: u32,
+}
+
+/// A writer for rgss3a archives.
+///
+/// Since the file table is stored before any file data,
+/// file data is buffered in a spill file until the archive is finished.
+#[derive(Debug)]
+pub struct Writer<W, S> {
+    writer: W,
+    spill: S,
+    base_key: u32,
+    key: u32,
+    next_file_key: u32,
+    data_size: u64,
+    entries: Vec<PendingEntry>,
+}
+
+impl<W, S> Writer<W, S>
+where
+    W: Write,
+    S: Read + Write + Seek,
+{
+    /// Create a new writer.
+    ///
+    /// The spill is used to hold file data until the archive is finished.
+    /// It should be empty.
+    pub fn new(writer: W, spill: S, base_key: u32) -> Self {
+        let key = derive_key(base_key);
+
+        Self {
+            writer,
+            spill,
+            base_key,
+            key,
+            next_file_key: key,
+            data_size: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Start writing a file.
+    ///
+    /// The file data is encrypted and written to the spill.
+    pub fn start_file(&mut self, name: &str) -> anyhow::Result<FileWriter<'_, W, S>> {
+        ensure!(
+            u32::try_from(name.len()).is_ok_and(|name_len| name_len <= MAX_FILE_NAME_LEN),
+            "file name \"{name}\" is too long"
+        );
+
+        // Data from a file that was never finished is past the end of the data,
+        // so overwrite it.
+        self.spill.seek(SeekFrom::Start(self.data_size))?;
+
+        let key = self.next_file_key;
+        self.next_file_key = rotate_key(self.next_file_key);
+
+        Ok(FileWriter {
+            name: name.into(),
+            key,
+            size: 0,
+            cipher: FileCipher::new(key),
+            buffer: Vec::new(),
+            writer: self,
+        })
+    }
+
+    /// Write a file from a reader.
+    pub fn write_file<R>(&mut self, name: &str, mut reader: R) -> anyhow::Result<()>
+    where
+        R: Read,
+    {
+        let mut file = self.start_file(name)?;
+        std::io::copy(&mut reader, &mut file)
+            .with_context(|| format!("failed to write file \"{name}\""))?;
+        file.finish()?;
+
+        Ok(())
+    }
+
+    /// Write the header, the file table, and the buffered file data.
+    ///
+    /// This must be called after all files are written.
+    pub fn finish(&mut self) -> anyhow::Result<()> {
+        let mut data_start = HEADER_SIZE + ENTRY_SIZE;
+        for entry in self.entries.iter() {
+            data_start += ENTRY_SIZE + u64::try_from(entry.name.len())?;
+        }
+        ensure!(
+            data_start + self.data_size <= u64::from(u32::MAX),
+            "archive is too large for the archive format, the max size is {} bytes",
+            u32::MAX
+        );
+        let data_start = u32::try_from(data_start)?;
+
+        self.writer.write_all(MAGIC)?;
+        self.writer.write_all(&[VERSION])?;
+        self.writer.write_all(&self.base_key.to_le_bytes())?;
+
+        for entry in self.entries.iter() {
+            let offset = data_start + entry.offset;
+            let name_len = u32::try_from(entry.name.len())?;
+
+            self.writer.write_all(&(offset ^ self.key).to_le_bytes())?;
+            self.writer
+                .write_all(&(entry.size ^ self.key).to_le_bytes())?;
+            self.writer
+                .write_all(&(entry.key ^ self.key).to_le_bytes())?;
+            self.writer
+                .write_all(&(name_len ^ self.key).to_le_bytes())?;
+            self.writer.write_all(&entry.name)?;
+        }
+
+        // The table is terminated by an entry with a zeroed offset.
+        for _ in 0..4 {
+            self.writer.write_all(&self.key.to_le_bytes())?;
+        }
+
+        self.spill.flush()?;
+        self.spill.seek(SeekFrom::Start(0))?;
+        let copied = std::io::copy(
+            &mut (&mut self.spill).take(self.data_size),
+            &mut self.writer,
+        )?;
+        ensure!(copied == self.data_size, "spill file is truncated");
+
+        self.writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Get the inner writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+}
+
+/// A writer for a file in an rgss3a archive.
+///
+/// [`FileWriter::finish`] must be called after all data is written,
+/// or the file will be left out of the archive.
+/// The data of an unfinished file is overwritten by the next file,
+/// and is never copied into the archive.
+#[derive(Debug)]
+pub struct FileWriter<'a, W, S> {
+    writer: &'a mut Writer<W, S>,
+    name: String,
+    key: u32,
+    size: u32,
+    cipher: FileCipher,
+    buffer: Vec<u8>,
+}
+
+impl<W, S> FileWriter<'_, W, S> {
+    /// Finish writing the file, adding it to the file table.
+    pub fn finish(self) -> anyhow::Result<()> {
+        // Offsets are relative to the data section until the table size is known.
+        let offset = u32::try_from(self.writer.data_size).with_context(|| {
+            format!(
+                "archive is too large for the archive format, the max size is {} bytes",
+                u32::MAX
+            )
+        })?;
+        self.writer.data_size += u64::from(self.size);
+
+        let mut name = self.name.into_bytes();
+        crypt_name(self.writer.key, &mut name);
+
+        self.writer.entries.push(PendingEntry {
+            name,
+            offset,
+            size: self.size,
+            key: self.key,
+        });
+
+        Ok(())
+    }
+}
+
+impl<W, S> Write for FileWriter<'_, W, S>
+where
+    S: Write,
+{
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        let new_size = u32::try_from(buffer.len())
+            .ok()
+            .and_then(|len| self.size.checked_add(len))
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "file \"{}\" is too large for the archive format, the max size is {} bytes",
+                        self.name,
+                        u32::MAX
+                    ),
+                )
+            })?;
+
+        self.buffer.clear();
+        self.buffer.extend(buffer);
+        self.cipher.apply(&mut self.buffer);
+        self.writer.spill.write_all(&self.buffer)?;
+        self.size = new_size;
+
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.spill.flush()
+    }
+}
@@ -0,0 +1,149 @@
+mod reader;
+mod recover;
+mod writer;
+
+pub use self::reader::File;
+pub use self::reader::Reader;
+pub use self::recover::recover_key;
+pub use self::writer::Writer;
+
+/// The magic number of an archive, without the version.
+///
+/// This is shared between all archive versions.
+pub const MAGIC: &[u8] = b"RGSSAD\0";
+
+/// The archive version for rgssad and rgss2a files.
+pub const VERSION: u8 = 1;
+
+/// The key used by stock RGSS players for rgssad and rgss2a files.
+pub const DEFAULT_KEY: u32 = 0xDEADCAFE;
+
+/// The max file name length that will be accepted.
+///
+/// This is used to prevent a hostile archive from making us allocate a huge name buffer.
+pub const MAX_FILE_NAME_LEN: u32 = 4096;
+
+/// File name prefixes that nearly every archive starts with.
+///
+/// These are used as known plaintext when recovering keys.
+pub const KNOWN_NAME_PREFIXES: &[&[u8]] = &[b"Data\\", b"Graphics\\"];
+
+/// Rotate a key.
+///
+/// This is done after every 4 bytes of file data,
+/// as well as after every header value in rgssad files.
+pub fn rotate_key(key: u32) -> u32 {
+    key.wrapping_mul(7).wrapping_add(3)
+}
+
+/// The state of a file data cipher.
+///
+/// File data is xored with a rolling key, 4 bytes at a time.
+/// This tracks the position within the current key so that data can be processed in arbitrary chunks.
+#[derive(Debug, Copy, Clone)]
+pub struct FileCipher {
+    key: u32,
+    position: u8,
+}
+
+impl FileCipher {
+    /// Create a new cipher from a file key.
+    pub fn new(key: u32) -> Self {
+        Self { key, position: 0 }
+    }
+
+    /// Encrypt or decrypt the given data in-place.
+    pub fn apply(&mut self, data: &mut [u8]) {
+        for byte in data.iter_mut() {
+            *byte ^= self.key.to_le_bytes()[usize::from(self.position)];
+
+            self.position += 1;
+            if self.position == 4 {
+                self.position = 0;
+                self.key = rotate_key(self.key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+    use std::io::Read;
+
+    #[test]
+    fn file_cipher_chunking() {
+        let data: Vec<u8> = (0..=255).collect();
+
+        let mut expected = data.clone();
+        FileCipher::new(DEFAULT_KEY).apply(&mut expected);
+
+        let mut actual = data.clone();
+        let mut cipher = FileCipher::new(DEFAULT_KEY);
+        for chunk in actual.chunks_mut(3) {
+            cipher.apply(chunk);
+        }
+        assert!(expected == actual);
+
+        FileCipher::new(DEFAULT_KEY).apply(&mut actual);
+        assert!(data == actual);
+    }
+
+    #[test]
+    fn round_trip() {
+        let files: [(&str, &[u8]); 3] = [
+            ("Data\\Map001.rxdata", b"map data"),
+            ("Graphics\\Pictures\\empty.png", b""),
+            ("Data\\Scripts.rxdata", &[0xFF; 37]),
+        ];
+
+        for key in [DEFAULT_KEY, 0x12345678] {
+            let mut archive = Cursor::new(Vec::new());
+            let mut writer = Writer::new(&mut archive, key);
+            writer.write_header().expect("failed to write header");
+            for (name, data) in files {
+                writer.write_file(name, data).expect("failed to write file");
+            }
+
+            let mut reader = Reader::new(Cursor::new(archive.into_inner()), key);
+            reader.read_header().expect("failed to read header");
+            // Skip the first file without reading it.
+            let _ = reader.read_file().expect("failed to read file");
+            for (name, data) in files.iter().skip(1) {
+                let mut file = reader
+                    .read_file()
+                    .expect("failed to read file")
+                    .expect("missing file");
+                assert!(file.name() == *name);
+
+                let mut actual = Vec::new();
+                file.read_to_end(&mut actual).expect("failed to read data");
+                assert!(actual == *data);
+            }
+            assert!(reader.read_file().expect("failed to read file").is_none());
+        }
+    }
+
+    #[test]
+    fn recover_key_sanity() {
+        let files: [(&str, &[u8]); 3] = [
+            ("Data\\Actors.rxdata", b"actors"),
+            ("Graphics\\Pictures\\empty.png", b""),
+            ("Data\\Scripts.rxdata", &[0xFF; 37]),
+        ];
+
+        for key in [DEFAULT_KEY, 0x12345678, 0] {
+            let mut archive = Cursor::new(Vec::new());
+            let mut writer = Writer::new(&mut archive, key);
+            writer.write_header().expect("failed to write header");
+            for (name, data) in files {
+                writer.write_file(name, data).expect("failed to write file");
+            }
+
+            let recovered =
+                recover_key(Cursor::new(archive.into_inner())).expect("failed to recover key");
+            assert!(recovered == Some(key));
+        }
+    }
+}
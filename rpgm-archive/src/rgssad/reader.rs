@@ -0,0 +1,180 @@
+use super::rotate_key;
+use super::FileCipher;
+use super::MAGIC;
+use super::MAX_FILE_NAME_LEN;
+use super::VERSION;
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+
+/// A reader for rgssad and rgss2a archives.
+#[derive(Debug)]
+pub struct Reader<R> {
+    reader: R,
+    key: u32,
+
+    /// The key at the start of the archive.
+    start_key: u32,
+
+    /// The offset of the next file header.
+    position: u64,
+}
+
+impl<R> Reader<R>
+where
+    R: Read + Seek,
+{
+    /// Create a new reader with the given starting key.
+    ///
+    /// Most archives use [`DEFAULT_KEY`](super::DEFAULT_KEY).
+    pub fn new(reader: R, key: u32) -> Self {
+        Self {
+            reader,
+            key,
+            start_key: key,
+            position: 0,
+        }
+    }
+
+    /// Read the header.
+    ///
+    /// This must be called before reading any files.
+    pub fn read_header(&mut self) -> anyhow::Result<()> {
+        let mut magic = [0; 8];
+        self.reader
+            .read_exact(&mut magic)
+            .context("failed to read magic")?;
+        ensure!(&magic[..MAGIC.len()] == MAGIC, "invalid magic");
+
+        let version = magic[MAGIC.len()];
+        ensure!(
+            version == VERSION,
+            "unsupported archive version {version}, expected {VERSION}"
+        );
+
+        self.position = u64::try_from(magic.len())?;
+
+        Ok(())
+    }
+
+    /// Go back to the first file.
+    ///
+    /// The header must have been read already.
+    pub fn rewind(&mut self) {
+        self.key = self.start_key;
+        // The header is the magic followed by the version.
+        self.position = u64::try_from(MAGIC.len()).unwrap() + 1;
+    }
+
+    /// Read the next file.
+    ///
+    /// Any unread data from the previous file is skipped.
+    pub fn read_file(&mut self) -> anyhow::Result<Option<File<'_, R>>> {
+        self.reader.seek(SeekFrom::Start(self.position))?;
+
+        let name_len = match read_u32_or_eof(&mut self.reader)? {
+            Some(name_len) => name_len ^ self.key,
+            None => return Ok(None),
+        };
+        self.key = rotate_key(self.key);
+        ensure!(
+            name_len <= MAX_FILE_NAME_LEN,
+            "file name len {name_len} is too large"
+        );
+
+        let mut name = vec![0; usize::try_from(name_len)?];
+        self.reader
+            .read_exact(&mut name)
+            .context("failed to read file name")?;
+        for byte in name.iter_mut() {
+            *byte ^= self.key.to_le_bytes()[0];
+            self.key = rotate_key(self.key);
+        }
+        let name = String::from_utf8(name).context("file name is not utf8")?;
+
+        let size = read_u32_or_eof(&mut self.reader)?.context("failed to read file size")?;
+        let size = size ^ self.key;
+        self.key = rotate_key(self.key);
+
+        let offset = self.position + 4 + u64::from(name_len) + 4;
+        self.position = offset + u64::from(size);
+
+        Ok(Some(File {
+            name,
+            offset,
+            size,
+            reader: (&mut self.reader).take(size.into()),
+            cipher: FileCipher::new(self.key),
+        }))
+    }
+}
+
+/// A file in an rgssad or rgss2a archive.
+#[derive(Debug)]
+pub struct File<'a, R> {
+    name: String,
+    offset: u64,
+    size: u32,
+    reader: std::io::Take<&'a mut R>,
+    cipher: FileCipher,
+}
+
+impl<R> File<'_, R> {
+    /// Get the file name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the file size.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Get the offset of the file data in the archive.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+impl<R> Read for File<'_, R>
+where
+    R: Read,
+{
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.reader.read(buffer)?;
+        if n == 0 && !buffer.is_empty() && self.reader.limit() != 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "archive file data is truncated",
+            ));
+        }
+        self.cipher.apply(&mut buffer[..n]);
+        Ok(n)
+    }
+}
+
+/// Read a u32, returning `None` if the reader is at EOF.
+fn read_u32_or_eof<R>(reader: &mut R) -> anyhow::Result<Option<u32>>
+where
+    R: Read,
+{
+    let mut buffer = [0; 4];
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    match filled {
+        0 => Ok(None),
+        4 => Ok(Some(u32::from_le_bytes(buffer))),
+        _ => bail!("unexpected EOF"),
+    }
+}
@@ -0,0 +1,110 @@
+use super::rotate_key;
+use super::Reader;
+use super::KNOWN_NAME_PREFIXES;
+use super::MAGIC;
+use super::MAX_FILE_NAME_LEN;
+use super::VERSION;
+use anyhow::ensure;
+use anyhow::Context;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+
+/// The number of files to decode when validating a candidate key.
+const VALIDATION_FILE_COUNT: usize = 16;
+
+/// Recover the starting key of an rgssad or rgss2a archive.
+///
+/// The first file name is used as known plaintext to find candidate keys.
+/// Each candidate is then validated by decoding the following file headers.
+/// Returns `None` if no key could be found.
+pub fn recover_key<R>(mut reader: R) -> anyhow::Result<Option<u32>>
+where
+    R: Read + Seek,
+{
+    let archive_len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    let mut header = [0; 8 + 4];
+    reader
+        .read_exact(&mut header)
+        .context("failed to read header")?;
+    ensure!(&header[..MAGIC.len()] == MAGIC, "invalid magic");
+    let version = header[MAGIC.len()];
+    ensure!(
+        version == VERSION,
+        "unsupported archive version {version}, expected {VERSION}"
+    );
+    let encrypted_name_len = u32::from_le_bytes(header[8..].try_into()?);
+
+    let max_prefix_len = KNOWN_NAME_PREFIXES
+        .iter()
+        .map(|prefix| prefix.len())
+        .max()
+        .unwrap_or(0);
+    let mut name_start = Vec::with_capacity(max_prefix_len);
+    (&mut reader)
+        .take(u64::try_from(max_prefix_len)?)
+        .read_to_end(&mut name_start)?;
+
+    // The name len is small, so the upper bits of the key are leaked by the encrypted name len.
+    // The name itself constrains the lower bits.
+    for name_len in 1..=MAX_FILE_NAME_LEN {
+        let key = encrypted_name_len ^ name_len;
+        if !matches_known_prefix(key, name_len, &name_start) {
+            continue;
+        }
+
+        if validate_key(&mut reader, archive_len, key)? {
+            return Ok(Some(key));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Check if the start of the first file name decrypts to a known prefix.
+fn matches_known_prefix(key: u32, name_len: u32, name_start: &[u8]) -> bool {
+    KNOWN_NAME_PREFIXES.iter().any(|prefix| {
+        if u32::try_from(prefix.len()).map_or(true, |prefix_len| prefix_len > name_len)
+            || prefix.len() > name_start.len()
+        {
+            return false;
+        }
+
+        let mut key = rotate_key(key);
+        prefix.iter().zip(name_start).all(|(expected, byte)| {
+            let byte = byte ^ key.to_le_bytes()[0];
+            key = rotate_key(key);
+            byte == *expected
+        })
+    })
+}
+
+/// Check if a key can decode the first few file headers.
+fn validate_key<R>(reader: &mut R, archive_len: u64, key: u32) -> anyhow::Result<bool>
+where
+    R: Read + Seek,
+{
+    reader.seek(SeekFrom::Start(0))?;
+    let mut reader = Reader::new(reader, key);
+    reader.read_header()?;
+
+    for _ in 0..VALIDATION_FILE_COUNT {
+        let file = match reader.read_file() {
+            Ok(Some(file)) => file,
+            Ok(None) => break,
+            Err(_) => return Ok(false),
+        };
+
+        if file.name().is_empty() || file.name().chars().any(|c| c.is_control()) {
+            return Ok(false);
+        }
+
+        if file.offset() + u64::from(file.size()) > archive_len {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
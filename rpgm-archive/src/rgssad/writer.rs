@@ -0,0 +1,159 @@
+use super::rotate_key;
+use super::FileCipher;
+use super::MAGIC;
+use super::MAX_FILE_NAME_LEN;
+use super::VERSION;
+use anyhow::Context;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+
+/// A writer for rgssad and rgss2a archives.
+#[derive(Debug)]
+pub struct Writer<W> {
+    writer: W,
+    key: u32,
+}
+
+impl<W> Writer<W>
+where
+    W: Write + Seek,
+{
+    /// Create a new writer with the given starting key.
+    ///
+    /// Most archives use [`DEFAULT_KEY`](super::DEFAULT_KEY).
+    pub fn new(writer: W, key: u32) -> Self {
+        Self { writer, key }
+    }
+
+    /// Write the header.
+    ///
+    /// This must be called before writing any files.
+    pub fn write_header(&mut self) -> anyhow::Result<()> {
+        self.writer.write_all(MAGIC)?;
+        self.writer.write_all(&[VERSION])?;
+
+        Ok(())
+    }
+
+    /// Start writing a file.
+    ///
+    /// The size is back-patched when the returned writer is finished,
+    /// so the file data is streamed without needing to know its size up front.
+    pub fn start_file(&mut self, name: &str) -> anyhow::Result<FileWriter<'_, W>> {
+        let name_len = u32::try_from(name.len())
+            .ok()
+            .filter(|name_len| *name_len <= MAX_FILE_NAME_LEN)
+            .with_context(|| format!("file name \"{name}\" is too long"))?;
+
+        self.writer
+            .write_all(&(name_len ^ self.key).to_le_bytes())?;
+        self.key = rotate_key(self.key);
+
+        let mut encrypted_name = name.as_bytes().to_vec();
+        for byte in encrypted_name.iter_mut() {
+            *byte ^= self.key.to_le_bytes()[0];
+            self.key = rotate_key(self.key);
+        }
+        self.writer.write_all(&encrypted_name)?;
+
+        // Write a placeholder size, to be replaced when the file is finished.
+        let size_position = self.writer.stream_position()?;
+        let size_key = self.key;
+        self.writer.write_all(&[0; 4])?;
+        self.key = rotate_key(self.key);
+
+        Ok(FileWriter {
+            writer: &mut self.writer,
+            name: name.into(),
+            size_position,
+            size_key,
+            size: 0,
+            cipher: FileCipher::new(self.key),
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Write a file from a reader.
+    pub fn write_file<R>(&mut self, name: &str, mut reader: R) -> anyhow::Result<()>
+    where
+        R: Read,
+    {
+        let mut file = self.start_file(name)?;
+        std::io::copy(&mut reader, &mut file)
+            .with_context(|| format!("failed to write file \"{name}\""))?;
+        file.finish()?;
+
+        Ok(())
+    }
+
+    /// Get the inner writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+}
+
+/// A writer for a file in an rgssad or rgss2a archive.
+///
+/// [`FileWriter::finish`] must be called after all data is written,
+/// or the archive will be corrupt.
+#[derive(Debug)]
+pub struct FileWriter<'a, W> {
+    writer: &'a mut W,
+    name: String,
+    size_position: u64,
+    size_key: u32,
+    size: u32,
+    cipher: FileCipher,
+    buffer: Vec<u8>,
+}
+
+impl<W> FileWriter<'_, W>
+where
+    W: Write + Seek,
+{
+    /// Finish writing the file, filling in its size.
+    pub fn finish(self) -> anyhow::Result<()> {
+        let end_position = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(self.size_position))?;
+        self.writer
+            .write_all(&(self.size ^ self.size_key).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(end_position))?;
+
+        Ok(())
+    }
+}
+
+impl<W> Write for FileWriter<'_, W>
+where
+    W: Write,
+{
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        let new_size = u32::try_from(buffer.len())
+            .ok()
+            .and_then(|len| self.size.checked_add(len))
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "file \"{}\" is too large for the archive format, the max size is {} bytes",
+                        self.name,
+                        u32::MAX
+                    ),
+                )
+            })?;
+
+        self.buffer.clear();
+        self.buffer.extend(buffer);
+        self.cipher.apply(&mut self.buffer);
+        self.writer.write_all(&self.buffer)?;
+        self.size = new_size;
+
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
@@ -14,5 +14,16 @@ pub use self::system_test_battler::*;
 pub use self::table::*;
 pub use self::tone::Tone;
 pub use self::tone::ToneFromValueError;
+pub use self::util::create_ruby_string;
+pub use self::util::decode_string;
+pub use self::util::encode_string;
+pub use self::util::is_byte_string;
+pub use self::util::read_string_encoding_marker;
 pub use self::util::ruby_string2string;
 pub use self::util::string2ruby_string;
+pub use self::util::StringContext;
+pub use self::util::StringContextGuard;
+pub use self::util::StringEncodingError;
+pub use self::util::StringEncodingMarker;
+pub use self::util::BYTE_STRING_PREFIX;
+pub use self::util::MARKED_STRING_PREFIX;
@@ -1,3 +1,5 @@
+use crate::util::decode_string;
+use crate::util::encode_string;
 use flate2::bufread::ZlibDecoder;
 use flate2::bufread::ZlibEncoder;
 use flate2::Compression;
@@ -36,7 +38,7 @@ impl IntoValue for CompressedScriptList {
 }
 
 /// A list of scripts
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct ScriptList {
     /// Scripts
     pub scripts: Vec<Script>,
@@ -60,9 +62,6 @@ impl IntoValue for ScriptList {
 pub enum ScriptFromValueError {
     /// The array len was invalid.
     InvalidArrayLen { len: usize },
-
-    /// The name was invalid
-    InvalidName { error: std::str::Utf8Error },
 }
 
 impl std::fmt::Display for ScriptFromValueError {
@@ -71,21 +70,11 @@ impl std::fmt::Display for ScriptFromValueError {
             Self::InvalidArrayLen { len } => {
                 write!(f, "invalid script array len of {len}, expected {ARRAY_LEN}")
             }
-            Self::InvalidName { .. } => {
-                write!(f, "the script name is invalid")
-            }
         }
     }
 }
 
-impl std::error::Error for ScriptFromValueError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            Self::InvalidName { error } => Some(error),
-            _ => None,
-        }
-    }
-}
+impl std::error::Error for ScriptFromValueError {}
 
 impl From<ScriptFromValueError> for FromValueError {
     fn from(error: ScriptFromValueError) -> Self {
@@ -123,15 +112,14 @@ impl<'a> FromValue<'a> for CompressedScript {
         let id: i32 = ctx.from_value(script[0])?;
 
         let name: &StringValue = ctx.from_value(script[1])?;
-        let name = std::str::from_utf8(name.value())
-            .map_err(|error| ScriptFromValueError::InvalidName { error })?;
+        let name = decode_string(name.value());
 
         let data: &StringValue = ctx.from_value(script[2])?;
         let data = data.value();
 
         Ok(Self {
             id,
-            name: name.into(),
+            name,
             data: data.into(),
         })
     }
@@ -140,7 +128,8 @@ impl<'a> FromValue<'a> for CompressedScript {
 impl IntoValue for CompressedScript {
     fn into_value(self, arena: &mut ValueArena) -> Result<ValueHandle, IntoValueError> {
         let id = self.id.into_value(arena)?;
-        let name = arena.create_string(self.name.into()).into_raw();
+        let name = encode_string(self.name).map_err(IntoValueError::new_other)?;
+        let name = arena.create_string(name).into_raw();
         let data = arena.create_string(self.data).into_raw();
 
         let array = arena.create_array(vec![id, name, data]);
@@ -169,10 +158,11 @@ impl<'a> FromValue<'a> for Script {
         let script: CompressedScript = FromValue::from_value(ctx, value)?;
 
         let mut decoder = ZlibDecoder::new(&*script.data);
-        let mut data = String::new();
+        let mut data = Vec::new();
         decoder
-            .read_to_string(&mut data)
+            .read_to_end(&mut data)
             .map_err(FromValueError::new_other)?;
+        let data = decode_string(&data);
 
         Ok(Self {
             id: script.id,
@@ -185,10 +175,12 @@ impl<'a> FromValue<'a> for Script {
 impl IntoValue for Script {
     fn into_value(self, arena: &mut ValueArena) -> Result<ValueHandle, IntoValueError> {
         let id = self.id.into_value(arena)?;
-        let name = arena.create_string(self.name.into()).into_raw();
+        let name = encode_string(self.name).map_err(IntoValueError::new_other)?;
+        let name = arena.create_string(name).into_raw();
 
+        let raw_data = encode_string(self.data).map_err(IntoValueError::new_other)?;
         let compression = Compression::default();
-        let mut encoder = ZlibEncoder::new(self.data.as_bytes(), compression);
+        let mut encoder = ZlibEncoder::new(&*raw_data, compression);
         let mut data = Vec::new();
         encoder
             .read_to_end(&mut data)
@@ -1,24 +1,696 @@
+use base64::engine::general_purpose::URL_SAFE;
+use base64::Engine;
+use encoding_rs::Encoding;
 use ruby_marshal::FromValue;
 use ruby_marshal::FromValueContext;
 use ruby_marshal::FromValueError;
+use ruby_marshal::IntoValueError;
 use ruby_marshal::StringValue;
+use ruby_marshal::SymbolValue;
 use ruby_marshal::Value;
 use ruby_marshal::ValueArena;
 use ruby_marshal::ValueHandle;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt::Write;
 
+thread_local! {
+    /// The string context entered on this thread, if any.
+    static CURRENT_STRING_CONTEXT: RefCell<Option<StringContext>> = const { RefCell::new(None) };
+}
+
+/// How to convert between ruby strings and rust strings.
+///
+/// The `FromValue` and `IntoValue` impls of this crate cannot take extra arguments,
+/// so a context is entered on a thread with [`StringContext::enter`] before converting values.
+/// Outside of a context, strings are UTF-8 and have no encoding marker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringContext {
+    /// The encoding of strings in ruby data.
+    ///
+    /// XP and VX games store strings in the code page of the machine they were made on,
+    /// like Shift_JIS for Japanese games.
+    pub encoding: &'static Encoding,
+
+    /// The encoding marker that most strings have.
+    ///
+    /// VX Ace games should use [`StringEncodingMarker::Utf8`], like the editor.
+    /// Strings with this marker are converted to plain strings,
+    /// and strings with a different marker are converted to marked strings.
+    /// Strings without a marker are also assumed to be in the encoding of this marker.
+    pub default_marker: StringEncodingMarker,
+}
+
+impl StringContext {
+    /// Create a new string context.
+    pub fn new(encoding: &'static Encoding, default_marker: StringEncodingMarker) -> Self {
+        Self {
+            encoding,
+            default_marker,
+        }
+    }
+
+    /// Get the string context of the current thread.
+    pub fn current() -> Self {
+        CURRENT_STRING_CONTEXT
+            .with_borrow(|string_context| string_context.clone())
+            .unwrap_or_else(|| Self::new(encoding_rs::UTF_8, StringEncodingMarker::None))
+    }
+
+    /// Use this context for conversions on the current thread until the guard is dropped.
+    ///
+    /// Other threads are not affected, so each worker thread needs to enter the context too.
+    pub fn enter(self) -> StringContextGuard {
+        let previous = CURRENT_STRING_CONTEXT.replace(Some(self));
+
+        StringContextGuard {
+            previous,
+            _not_send: std::marker::PhantomData,
+        }
+    }
+}
+
+/// A guard that restores the previous string context of a thread when dropped.
+#[derive(Debug)]
+#[must_use = "the context is exited when the guard is dropped"]
+pub struct StringContextGuard {
+    previous: Option<StringContext>,
+
+    /// The guard must be dropped on the thread that created it.
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl Drop for StringContextGuard {
+    fn drop(&mut self) {
+        CURRENT_STRING_CONTEXT.set(self.previous.take());
+    }
+}
+
+/// The encoding marker of a ruby string.
+///
+/// Ruby 1.9 and later, used by VX Ace, store the encoding of a string in its instance variables.
+/// XP and VX use Ruby 1.8, which has no string encodings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StringEncodingMarker {
+    /// The string has no marker.
+    None,
+
+    /// The "E" instance variable is true.
+    Utf8,
+
+    /// The "E" instance variable is false.
+    UsAscii,
+
+    /// The "encoding" instance variable names the encoding.
+    ///
+    /// The name is kept as it is, so that it is written back exactly,
+    /// even if it is an alias like "Windows-31J" or an encoding that cannot be decoded.
+    Named(Vec<u8>),
+}
+
+impl StringEncodingMarker {
+    /// Get the encoding that this marker implies.
+    ///
+    /// # Returns
+    /// Returns `None` if there is no marker, or if the named encoding is unknown.
+    pub fn encoding(&self) -> Option<&'static Encoding> {
+        match self {
+            Self::None => None,
+            Self::Utf8 | Self::UsAscii => Some(encoding_rs::UTF_8),
+            Self::Named(name) => Encoding::for_label(name),
+        }
+    }
+
+    /// Get the label of this marker in a marked string, like "E=false".
+    ///
+    /// Bytes of encoding names that are not letters, digits, "-", "_", or "." are percent-escaped.
+    fn label(&self) -> Cow<'static, str> {
+        match self {
+            Self::None => "none".into(),
+            Self::Utf8 => "E=true".into(),
+            Self::UsAscii => "E=false".into(),
+            Self::Named(name) => {
+                let mut label = String::from("encoding=");
+                for byte in name.iter().copied() {
+                    if byte.is_ascii_alphanumeric() || b"-_.".contains(&byte) {
+                        label.push(char::from(byte));
+                    } else {
+                        write!(label, "%{byte:02X}").unwrap();
+                    }
+                }
+                label.into()
+            }
+        }
+    }
+
+    /// Parse the label of a marker in a marked string.
+    fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "none" => Some(Self::None),
+            "E=true" => Some(Self::Utf8),
+            "E=false" => Some(Self::UsAscii),
+            _ => {
+                let mut rest = label.strip_prefix("encoding=")?.as_bytes();
+                let mut name = Vec::with_capacity(rest.len());
+                while let Some((byte, next)) = rest.split_first() {
+                    if *byte == b'%' {
+                        let hex = next.get(..2)?;
+                        let hex = std::str::from_utf8(hex).ok()?;
+                        name.push(u8::from_str_radix(hex, 16).ok()?);
+                        rest = &next[2..];
+                    } else {
+                        name.push(*byte);
+                        rest = next;
+                    }
+                }
+                Some(Self::Named(name))
+            }
+        }
+    }
+}
+
+/// Get the encoding of strings that have no marker of their own.
+fn default_string_encoding() -> &'static Encoding {
+    let string_context = StringContext::current();
+    string_context
+        .default_marker
+        .encoding()
+        .unwrap_or(string_context.encoding)
+}
+
+/// The prefix of strings that have a different encoding marker than the default marker.
+///
+/// The prefix is followed by the marker, like "E=false", "none", or "encoding=Shift_JIS",
+/// then a ":" and the string.
+/// See [`StringContext::default_marker`].
+pub const MARKED_STRING_PREFIX: &str = "\u{FFFF}marker:";
+
+/// Split a marked string into its marker and string.
+///
+/// Strings that are not marked are returned as they are, without a marker.
+fn split_marked_string(
+    string: String,
+) -> Result<(Option<StringEncodingMarker>, String), StringEncodingError> {
+    let Some(rest) = string.strip_prefix(MARKED_STRING_PREFIX) else {
+        return Ok((None, string));
+    };
+
+    let parsed = rest
+        .split_once(':')
+        .and_then(|(label, rest)| Some((StringEncodingMarker::from_label(label)?, rest)));
+    match parsed {
+        Some((marker, rest)) => Ok((Some(marker), rest.to_string())),
+        None => Err(StringEncodingError::InvalidMarkedString { string }),
+    }
+}
+
+/// Check if a string starts with a prefix that has a special meaning.
+fn has_string_prefix(string: &str) -> bool {
+    string.starts_with(BYTE_STRING_PREFIX) || string.starts_with(MARKED_STRING_PREFIX)
+}
+
+/// The prefix of strings that hold the raw bytes of a ruby string, as url-safe base64.
+///
+/// Ruby strings that are not valid in the string encoding are kept in this form,
+/// so that they round-trip exactly.
+/// The prefix starts with a noncharacter, so normal text should never start with it.
+pub const BYTE_STRING_PREFIX: &str = "\u{FFFF}bytes:";
+
+/// An error converting a string to the string encoding.
+#[derive(Debug)]
+pub enum StringEncodingError {
+    /// The string has chars that the encoding cannot represent.
+    Encode {
+        encoding: &'static Encoding,
+        string: String,
+    },
+
+    /// The string is a byte string, but the bytes are not valid base64.
+    InvalidByteString {
+        string: String,
+        error: base64::DecodeError,
+    },
+
+    /// The string is a marked string, but the marker is not valid.
+    InvalidMarkedString { string: String },
+
+    /// The string is not a byte string, but its marker names an encoding that cannot be encoded.
+    UnknownEncoding { name: Vec<u8>, string: String },
+}
+
+impl std::fmt::Display for StringEncodingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Encode { encoding, string } => {
+                write!(f, "\"{string}\" cannot be encoded as {}", encoding.name())
+            }
+            Self::InvalidByteString { string, .. } => {
+                write!(f, "\"{string}\" is not a valid byte string")
+            }
+            Self::InvalidMarkedString { string } => {
+                write!(f, "\"{string}\" is not a valid marked string")
+            }
+            Self::UnknownEncoding { name, string } => {
+                write!(
+                    f,
+                    "\"{string}\" cannot be encoded as unknown encoding \"{}\", so it must be a byte string",
+                    name.escape_ascii()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for StringEncodingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidByteString { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Check if a string holds the raw bytes of a ruby string.
+///
+/// Marked strings are byte strings if the string after the marker is one.
+pub fn is_byte_string(string: &str) -> bool {
+    let string = string
+        .strip_prefix(MARKED_STRING_PREFIX)
+        .and_then(|rest| rest.split_once(':'))
+        .map_or(string, |(_label, string)| string);
+
+    string.starts_with(BYTE_STRING_PREFIX)
+}
+
+/// Decode the bytes of a ruby string with the encoding of the current [`StringContext`].
+///
+/// If the bytes are not valid in the encoding, a byte string is returned instead.
+pub fn decode_string(bytes: &[u8]) -> String {
+    decode_string_with_encoding(bytes, default_string_encoding())
+}
+
+fn decode_string_with_encoding(bytes: &[u8], encoding: &'static Encoding) -> String {
+    match encoding.decode_without_bom_handling_and_without_replacement(bytes) {
+        // Text that looks like a byte string or marked string is kept as a byte string,
+        // so that it round-trips.
+        Some(string) if !has_string_prefix(&string) => string.into_owned(),
+        _ => encode_byte_string(bytes),
+    }
+}
+
+fn encode_byte_string(bytes: &[u8]) -> String {
+    format!("{BYTE_STRING_PREFIX}{}", URL_SAFE.encode(bytes))
+}
+
+/// Encode a string into the bytes of a ruby string with the encoding of the current [`StringContext`].
+///
+/// Byte strings are converted back into their raw bytes.
+pub fn encode_string(string: String) -> Result<Vec<u8>, StringEncodingError> {
+    encode_string_with_encoding(string, default_string_encoding())
+}
+
+fn encode_string_with_encoding(
+    string: String,
+    encoding: &'static Encoding,
+) -> Result<Vec<u8>, StringEncodingError> {
+    if let Some(data) = string.strip_prefix(BYTE_STRING_PREFIX) {
+        return URL_SAFE
+            .decode(data)
+            .map_err(|error| StringEncodingError::InvalidByteString {
+                string: string.clone(),
+                error,
+            });
+    }
+
+    let (bytes, _, had_errors) = encoding.encode(&string);
+    if had_errors {
+        return Err(StringEncodingError::Encode { encoding, string });
+    }
+
+    let bytes = match bytes {
+        Cow::Borrowed(_) => string.into_bytes(),
+        Cow::Owned(bytes) => bytes,
+    };
+
+    Ok(bytes)
+}
+
+/// Read the encoding marker of a ruby string.
+pub fn read_string_encoding_marker(
+    ctx: &FromValueContext,
+    value: &StringValue,
+) -> Result<StringEncodingMarker, FromValueError> {
+    let Some(instance_variables) = value.instance_variables() else {
+        return Ok(StringEncodingMarker::None);
+    };
+
+    for (key, value) in instance_variables.iter().copied() {
+        let key: &SymbolValue = ctx.from_value(key.into())?;
+        match key.value() {
+            b"E" => {
+                let value: bool = ctx.from_value(value)?;
+                let marker = if value {
+                    StringEncodingMarker::Utf8
+                } else {
+                    StringEncodingMarker::UsAscii
+                };
+
+                return Ok(marker);
+            }
+            b"encoding" => {
+                let name: &StringValue = ctx.from_value(value)?;
+
+                return Ok(StringEncodingMarker::Named(name.value().into()));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(StringEncodingMarker::None)
+}
+
+/// Create a ruby string with an encoding marker.
+pub fn create_ruby_string(
+    arena: &mut ValueArena,
+    value: Vec<u8>,
+    marker: &StringEncodingMarker,
+) -> ValueHandle {
+    let instance_variable = match marker {
+        StringEncodingMarker::None => None,
+        StringEncodingMarker::Utf8 | StringEncodingMarker::UsAscii => {
+            let key = arena.create_symbol("E".into());
+            let value = arena.create_bool(*marker == StringEncodingMarker::Utf8);
+            Some((key, value.into()))
+        }
+        StringEncodingMarker::Named(name) => {
+            let key = arena.create_symbol("encoding".into());
+            let value = arena.create_string(name.clone());
+            Some((key, value.into()))
+        }
+    };
+
+    let handle = arena.create_string(value).into_raw();
+    if let Some(instance_variable) = instance_variable {
+        if let Some(Value::String(value)) = arena.get_mut(handle) {
+            value.set_instance_variables(Some(vec![instance_variable]));
+        }
+    }
+
+    handle
+}
+
+/// Convert a ruby string into a string.
+///
+/// Strings with a different encoding marker than the default marker become marked strings,
+/// see [`MARKED_STRING_PREFIX`].
+/// Strings marked with an unknown encoding become byte strings.
 pub fn ruby_string2string(ctx: &FromValueContext, value: &Value) -> Result<String, FromValueError> {
     let value: &StringValue = FromValue::from_value(ctx, value)?;
-    let value = value.value();
-    let value = std::str::from_utf8(value)
-        .map_err(FromValueError::new_other)?
-        .to_string();
+    let marker = read_string_encoding_marker(ctx, value)?;
+    let string = match (&marker, marker.encoding()) {
+        (StringEncodingMarker::None, _) => decode_string(value.value()),
+        (_, Some(encoding)) => decode_string_with_encoding(value.value(), encoding),
+        (_, None) => encode_byte_string(value.value()),
+    };
 
-    Ok(value)
+    if marker == StringContext::current().default_marker {
+        return Ok(string);
+    }
+
+    Ok(format!("{MARKED_STRING_PREFIX}{}:{string}", marker.label()))
 }
 
+/// Convert a string into a ruby string.
+///
+/// Marked strings get their own encoding marker, and other strings get the default marker.
 pub fn string2ruby_string(
     s: String,
     arena: &mut ValueArena,
-) -> Result<ValueHandle, ruby_marshal::IntoValueError> {
-    Ok(arena.create_string(s.into()).into())
+) -> Result<ValueHandle, IntoValueError> {
+    let (marker, s) = split_marked_string(s).map_err(IntoValueError::new_other)?;
+    let (marker, value) = match marker {
+        Some(StringEncodingMarker::None) => (StringEncodingMarker::None, encode_string(s)),
+        Some(marker) => {
+            let encoding = marker.encoding();
+            let value = match &marker {
+                StringEncodingMarker::Named(name)
+                    if encoding.is_none() && !s.starts_with(BYTE_STRING_PREFIX) =>
+                {
+                    Err(StringEncodingError::UnknownEncoding {
+                        name: name.clone(),
+                        string: s,
+                    })
+                }
+                // Byte strings do not use the encoding.
+                _ => encode_string_with_encoding(s, encoding.unwrap_or(encoding_rs::UTF_8)),
+            };
+            (marker, value)
+        }
+        None => (StringContext::current().default_marker, encode_string(s)),
+    };
+    let value = value.map_err(IntoValueError::new_other)?;
+
+    Ok(create_ruby_string(arena, value, &marker))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn string_context_scopes() {
+        assert!(StringContext::current().encoding == encoding_rs::UTF_8);
+
+        {
+            let _outer =
+                StringContext::new(encoding_rs::SHIFT_JIS, StringEncodingMarker::None).enter();
+            assert!(StringContext::current().encoding == encoding_rs::SHIFT_JIS);
+            assert!(decode_string(b"\x83\x65") == "テ");
+
+            {
+                let _inner =
+                    StringContext::new(encoding_rs::WINDOWS_1252, StringEncodingMarker::None)
+                        .enter();
+                assert!(decode_string(b"\xE9") == "é");
+            }
+            assert!(StringContext::current().encoding == encoding_rs::SHIFT_JIS);
+
+            // Other threads do not see the context.
+            let encoding = std::thread::spawn(|| StringContext::current().encoding)
+                .join()
+                .unwrap();
+            assert!(encoding == encoding_rs::UTF_8);
+        }
+
+        assert!(StringContext::current().encoding == encoding_rs::UTF_8);
+    }
+
+    #[test]
+    fn byte_string_round_trip() {
+        let _string_context =
+            StringContext::new(encoding_rs::SHIFT_JIS, StringEncodingMarker::None).enter();
+
+        let text = b"\x83\x65\x83\x58\x83\x67";
+        let string = decode_string(text);
+        assert!(string == "テスト");
+        assert!(encode_string(string).unwrap() == text);
+
+        // A trailing lead byte is not valid Shift_JIS.
+        let bytes = b"Potion \x82";
+        let string = decode_string(bytes);
+        assert!(is_byte_string(&string));
+        assert!(string == format!("{BYTE_STRING_PREFIX}{}", URL_SAFE.encode(bytes)));
+        assert!(encode_string(string).unwrap() == bytes);
+    }
+
+    #[test]
+    fn prefixed_text_round_trip() {
+        // Text that starts with the prefix must not be mistaken for a byte string when compiling.
+        let text = format!("{BYTE_STRING_PREFIX}AAAA");
+        let string = decode_string(text.as_bytes());
+        assert!(string != text);
+        assert!(is_byte_string(&string));
+        assert!(encode_string(string).unwrap() == text.as_bytes());
+
+        let text = format!("{BYTE_STRING_PREFIX}not base64!");
+        let string = decode_string(text.as_bytes());
+        assert!(encode_string(string).unwrap() == text.as_bytes());
+    }
+
+    #[test]
+    fn invalid_byte_string() {
+        let error = encode_string(format!("{BYTE_STRING_PREFIX}!!")).unwrap_err();
+        assert!(matches!(
+            error,
+            StringEncodingError::InvalidByteString { .. }
+        ));
+    }
+
+    #[test]
+    fn unencodable_string() {
+        let _string_context =
+            StringContext::new(encoding_rs::SHIFT_JIS, StringEncodingMarker::None).enter();
+
+        let error = encode_string("😀".to_string()).unwrap_err();
+        assert!(matches!(
+            error,
+            StringEncodingError::Encode { encoding, .. } if encoding == encoding_rs::SHIFT_JIS
+        ));
+    }
+
+    #[test]
+    fn marked_string_round_trip() {
+        let _string_context =
+            StringContext::new(encoding_rs::UTF_8, StringEncodingMarker::Utf8).enter();
+
+        // ["a", "b", "c", "d"], with the markers E true, E false, Shift_JIS, and none.
+        let data = b"\x04\x08[\x09\
+            I\"\x06a\x06:\x06ET\
+            I\"\x06b\x06;\x00F\
+            I\"\x06c\x06:\x0dencoding\"\x0eShift_JIS\
+            \"\x06d";
+        let markers = [
+            StringEncodingMarker::Utf8,
+            StringEncodingMarker::UsAscii,
+            StringEncodingMarker::Named(b"Shift_JIS".to_vec()),
+            StringEncodingMarker::None,
+        ];
+
+        let arena = ruby_marshal::load(&data[..]).expect("failed to load");
+        let ctx = FromValueContext::new(&arena);
+        let array: &ruby_marshal::ArrayValue = ctx.from_value(arena.root()).unwrap();
+        let strings: Vec<String> = array
+            .value()
+            .iter()
+            .map(|handle| ruby_string2string(&ctx, arena.get(*handle).unwrap()).unwrap())
+            .collect();
+        assert!(
+            strings
+                == [
+                    "a".to_string(),
+                    format!("{MARKED_STRING_PREFIX}E=false:b"),
+                    format!("{MARKED_STRING_PREFIX}encoding=Shift_JIS:c"),
+                    format!("{MARKED_STRING_PREFIX}none:d"),
+                ]
+        );
+
+        let mut arena = ValueArena::new();
+        let handles = strings
+            .into_iter()
+            .map(|string| string2ruby_string(string, &mut arena).unwrap())
+            .collect();
+        let root: ValueHandle = arena.create_array(handles).into();
+        arena.replace_root(root);
+        let mut new_data = Vec::new();
+        ruby_marshal::dump(&mut new_data, &arena).expect("failed to dump");
+
+        let arena = ruby_marshal::load(&*new_data).expect("failed to load again");
+        let ctx = FromValueContext::new(&arena);
+        let array: &ruby_marshal::ArrayValue = ctx.from_value(arena.root()).unwrap();
+        assert!(array.value().len() == markers.len());
+        for ((handle, marker), text) in array
+            .value()
+            .iter()
+            .zip(markers.clone())
+            .zip(["a", "b", "c", "d"])
+        {
+            let value: &StringValue = ctx.from_value(*handle).unwrap();
+            assert!(value.value() == text.as_bytes());
+            assert!(read_string_encoding_marker(&ctx, value).unwrap() == marker);
+        }
+    }
+
+    #[test]
+    fn named_marker_round_trip() {
+        let _string_context =
+            StringContext::new(encoding_rs::UTF_8, StringEncodingMarker::Utf8).enter();
+
+        // ["テ", "\x80"], with the markers Windows-31J and IBM437.
+        let data = b"\x04\x08[\x07\
+            I\"\x07\x83\x65\x06:\x0dencoding\"\x10Windows-31J\
+            I\"\x06\x80\x06;\x00\"\x0bIBM437";
+        let markers = [
+            StringEncodingMarker::Named(b"Windows-31J".to_vec()),
+            StringEncodingMarker::Named(b"IBM437".to_vec()),
+        ];
+
+        let arena = ruby_marshal::load(&data[..]).expect("failed to load");
+        let ctx = FromValueContext::new(&arena);
+        let array: &ruby_marshal::ArrayValue = ctx.from_value(arena.root()).unwrap();
+        let strings: Vec<String> = array
+            .value()
+            .iter()
+            .map(|handle| ruby_string2string(&ctx, arena.get(*handle).unwrap()).unwrap())
+            .collect();
+        // encoding_rs has no decoder for IBM437, so the string is kept as bytes.
+        assert!(
+            strings
+                == [
+                    format!("{MARKED_STRING_PREFIX}encoding=Windows-31J:テ"),
+                    format!("{MARKED_STRING_PREFIX}encoding=IBM437:{BYTE_STRING_PREFIX}gA=="),
+                ]
+        );
+
+        let mut arena = ValueArena::new();
+        let handles = strings
+            .into_iter()
+            .map(|string| string2ruby_string(string, &mut arena).unwrap())
+            .collect();
+        let root: ValueHandle = arena.create_array(handles).into();
+        arena.replace_root(root);
+        let mut new_data = Vec::new();
+        ruby_marshal::dump(&mut new_data, &arena).expect("failed to dump");
+
+        let arena = ruby_marshal::load(&*new_data).expect("failed to load again");
+        let ctx = FromValueContext::new(&arena);
+        let array: &ruby_marshal::ArrayValue = ctx.from_value(arena.root()).unwrap();
+        assert!(array.value().len() == markers.len());
+        for ((handle, marker), bytes) in array
+            .value()
+            .iter()
+            .zip(markers)
+            .zip([&b"\x83\x65"[..], &b"\x80"[..]])
+        {
+            let value: &StringValue = ctx.from_value(*handle).unwrap();
+            assert!(value.value() == bytes);
+            assert!(read_string_encoding_marker(&ctx, value).unwrap() == marker);
+        }
+
+        // Text cannot be encoded without a decoder.
+        let mut arena = ValueArena::new();
+        let text = format!("{MARKED_STRING_PREFIX}encoding=IBM437:text");
+        assert!(string2ruby_string(text, &mut arena).is_err());
+    }
+
+    #[test]
+    fn named_marker_labels() {
+        let marker = StringEncodingMarker::Named(b"a:b%\xFF".to_vec());
+        let label = marker.label();
+        assert!(label == "encoding=a%3Ab%25%FF");
+        assert!(StringEncodingMarker::from_label(&label) == Some(marker));
+
+        assert!(StringEncodingMarker::from_label("encoding=%4").is_none());
+        assert!(StringEncodingMarker::from_label("encoding=%zz").is_none());
+    }
+
+    #[test]
+    fn marked_string_prefixes() {
+        let marked = format!("{MARKED_STRING_PREFIX}E=false:{BYTE_STRING_PREFIX}gQ==");
+        assert!(is_byte_string(&marked));
+
+        let (marker, string) = split_marked_string(marked).unwrap();
+        assert!(marker == Some(StringEncodingMarker::UsAscii));
+        assert!(encode_string(string).unwrap() == b"\x81");
+
+        let (marker, string) = split_marked_string("plain".to_string()).unwrap();
+        assert!(marker.is_none() && string == "plain");
+
+        let invalid = format!("{MARKED_STRING_PREFIX}E=maybe:text");
+        assert!(split_marked_string(invalid).is_err());
+
+        // Text that looks like a marked string is kept as a byte string.
+        let text = format!("{MARKED_STRING_PREFIX}none:text");
+        let string = decode_string(text.as_bytes());
+        assert!(is_byte_string(&string));
+        assert!(encode_string(string).unwrap() == text.as_bytes());
+    }
 }
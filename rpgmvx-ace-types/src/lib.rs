@@ -11,3 +11,7 @@ pub use rpgm_common_types::Table;
 pub use rpgmvx_types::Bgm;
 pub use rpgmvx_types::Bgs;
 pub use rpgmvx_types::Event;
+
+/// The encoding marker that the editor adds to strings.
+pub const STRING_ENCODING_MARKER: rpgm_common_types::StringEncodingMarker =
+    rpgm_common_types::StringEncodingMarker::Utf8;
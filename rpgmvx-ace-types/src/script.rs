@@ -1,6 +1,10 @@
 use flate2::Compression;
 use flate2::bufread::ZlibDecoder;
 use flate2::bufread::ZlibEncoder;
+use rpgm_common_types::decode_string;
+use rpgm_common_types::encode_string;
+use rpgm_common_types::ruby_string2string;
+use rpgm_common_types::string2ruby_string;
 use ruby_marshal::ArrayValue;
 use ruby_marshal::FromValue;
 use ruby_marshal::FromValueContext;
@@ -37,7 +41,7 @@ impl IntoValue for CompressedScriptList {
 }
 
 /// A list of scripts
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct ScriptList {
     /// Scripts
     pub scripts: Vec<Script>,
@@ -61,9 +65,6 @@ impl IntoValue for ScriptList {
 pub enum ScriptFromValueError {
     /// The array len was invalid.
     InvalidArrayLen { len: usize },
-
-    /// The name was invalid
-    InvalidName { error: std::str::Utf8Error },
 }
 
 impl std::fmt::Display for ScriptFromValueError {
@@ -72,21 +73,11 @@ impl std::fmt::Display for ScriptFromValueError {
             Self::InvalidArrayLen { len } => {
                 write!(f, "invalid script array len of {len}, expected {ARRAY_LEN}")
             }
-            Self::InvalidName { .. } => {
-                write!(f, "the script name is invalid")
-            }
         }
     }
 }
 
-impl std::error::Error for ScriptFromValueError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            Self::InvalidName { error } => Some(error),
-            _ => None,
-        }
-    }
-}
+impl std::error::Error for ScriptFromValueError {}
 
 impl From<ScriptFromValueError> for FromValueError {
     fn from(error: ScriptFromValueError) -> Self {
@@ -138,16 +129,15 @@ impl<'a> FromValue<'a> for CompressedScript {
             }
         };
 
-        let name: &StringValue = ctx.from_value(script[1])?;
-        let name = std::str::from_utf8(name.value())
-            .map_err(|error| ScriptFromValueError::InvalidName { error })?;
+        let name: &Value = ctx.from_value(script[1])?;
+        let name = ruby_string2string(ctx, name)?;
 
         let data: &StringValue = ctx.from_value(script[2])?;
         let data = data.value();
 
         Ok(Self {
             id,
-            name: name.into(),
+            name,
             data: data.into(),
         })
     }
@@ -158,7 +148,7 @@ impl IntoValue for CompressedScript {
         let id = arena
             .create_string(itoa::Buffer::new().format(self.id).into())
             .into();
-        let name = arena.create_string(self.name.into()).into_raw();
+        let name = string2ruby_string(self.name, arena)?;
         let data = arena.create_string(self.data).into_raw();
         let array = arena.create_array(vec![id, name, data]);
 
@@ -186,10 +176,11 @@ impl<'a> FromValue<'a> for Script {
         let script: CompressedScript = FromValue::from_value(ctx, value)?;
 
         let mut decoder = ZlibDecoder::new(&*script.data);
-        let mut data = String::new();
+        let mut data = Vec::new();
         decoder
-            .read_to_string(&mut data)
+            .read_to_end(&mut data)
             .map_err(FromValueError::new_other)?;
+        let data = decode_string(&data);
 
         Ok(Self {
             id: script.id,
@@ -204,10 +195,11 @@ impl IntoValue for Script {
         let id = arena
             .create_string(itoa::Buffer::new().format(self.id).into())
             .into();
-        let name = arena.create_string(self.name.into()).into_raw();
+        let name = string2ruby_string(self.name, arena)?;
 
+        let raw_data = encode_string(self.data).map_err(IntoValueError::new_other)?;
         let compression = Compression::default();
-        let mut encoder = ZlibEncoder::new(self.data.as_bytes(), compression);
+        let mut encoder = ZlibEncoder::new(&*raw_data, compression);
         let mut data = Vec::new();
         encoder
             .read_to_end(&mut data)
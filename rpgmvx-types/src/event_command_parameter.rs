@@ -1,3 +1,5 @@
+use crate::util::ruby_string2string;
+use crate::util::string2ruby_string;
 use crate::Bgm;
 use crate::Bgs;
 use crate::Color;
@@ -12,7 +14,6 @@ use ruby_marshal::FromValueContext;
 use ruby_marshal::FromValueError;
 use ruby_marshal::IntoValue;
 use ruby_marshal::IntoValueError;
-use ruby_marshal::StringValue;
 use ruby_marshal::SymbolValue;
 use ruby_marshal::Value;
 use ruby_marshal::ValueArena;
@@ -90,11 +91,8 @@ pub enum EventCommandParameter {
 impl<'a> FromValue<'a> for EventCommandParameter {
     fn from_value(ctx: &FromValueContext<'a>, value: &Value) -> Result<Self, FromValueError> {
         match value {
-            Value::String(value) => {
-                let value = value.value();
-                let value = std::str::from_utf8(value)
-                    .map_err(FromValueError::new_other)?
-                    .to_string();
+            Value::String(_) => {
+                let value = ruby_string2string(ctx, value)?;
                 Ok(Self::String(value))
             }
             Value::Fixnum(value) => {
@@ -120,10 +118,8 @@ impl<'a> FromValue<'a> for EventCommandParameter {
                     ValueKind::String => {
                         let mut new_value = Vec::with_capacity(value.len());
                         for value in value.iter().copied() {
-                            let value: &StringValue = ctx.from_value(value)?;
-                            let value = std::str::from_utf8(value.value())
-                                .map_err(FromValueError::new_other)?
-                                .to_string();
+                            let value: &Value = ctx.from_value(value)?;
+                            let value = ruby_string2string(ctx, value)?;
                             new_value.push(value);
                         }
 
@@ -200,15 +196,15 @@ impl<'a> FromValue<'a> for EventCommandParameter {
 impl IntoValue for EventCommandParameter {
     fn into_value(self, arena: &mut ValueArena) -> Result<ValueHandle, IntoValueError> {
         match self {
-            Self::String(value) => Ok(arena.create_string(value.into()).into()),
+            Self::String(value) => string2ruby_string(value, arena),
             Self::Int(value) => value.into_value(arena),
             Self::Bool(value) => value.into_value(arena),
             Self::Nil => Ok(arena.create_nil().into()),
             Self::StringArray(value) => {
-                let values: Vec<_> = value
+                let values = value
                     .into_iter()
-                    .map(|value| arena.create_string(value.into()).into())
-                    .collect();
+                    .map(|value| string2ruby_string(value, arena))
+                    .collect::<Result<_, _>>()?;
                 Ok(arena.create_array(values).into())
             }
             Self::MoveRoute(value) => value.into_value(arena),
@@ -222,3 +218,34 @@ impl IntoValue for EventCommandParameter {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rpgm_common_types::StringContext;
+    use rpgm_common_types::StringEncodingMarker;
+    use rpgm_common_types::MARKED_STRING_PREFIX;
+
+    #[test]
+    fn string_marker_round_trip() {
+        // VX Ace reuses these types, so strings keep markers that differ from the default.
+        let _string_context =
+            StringContext::new(encoding_rs::UTF_8, StringEncodingMarker::Utf8).enter();
+
+        // "b", with the marker E false.
+        let data = b"\x04\x08I\"\x06b\x06:\x06EF";
+
+        let arena = ruby_marshal::load(&data[..]).expect("failed to load");
+        let ctx = FromValueContext::new(&arena);
+        let parameter: EventCommandParameter = ctx.from_value(arena.root()).unwrap();
+        let expected = format!("{MARKED_STRING_PREFIX}E=false:b");
+        assert!(matches!(&parameter, EventCommandParameter::String(value) if *value == expected));
+
+        let mut arena = ValueArena::new();
+        let root = parameter.into_value(&mut arena).unwrap();
+        arena.replace_root(root);
+        let mut new_data = Vec::new();
+        ruby_marshal::dump(&mut new_data, &arena).expect("failed to dump");
+        assert!(new_data == data);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::util::ruby_string2string;
+use crate::util::string2ruby_string;
 use crate::Bgm;
 use crate::Se;
 use ruby_marshal::FromValue;
@@ -63,11 +65,8 @@ impl<'a> FromValue<'a> for MoveCommandParameter {
                     _ => Err(FromValueError::UnexpectedObjectName { name: name.into() }),
                 }
             }
-            Value::String(value) => {
-                let value = value.value();
-                let value = std::str::from_utf8(value)
-                    .map_err(FromValueError::new_other)?
-                    .to_string();
+            Value::String(_) => {
+                let value = ruby_string2string(ctx, value)?;
                 Ok(Self::String(value))
             }
             _ => Err(FromValueError::new_other(
@@ -82,7 +81,7 @@ impl IntoValue for MoveCommandParameter {
         match self {
             Self::Int(value) => value.into_value(arena),
             Self::Se(value) => value.into_value(arena),
-            Self::String(value) => Ok(arena.create_string(value.into()).into()),
+            Self::String(value) => string2ruby_string(value, arena),
             Self::Bgm(value) => value.into_value(arena),
         }
     }
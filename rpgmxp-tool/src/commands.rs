@@ -1,2 +1,10 @@
 pub mod compile_assets;
+pub mod diff;
+pub mod exe_info;
 pub mod extract_assets;
+pub mod extract_icon;
+pub mod list;
+pub mod patch_archive;
+pub mod patch_exe;
+pub mod recover_key;
+pub mod verify;
@@ -1,16 +1,22 @@
-mod file_sink;
+mod cache;
+mod dedup;
+pub mod layout;
 mod vx;
 mod vx_ace;
 mod xp;
 
-use self::file_sink::FileSink;
+pub use self::cache::Cache;
+pub use self::layout::Layout;
+use crate::lossless::Original;
 use crate::util::ArrayLikeElement;
-use crate::GameKind;
 use anyhow::bail;
 use anyhow::ensure;
 use anyhow::Context;
+use encoding_rs::Encoding;
+use rpgm_archive::FileSink;
+use rpgm_archive::GameKind;
+use rpgm_common_types::CompressedScript;
 use rpgm_common_types::Script;
-use rpgm_common_types::ScriptList;
 use ruby_marshal::IntoValue;
 use std::collections::BTreeMap;
 use std::path::Component as PathComponent;
@@ -50,24 +56,28 @@ impl FromStr for Format {
     }
 }
 
-fn generate_ruby_data<T>(path: &Path) -> anyhow::Result<Vec<u8>>
+fn generate_ruby_data<T>(path: &Path, cache: &mut Cache) -> anyhow::Result<Vec<u8>>
 where
     T: serde::de::DeserializeOwned + ruby_marshal::IntoValue,
 {
-    let map = std::fs::read_to_string(path)?;
-    let map: T = serde_json::from_str(&map)?;
+    cache.get_or_generate(std::any::type_name::<T>(), path, || {
+        let map = std::fs::read_to_string(path)?;
+        let map: T = serde_json::from_str(&map)?;
 
-    let mut arena = ruby_marshal::ValueArena::new();
-    let handle = map.into_value(&mut arena)?;
-    arena.replace_root(handle);
+        let mut arena = ruby_marshal::ValueArena::new();
+        let handle = map.into_value(&mut arena)?;
+        arena.replace_root(handle);
 
-    let mut data = Vec::new();
-    ruby_marshal::dump(&mut data, &arena)?;
+        let mut data = Vec::new();
+        ruby_marshal::dump(&mut data, &arena)?;
 
-    Ok(data)
+        let data = self::dedup::dedup(&data).context("failed to dedup ruby data")?;
+
+        Ok(data)
+    })
 }
 
-fn generate_scripts_data(path: &Path) -> anyhow::Result<Vec<u8>> {
+fn generate_scripts_data(path: &Path, original: Option<&Original>) -> anyhow::Result<Vec<u8>> {
     let mut scripts_map = BTreeMap::new();
 
     for dir_entry in path.read_dir()? {
@@ -108,13 +118,28 @@ fn generate_scripts_data(path: &Path) -> anyhow::Result<Vec<u8>> {
         }
     }
 
-    // TODO: Consider enforcing that script index ranges cannot have holes and must start at 0.
-    let script_list = ScriptList {
-        scripts: scripts_map.into_values().collect(),
-    };
+    // Reuse the original compressed data of unchanged scripts,
+    // since we cannot reproduce the original zlib settings.
+    let original_scripts = original.map_or(&[][..], |original| original.scripts.as_slice());
 
+    // TODO: Consider enforcing that script index ranges cannot have holes and must start at 0.
     let mut arena = ruby_marshal::ValueArena::new();
-    let handle = script_list.into_value(&mut arena)?;
+    let mut handles = Vec::with_capacity(scripts_map.len());
+    for (script_index, script) in scripts_map {
+        let handle = match original_scripts.get(script_index) {
+            Some(original_script) if original_script.matches(&script.name, &script.data) => {
+                CompressedScript {
+                    id: original_script.id,
+                    name: script.name,
+                    data: original_script.compressed_data()?,
+                }
+                .into_value(&mut arena)?
+            }
+            _ => script.into_value(&mut arena)?,
+        };
+        handles.push(handle);
+    }
+    let handle: ruby_marshal::ValueHandle = arena.create_array(handles).into();
     arena.replace_root(handle);
 
     let mut data = Vec::new();
@@ -123,7 +148,7 @@ fn generate_scripts_data(path: &Path) -> anyhow::Result<Vec<u8>> {
     Ok(data)
 }
 
-fn generate_arraylike_rx_data<T>(path: &Path) -> anyhow::Result<Vec<u8>>
+fn generate_arraylike_rx_data<T>(path: &Path, cache: &mut Cache) -> anyhow::Result<Vec<u8>>
 where
     T: for<'a> ArrayLikeElement<'a>,
 {
@@ -156,34 +181,38 @@ where
         Ok((index, json))
     }
 
-    let type_display_name = T::type_display_name();
-    let mut map: BTreeMap<usize, T> = BTreeMap::new();
+    cache.get_or_generate(std::any::type_name::<Vec<Option<T>>>(), path, || {
+        let type_display_name = T::type_display_name();
+        let mut map: BTreeMap<usize, T> = BTreeMap::new();
 
-    for dir_entry in path.read_dir()? {
-        let (index, json) = load_json_str(dir_entry, type_display_name)?;
-        let value: T = serde_json::from_str(&json)?;
+        for dir_entry in path.read_dir()? {
+            let (index, json) = load_json_str(dir_entry, type_display_name)?;
+            let value: T = serde_json::from_str(&json)?;
 
-        let old_entry = map.insert(index, value);
-        if old_entry.is_some() {
-            bail!("duplicate {type_display_name} for index {index}");
+            let old_entry = map.insert(index, value);
+            if old_entry.is_some() {
+                bail!("duplicate {type_display_name} for index {index}");
+            }
         }
-    }
 
-    // TODO: Consider enforcing that value index ranges cannot have holes and must start at 1.
-    let mut data = Vec::with_capacity(map.len() + 1);
-    data.push(None);
-    for value in map.into_values() {
-        data.push(Some(value));
-    }
+        // TODO: Consider enforcing that value index ranges cannot have holes and must start at 1.
+        let mut data = Vec::with_capacity(map.len() + 1);
+        data.push(None);
+        for value in map.into_values() {
+            data.push(Some(value));
+        }
 
-    let mut arena = ruby_marshal::ValueArena::new();
-    let handle = data.into_value(&mut arena)?;
-    arena.replace_root(handle);
+        let mut arena = ruby_marshal::ValueArena::new();
+        let handle = data.into_value(&mut arena)?;
+        arena.replace_root(handle);
 
-    let mut data = Vec::new();
-    ruby_marshal::dump(&mut data, &arena)?;
+        let mut data = Vec::new();
+        ruby_marshal::dump(&mut data, &arena)?;
 
-    Ok(data)
+        let data = self::dedup::dedup(&data).context("failed to dedup ruby data")?;
+
+        Ok(data)
+    })
 }
 
 fn generate_map_infos_data(path: &Path) -> anyhow::Result<Vec<u8>> {
@@ -204,6 +233,7 @@ fn generate_map_infos_data(path: &Path) -> anyhow::Result<Vec<u8>> {
         let (index, name) = dir_entry_file_stem
             .split_once('-')
             .context("invalid name format")?;
+        let name = crate::util::percent_unescape_file_name(name)?;
         let index: i32 = index.parse()?;
 
         println!("  packing map info \"{name}\"");
@@ -229,6 +259,217 @@ fn generate_map_infos_data(path: &Path) -> anyhow::Result<Vec<u8>> {
     Ok(data)
 }
 
+/// Where compiled entries are written.
+enum Output<'a> {
+    /// Write entries into a file sink.
+    ///
+    /// The layout is applied to data files, if provided.
+    FileSink {
+        file_sink: &'a mut FileSink,
+        layout: Option<&'a Layout>,
+    },
+
+    /// Keep the data of a compiled data file, ignoring copied files.
+    Memory(Option<Vec<u8>>),
+}
+
+impl Output<'_> {
+    /// Write a compiled data file.
+    fn write_data(&mut self, path_components: &[&str], data: Vec<u8>) -> anyhow::Result<()> {
+        match self {
+            Self::FileSink { file_sink, layout } => {
+                let data = match layout {
+                    Some(layout) => self::layout::apply(&data, layout)
+                        .context("failed to apply the lossless layout")?,
+                    None => data,
+                };
+                file_sink.write_file(path_components, &*data)
+            }
+            Self::Memory(output) => {
+                *output = Some(data);
+                Ok(())
+            }
+        }
+    }
+
+    /// Write a copied file.
+    fn write_file<R>(&mut self, path_components: &[&str], reader: R) -> anyhow::Result<()>
+    where
+        R: std::io::Read,
+    {
+        match self {
+            Self::FileSink { file_sink, .. } => file_sink.write_file(path_components, reader),
+            Self::Memory(_) => Ok(()),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn compile_entry(
+    game_kind: GameKind,
+    entry_path: &Path,
+    entry_file_type: std::fs::FileType,
+    relative_path: &Path,
+    relative_path_components: Vec<&str>,
+    original: Option<&Original>,
+    cache: &mut Cache,
+    output: &mut Output,
+) -> anyhow::Result<()> {
+    match game_kind {
+        GameKind::Xp => self::xp::compile(
+            entry_path,
+            entry_file_type,
+            relative_path,
+            relative_path_components,
+            original,
+            cache,
+            output,
+        ),
+        GameKind::Vx => self::vx::compile(
+            entry_path,
+            entry_file_type,
+            relative_path,
+            relative_path_components,
+            original,
+            cache,
+            output,
+        ),
+        GameKind::VxAce => self::vx_ace::compile(
+            entry_path,
+            entry_file_type,
+            relative_path,
+            relative_path_components,
+            original,
+            cache,
+            output,
+        ),
+    }
+}
+
+/// Get the percent-unescaped components of a relative path.
+///
+/// Files may be added by hand, so invalid escapes are kept as they are.
+fn get_relative_path_components(relative_path: &Path) -> anyhow::Result<Vec<String>> {
+    relative_path
+        .components()
+        .map(|component| match component {
+            PathComponent::Normal(value) => {
+                let value = value.to_str().context("non-unicode path")?;
+                Ok(crate::util::percent_unescape_file_name_lenient(value))
+            }
+            component => bail!("unexpected path component \"{component:?}\""),
+        })
+        .collect()
+}
+
+/// Compile a single extracted data file or dir, without applying a lossless layout.
+///
+/// This returns `None` if the entry is copied instead of compiled.
+pub fn compile_data_file(
+    input_path: &Path,
+    relative_path: &Path,
+    game_kind: GameKind,
+    original: &Original,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let entry_path = input_path.join(relative_path);
+    let entry_file_type = std::fs::metadata(&entry_path)
+        .with_context(|| format!("failed to get metadata for \"{}\"", entry_path.display()))?
+        .file_type();
+    let relative_path_components = get_relative_path_components(relative_path)?;
+    let relative_path_components: Vec<&str> = relative_path_components
+        .iter()
+        .map(|component| component.as_str())
+        .collect();
+
+    let mut output = Output::Memory(None);
+    compile_entry(
+        game_kind,
+        &entry_path,
+        entry_file_type,
+        relative_path,
+        relative_path_components,
+        Some(original),
+        &mut Cache::new_disabled(),
+        &mut output,
+    )?;
+
+    match output {
+        Output::Memory(data) => Ok(data),
+        Output::FileSink { .. } => unreachable!(),
+    }
+}
+
+/// Compile the extracted assets in a dir into the given file sink.
+pub fn compile_dir(
+    input_path: &Path,
+    game_kind: GameKind,
+    cache: &mut Cache,
+    file_sink: &mut FileSink,
+) -> anyhow::Result<()> {
+    // Sort entries so that identical inputs produce identical archives.
+    let walk_dir = WalkDir::new(input_path).sort_by(|a, b| {
+        crate::util::compare_file_names(
+            &a.file_name().to_string_lossy(),
+            &b.file_name().to_string_lossy(),
+        )
+    });
+    let mut iter = walk_dir.into_iter();
+    while let Some(entry) = iter.next() {
+        let entry = entry?;
+        let entry_file_type = entry.file_type();
+        let entry_path = entry.path();
+
+        let relative_path = entry_path.strip_prefix(input_path)?;
+        let relative_path_components = get_relative_path_components(relative_path)?;
+        let relative_path_components: Vec<&str> = relative_path_components
+            .iter()
+            .map(|component| component.as_str())
+            .collect();
+
+        if relative_path_components == [crate::encoding::FILE_NAME] {
+            continue;
+        }
+
+        if relative_path_components.first() == Some(&crate::lossless::DIR_NAME) {
+            if entry_file_type.is_dir() {
+                iter.skip_current_dir();
+            }
+            continue;
+        }
+
+        let original = if relative_path_components.is_empty() {
+            None
+        } else {
+            crate::lossless::load(input_path, relative_path)?
+        };
+
+        // The layout only describes the data compiled from the unmodified extracted data.
+        let layout = match original.as_ref() {
+            Some(original) if crate::lossless::fingerprint(entry_path)? == original.fingerprint => {
+                Some(&original.layout)
+            }
+            _ => None,
+        };
+        let mut output = Output::FileSink {
+            file_sink: &mut *file_sink,
+            layout,
+        };
+
+        compile_entry(
+            game_kind,
+            entry_path,
+            entry_file_type,
+            relative_path,
+            relative_path_components,
+            original.as_ref(),
+            cache,
+            &mut output,
+        )?;
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, argh::FromArgs)]
 #[argh(
     subcommand,
@@ -258,12 +499,35 @@ pub struct Options {
     )]
     game: Option<GameKind>,
 
+    #[argh(
+        option,
+        long = "encoding",
+        from_str_fn(crate::encoding::parse),
+        description = "the encoding of strings in data files and scripts, like \"shift_jis\". Defaults to the encoding recorded when extracting, or UTF-8."
+    )]
+    encoding: Option<&'static Encoding>,
+
     #[argh(
         switch,
         long = "overwrite",
         description = "whether overwrite the output if it exists"
     )]
     pub overwrite: bool,
+
+    #[argh(
+        option,
+        long = "key",
+        from_str_fn(crate::util::parse_key),
+        description = "the key to encrypt archives with, as a decimal or 0x-prefixed hex number. Defaults to the standard key."
+    )]
+    pub key: Option<u32>,
+
+    #[argh(
+        switch,
+        long = "incremental",
+        description = "whether to cache encoded data files next to the output, so that unchanged files are not encoded again"
+    )]
+    pub incremental: bool,
 }
 
 pub fn exec(mut options: Options) -> anyhow::Result<()> {
@@ -294,9 +558,10 @@ pub fn exec(mut options: Options) -> anyhow::Result<()> {
 
     let mut file_sink = match format {
         Format::Dir => FileSink::new_dir(&options.output, options.overwrite)?,
-        Format::Rgssad | Format::Rgss2a | Format::Rgss3a => {
-            FileSink::new_rgssad(&options.output, options.overwrite)?
+        Format::Rgssad | Format::Rgss2a => {
+            FileSink::new_rgssad(&options.output, options.overwrite, options.key)?
         }
+        Format::Rgss3a => FileSink::new_rgss3a(&options.output, options.overwrite, options.key)?,
     };
     let game_kind = options.game.map(Ok).unwrap_or_else(|| match format {
         Format::Dir => {
@@ -307,46 +572,22 @@ pub fn exec(mut options: Options) -> anyhow::Result<()> {
         Format::Rgss3a => Ok(GameKind::VxAce),
     })?;
 
-    for entry in WalkDir::new(&options.input) {
-        let entry = entry?;
-        let entry_file_type = entry.file_type();
-        let entry_path = entry.path();
+    let encoding = match options.encoding {
+        Some(encoding) => encoding,
+        None => crate::encoding::load(&options.input)?.unwrap_or(encoding_rs::UTF_8),
+    };
+    let _string_context = crate::encoding::string_context(encoding, game_kind).enter();
 
-        let relative_path = entry_path.strip_prefix(&options.input)?;
-        let relative_path_components = relative_path
-            .components()
-            .map(|component| match component {
-                PathComponent::Normal(value) => value.to_str().context("non-unicode path"),
-                component => bail!("unexpected path component \"{component:?}\""),
-            })
-            .collect::<anyhow::Result<Vec<_>>>()?;
-
-        match game_kind {
-            GameKind::Xp => self::xp::compile(
-                entry_path,
-                entry_file_type,
-                relative_path,
-                relative_path_components,
-                &mut file_sink,
-            )?,
-            GameKind::Vx => self::vx::compile(
-                entry_path,
-                entry_file_type,
-                relative_path,
-                relative_path_components,
-                &mut file_sink,
-            )?,
-            GameKind::VxAce => self::vx_ace::compile(
-                entry_path,
-                entry_file_type,
-                relative_path,
-                relative_path_components,
-                &mut file_sink,
-            )?,
-        }
-    }
+    let mut cache = if options.incremental {
+        Cache::open(nd_util::with_push_extension(&options.output, "cache"))?
+    } else {
+        Cache::new_disabled()
+    };
+
+    compile_dir(&options.input, game_kind, &mut cache, &mut file_sink)?;
 
     file_sink.finish()?;
+    cache.finish()?;
 
     Ok(())
 }
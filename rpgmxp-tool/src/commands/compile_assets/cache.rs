@@ -0,0 +1,208 @@
+use anyhow::Context;
+use rpgm_common_types::StringContext;
+use sha2::Digest;
+use sha2::Sha256;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The version of the encoded data.
+///
+/// Bump this whenever encoding or deduping changes, so stale cached data is not reused.
+const CACHE_FORMAT_VERSION: u32 = 1;
+
+/// A cache of encoded data files, keyed by a hash of their inputs.
+#[derive(Debug)]
+pub struct Cache {
+    /// The dir that holds the cached data, if caching is enabled.
+    dir_path: Option<PathBuf>,
+
+    /// The keys that were used in this build.
+    used_keys: HashSet<String>,
+}
+
+impl Cache {
+    /// Create a cache that always regenerates data.
+    pub fn new_disabled() -> Self {
+        Self {
+            dir_path: None,
+            used_keys: HashSet::new(),
+        }
+    }
+
+    /// Open or create a cache in the given dir.
+    pub fn open(dir_path: PathBuf) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&dir_path)
+            .with_context(|| format!("failed to create dir at \"{}\"", dir_path.display()))?;
+
+        Ok(Self {
+            dir_path: Some(dir_path),
+            used_keys: HashSet::new(),
+        })
+    }
+
+    /// Get the encoded data for an input file or dir,
+    /// only calling `generate` if the input changed since it was last cached.
+    ///
+    /// The kind identifies what the input is encoded as.
+    pub fn get_or_generate<F>(
+        &mut self,
+        kind: &str,
+        input_path: &Path,
+        generate: F,
+    ) -> anyhow::Result<Vec<u8>>
+    where
+        F: FnOnce() -> anyhow::Result<Vec<u8>>,
+    {
+        let Some(dir_path) = self.dir_path.as_ref() else {
+            return generate();
+        };
+
+        let mut hasher = Sha256::new();
+        hasher.update(CACHE_FORMAT_VERSION.to_le_bytes());
+        hasher.update(b"\0");
+        hasher.update(kind.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(StringContext::current().encoding.name().as_bytes());
+        hasher.update(b"\0");
+        hasher.update(format!("{:?}", StringContext::current().default_marker).as_bytes());
+        hasher.update(b"\0");
+        hasher.update(crate::lossless::fingerprint(input_path)?.as_bytes());
+        let key = format!("{:x}", hasher.finalize());
+
+        let data_path = dir_path.join(&key);
+        self.used_keys.insert(key);
+
+        match std::fs::read(&data_path) {
+            Ok(data) => {
+                println!("  reusing cached data");
+                return Ok(data);
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("failed to read \"{}\"", data_path.display()));
+            }
+        }
+
+        let data = generate()?;
+
+        // TODO: Lock?
+        // TODO: Drop delete guard for file?
+        let temp_path = nd_util::with_push_extension(&data_path, "temp");
+        std::fs::write(&temp_path, &data)
+            .with_context(|| format!("failed to write \"{}\"", temp_path.display()))?;
+        std::fs::rename(temp_path, data_path)?;
+
+        Ok(data)
+    }
+
+    /// Remove cached data that was not used in this build.
+    pub fn finish(self) -> anyhow::Result<()> {
+        let Some(dir_path) = self.dir_path else {
+            return Ok(());
+        };
+
+        for dir_entry in dir_path.read_dir()? {
+            let dir_entry = dir_entry?;
+            let file_name = dir_entry.file_name();
+            let is_used = file_name
+                .to_str()
+                .is_some_and(|file_name| self.used_keys.contains(file_name));
+
+            if !is_used {
+                std::fs::remove_file(dir_entry.path()).with_context(|| {
+                    format!("failed to remove \"{}\"", dir_entry.path().display())
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Create an empty dir with an input file for a test.
+    fn setup(name: &str) -> (PathBuf, PathBuf) {
+        let base_path =
+            std::env::temp_dir().join(format!("rpgmxp-tool-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&base_path).expect("failed to create dir");
+
+        let input_path = base_path.join("input.json");
+        std::fs::write(&input_path, "1").expect("failed to write input");
+
+        (base_path, input_path)
+    }
+
+    #[test]
+    fn hit() {
+        let (base_path, input_path) = setup("cache-hit");
+        let cache_path = base_path.join("cache");
+
+        let mut cache = Cache::open(cache_path.clone()).expect("failed to open cache");
+        cache
+            .get_or_generate("kind", &input_path, || Ok(b"data".to_vec()))
+            .expect("failed to generate");
+        cache.finish().expect("failed to finish");
+
+        let mut cache = Cache::open(cache_path).expect("failed to open cache");
+        let data = cache
+            .get_or_generate("kind", &input_path, || panic!("cache miss"))
+            .expect("failed to generate");
+        std::fs::remove_dir_all(&base_path).expect("failed to remove dir");
+
+        assert!(data == b"data");
+    }
+
+    #[test]
+    fn changed_input_misses() {
+        let (base_path, input_path) = setup("cache-miss");
+        let cache_path = base_path.join("cache");
+
+        let mut cache = Cache::open(cache_path.clone()).expect("failed to open cache");
+        cache
+            .get_or_generate("kind", &input_path, || Ok(b"old".to_vec()))
+            .expect("failed to generate");
+        cache.finish().expect("failed to finish");
+
+        std::fs::write(&input_path, "2").expect("failed to write input");
+        let mut cache = Cache::open(cache_path).expect("failed to open cache");
+        let data = cache
+            .get_or_generate("kind", &input_path, || Ok(b"new".to_vec()))
+            .expect("failed to generate");
+        std::fs::remove_dir_all(&base_path).expect("failed to remove dir");
+
+        assert!(data == b"new");
+    }
+
+    #[test]
+    fn finish_prunes_unused() {
+        let (base_path, input_path) = setup("cache-prune");
+        let cache_path = base_path.join("cache");
+
+        let mut cache = Cache::open(cache_path.clone()).expect("failed to open cache");
+        cache
+            .get_or_generate("old", &input_path, || Ok(b"old".to_vec()))
+            .expect("failed to generate");
+        cache.finish().expect("failed to finish");
+
+        let mut cache = Cache::open(cache_path.clone()).expect("failed to open cache");
+        cache
+            .get_or_generate("new", &input_path, || Ok(b"new".to_vec()))
+            .expect("failed to generate");
+        cache.finish().expect("failed to finish");
+
+        let entries: Vec<_> = cache_path
+            .read_dir()
+            .expect("failed to read cache dir")
+            .map(|entry| std::fs::read(entry.expect("failed to read entry").path()))
+            .collect::<Result<_, _>>()
+            .expect("failed to read cached data");
+        std::fs::remove_dir_all(&base_path).expect("failed to remove dir");
+
+        assert!(entries == [b"new".to_vec()]);
+    }
+}
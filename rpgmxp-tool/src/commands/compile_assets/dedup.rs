@@ -0,0 +1,749 @@
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+pub(super) const MAJOR_VERSION: u8 = 4;
+pub(super) const MINOR_VERSION: u8 = 8;
+
+/// The maximum number of nodes that a stream may have once its object links are expanded.
+///
+/// Links to nested shared objects can make the expanded stream exponentially larger,
+/// so this bounds the work done on it.
+pub(super) const MAX_EXPANDED_NODES: usize = 1 << 24;
+
+/// The user defined object names that are deduped.
+const DEDUP_USER_DEFINED_NAMES: &[&[u8]] = &[b"Table"];
+
+/// The object names that are deduped.
+///
+/// VX and VX Ace store audio as the `RPG::AudioFile` subclasses instead.
+const DEDUP_OBJECT_NAMES: &[&[u8]] = &[
+    b"RPG::AudioFile",
+    b"RPG::BGM",
+    b"RPG::BGS",
+    b"RPG::ME",
+    b"RPG::SE",
+    b"RPG::MoveCommand",
+];
+
+/// Dedupe a Marshal stream.
+///
+/// Identical strings, Tables, AudioFiles, and MoveCommands are replaced with object links,
+/// like the editor does.
+/// Object links in the input are expanded before deduping.
+pub fn dedup(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let (root, _objects) = parse(data, false)?;
+
+    let mut writer = Writer::new(true);
+    writer.data.extend([MAJOR_VERSION, MINOR_VERSION]);
+    writer.write_node(&root)?;
+
+    Ok(writer.data)
+}
+
+/// Parse a Marshal stream.
+///
+/// If `keep_links` is true, object links are kept as [`Node::Link`] instead of being expanded.
+/// This also returns the objects by index.
+pub(super) fn parse(data: &[u8], keep_links: bool) -> anyhow::Result<(Node, Vec<Option<Node>>)> {
+    let mut parser = Parser::new(data, keep_links);
+    let version = [parser.read_byte()?, parser.read_byte()?];
+    ensure!(
+        version == [MAJOR_VERSION, MINOR_VERSION],
+        "unsupported Marshal version {}.{}",
+        version[0],
+        version[1]
+    );
+    let root = parser.read_node()?;
+    ensure!(
+        parser.position == data.len(),
+        "unexpected trailing data in Marshal stream"
+    );
+
+    Ok((root, parser.objects))
+}
+
+pub(super) type InstanceVariables = Rc<[(Vec<u8>, Node)]>;
+
+/// A Marshal value.
+///
+/// Children are reference counted,
+/// so expanding an object link shares the linked object instead of copying it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) enum Node {
+    Nil,
+    True,
+    False,
+    Fixnum(i32),
+    Symbol(Vec<u8>),
+    String(Vec<u8>),
+    Regexp {
+        value: Vec<u8>,
+        options: u8,
+    },
+    Float(Vec<u8>),
+    Bignum {
+        sign: u8,
+        value: Vec<u8>,
+    },
+    Class(Vec<u8>),
+    Module(Vec<u8>),
+    Array(Rc<[Node]>),
+    Hash {
+        pairs: Rc<[(Node, Node)]>,
+        default: Option<Rc<Node>>,
+    },
+    Object {
+        name: Vec<u8>,
+        instance_variables: InstanceVariables,
+    },
+    Struct {
+        name: Vec<u8>,
+        members: InstanceVariables,
+    },
+    UserDefined {
+        name: Vec<u8>,
+        value: Vec<u8>,
+    },
+    UserMarshal {
+        name: Vec<u8>,
+        value: Rc<Node>,
+    },
+    UserClass {
+        name: Vec<u8>,
+        value: Rc<Node>,
+    },
+    Extended {
+        name: Vec<u8>,
+        value: Rc<Node>,
+    },
+    InstanceVariables {
+        value: Rc<Node>,
+        instance_variables: InstanceVariables,
+    },
+
+    /// A link to an object by index.
+    ///
+    /// This is only produced when links are kept while parsing.
+    Link(usize),
+}
+
+impl Node {
+    /// Check if this node should be replaced with a link if it was already written.
+    fn is_dedupable(&self) -> bool {
+        match self {
+            Self::String(_) => true,
+            Self::InstanceVariables { value, .. } => matches!(**value, Self::String(_)),
+            Self::UserDefined { name, .. } => DEDUP_USER_DEFINED_NAMES.contains(&name.as_slice()),
+            Self::Object { name, .. } => DEDUP_OBJECT_NAMES.contains(&name.as_slice()),
+            _ => false,
+        }
+    }
+}
+
+pub(super) struct Parser<'a> {
+    data: &'a [u8],
+    position: usize,
+    symbols: Vec<Vec<u8>>,
+
+    /// Whether object links are kept instead of being expanded.
+    keep_links: bool,
+
+    /// Objects by index, for resolving links.
+    ///
+    /// This is `None` while the object is being read.
+    objects: Vec<Option<Node>>,
+
+    /// The number of nodes of each object, once its links are expanded.
+    object_sizes: Vec<usize>,
+
+    /// The number of nodes read so far, once links are expanded.
+    expanded_nodes: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub(super) fn new(data: &'a [u8], keep_links: bool) -> Self {
+        Self {
+            data,
+            position: 0,
+            symbols: Vec::new(),
+            keep_links,
+            objects: Vec::new(),
+            object_sizes: Vec::new(),
+            expanded_nodes: 0,
+        }
+    }
+
+    fn read_byte(&mut self) -> anyhow::Result<u8> {
+        let byte = *self
+            .data
+            .get(self.position)
+            .context("unexpected end of Marshal stream")?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> anyhow::Result<Vec<u8>> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .context("unexpected end of Marshal stream")?;
+        let bytes = self.data[self.position..end].to_vec();
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn read_int(&mut self) -> anyhow::Result<i32> {
+        let byte = self.read_byte()? as i8;
+        match byte {
+            0 => Ok(0),
+            5..=127 => Ok(i32::from(byte) - 5),
+            -128..=-5 => Ok(i32::from(byte) + 5),
+            1..=4 => {
+                let mut value: u32 = 0;
+                for i in 0..u32::from(byte.unsigned_abs()) {
+                    value |= u32::from(self.read_byte()?) << (8 * i);
+                }
+                Ok(value as i32)
+            }
+            -4..=-1 => {
+                let mut value: u32 = u32::MAX;
+                for i in 0..u32::from(byte.unsigned_abs()) {
+                    value &= !(0xFF << (8 * i));
+                    value |= u32::from(self.read_byte()?) << (8 * i);
+                }
+                Ok(value as i32)
+            }
+        }
+    }
+
+    fn read_len(&mut self) -> anyhow::Result<usize> {
+        let len = self.read_int()?;
+        usize::try_from(len).with_context(|| format!("invalid length {len}"))
+    }
+
+    fn read_byte_string(&mut self) -> anyhow::Result<Vec<u8>> {
+        let len = self.read_len()?;
+        self.read_bytes(len)
+    }
+
+    fn read_symbol(&mut self) -> anyhow::Result<Vec<u8>> {
+        match self.read_byte()? {
+            b':' => {
+                let symbol = self.read_byte_string()?;
+                self.symbols.push(symbol.clone());
+                Ok(symbol)
+            }
+            b';' => {
+                let index = self.read_len()?;
+                self.symbols
+                    .get(index)
+                    .cloned()
+                    .with_context(|| format!("invalid symbol link {index}"))
+            }
+            kind => bail!("expected a symbol, got type byte 0x{kind:02X}"),
+        }
+    }
+
+    fn read_instance_variables(&mut self) -> anyhow::Result<InstanceVariables> {
+        let len = self.read_len()?;
+        let mut instance_variables = Vec::with_capacity(std::cmp::min(len, 1024));
+        for _ in 0..len {
+            let key = self.read_symbol()?;
+            let value = self.read_node()?;
+            instance_variables.push((key, value));
+        }
+        Ok(instance_variables.into())
+    }
+
+    /// Reserve an object index for an object that is about to be read.
+    fn reserve_object(&mut self) -> usize {
+        self.objects.push(None);
+        self.object_sizes.push(0);
+        self.objects.len() - 1
+    }
+
+    pub(super) fn read_node(&mut self) -> anyhow::Result<Node> {
+        let start_expanded_nodes = self.expanded_nodes;
+        self.expanded_nodes += 1;
+
+        let kind = self.read_byte()?;
+        let (index, node) = match kind {
+            b'0' => return Ok(Node::Nil),
+            b'T' => return Ok(Node::True),
+            b'F' => return Ok(Node::False),
+            b'i' => return Ok(Node::Fixnum(self.read_int()?)),
+            b':' | b';' => {
+                self.position -= 1;
+                return Ok(Node::Symbol(self.read_symbol()?));
+            }
+            b'@' => {
+                let index = self.read_len()?;
+                if self.keep_links {
+                    return Ok(Node::Link(index));
+                }
+                let node = self
+                    .objects
+                    .get(index)
+                    .with_context(|| format!("invalid object link {index}"))?
+                    .clone()
+                    .context("cyclic object links are not supported")?;
+
+                self.expanded_nodes = self.expanded_nodes.saturating_add(self.object_sizes[index]);
+                ensure!(
+                    self.expanded_nodes <= MAX_EXPANDED_NODES,
+                    "expanding object links produces more than {MAX_EXPANDED_NODES} nodes"
+                );
+
+                return Ok(node);
+            }
+            b'I' => {
+                let index = self.objects.len();
+                let value = self.read_node()?;
+                let instance_variables = self.read_instance_variables()?;
+                let node = Node::InstanceVariables {
+                    value: Rc::new(value),
+                    instance_variables,
+                };
+                ensure!(
+                    index < self.objects.len(),
+                    "instance variables on an immediate value"
+                );
+                (index, node)
+            }
+            b'e' => {
+                let name = self.read_symbol()?;
+                let index = self.objects.len();
+                let value = self.read_node()?;
+                let node = Node::Extended {
+                    name,
+                    value: Rc::new(value),
+                };
+                ensure!(index < self.objects.len(), "extended an immediate value");
+                (index, node)
+            }
+            b'C' => {
+                let name = self.read_symbol()?;
+                let index = self.objects.len();
+                let value = self.read_node()?;
+                let node = Node::UserClass {
+                    name,
+                    value: Rc::new(value),
+                };
+                ensure!(
+                    index < self.objects.len(),
+                    "user class on an immediate value"
+                );
+                (index, node)
+            }
+            b'"' => {
+                let index = self.reserve_object();
+                (index, Node::String(self.read_byte_string()?))
+            }
+            b'/' => {
+                let index = self.reserve_object();
+                let value = self.read_byte_string()?;
+                let options = self.read_byte()?;
+                (index, Node::Regexp { value, options })
+            }
+            b'f' => {
+                let index = self.reserve_object();
+                (index, Node::Float(self.read_byte_string()?))
+            }
+            b'l' => {
+                let index = self.reserve_object();
+                let sign = self.read_byte()?;
+                let len = self.read_len()?;
+                let value = self.read_bytes(len.checked_mul(2).context("bignum is too large")?)?;
+                (index, Node::Bignum { sign, value })
+            }
+            b'c' => {
+                let index = self.reserve_object();
+                (index, Node::Class(self.read_byte_string()?))
+            }
+            b'm' => {
+                let index = self.reserve_object();
+                (index, Node::Module(self.read_byte_string()?))
+            }
+            b'[' => {
+                let index = self.reserve_object();
+                let len = self.read_len()?;
+                let mut values = Vec::with_capacity(std::cmp::min(len, 1024));
+                for _ in 0..len {
+                    values.push(self.read_node()?);
+                }
+                (index, Node::Array(values.into()))
+            }
+            b'{' | b'}' => {
+                let index = self.reserve_object();
+                let len = self.read_len()?;
+                let mut pairs = Vec::with_capacity(std::cmp::min(len, 1024));
+                for _ in 0..len {
+                    let key = self.read_node()?;
+                    let value = self.read_node()?;
+                    pairs.push((key, value));
+                }
+                let default = if kind == b'}' {
+                    Some(Rc::new(self.read_node()?))
+                } else {
+                    None
+                };
+                (
+                    index,
+                    Node::Hash {
+                        pairs: pairs.into(),
+                        default,
+                    },
+                )
+            }
+            b'o' => {
+                let index = self.reserve_object();
+                let name = self.read_symbol()?;
+                let instance_variables = self.read_instance_variables()?;
+                (
+                    index,
+                    Node::Object {
+                        name,
+                        instance_variables,
+                    },
+                )
+            }
+            b'S' => {
+                let index = self.reserve_object();
+                let name = self.read_symbol()?;
+                let members = self.read_instance_variables()?;
+                (index, Node::Struct { name, members })
+            }
+            b'u' => {
+                let index = self.reserve_object();
+                let name = self.read_symbol()?;
+                let value = self.read_byte_string()?;
+                (index, Node::UserDefined { name, value })
+            }
+            b'U' => {
+                let index = self.reserve_object();
+                let name = self.read_symbol()?;
+                let value = self.read_node()?;
+                (
+                    index,
+                    Node::UserMarshal {
+                        name,
+                        value: Rc::new(value),
+                    },
+                )
+            }
+            kind => bail!("unsupported Marshal type byte 0x{kind:02X}"),
+        };
+
+        self.objects[index] = Some(node.clone());
+        self.object_sizes[index] = self.expanded_nodes - start_expanded_nodes;
+
+        Ok(node)
+    }
+}
+
+pub(super) struct Writer {
+    pub(super) data: Vec<u8>,
+    symbols: HashMap<Vec<u8>, usize>,
+    object_count: usize,
+
+    /// Whether dedupable nodes are replaced with links.
+    dedup: bool,
+
+    /// The object indices of written dedupable nodes.
+    links: HashMap<Node, usize>,
+}
+
+impl Writer {
+    pub(super) fn new(dedup: bool) -> Self {
+        Self {
+            data: Vec::new(),
+            symbols: HashMap::new(),
+            object_count: 0,
+            dedup,
+            links: HashMap::new(),
+        }
+    }
+
+    fn write_int(&mut self, value: i32) {
+        match value {
+            0 => self.data.push(0),
+            1..=122 => self.data.push((value + 5) as u8),
+            -123..=-1 => self.data.push((value - 5) as u8),
+            _ => {
+                let bytes = value.to_le_bytes();
+                let fill = if value < 0 { 0xFF } else { 0x00 };
+                let len = bytes
+                    .iter()
+                    .rposition(|byte| *byte != fill)
+                    .map_or(1, |position| position + 1);
+                let len_byte = if value < 0 { -(len as i8) } else { len as i8 };
+                self.data.push(len_byte as u8);
+                self.data.extend(&bytes[..len]);
+            }
+        }
+    }
+
+    pub(super) fn write_len(&mut self, len: usize) -> anyhow::Result<()> {
+        let len = i32::try_from(len).context("length is too large")?;
+        self.write_int(len);
+        Ok(())
+    }
+
+    fn write_byte_string(&mut self, value: &[u8]) -> anyhow::Result<()> {
+        self.write_len(value.len())?;
+        self.data.extend(value);
+        Ok(())
+    }
+
+    pub(super) fn write_symbol(&mut self, symbol: &[u8]) -> anyhow::Result<()> {
+        match self.symbols.get(symbol) {
+            Some(index) => {
+                self.data.push(b';');
+                self.write_len(*index)?;
+            }
+            None => {
+                self.symbols.insert(symbol.to_vec(), self.symbols.len());
+                self.data.push(b':');
+                self.write_byte_string(symbol)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_instance_variables(
+        &mut self,
+        instance_variables: &InstanceVariables,
+    ) -> anyhow::Result<()> {
+        self.write_len(instance_variables.len())?;
+        for (key, value) in instance_variables.iter() {
+            self.write_symbol(key)?;
+            self.write_node(value)?;
+        }
+        Ok(())
+    }
+
+    /// Write a node, replacing it with a link if an identical one was already written.
+    fn write_node(&mut self, node: &Node) -> anyhow::Result<()> {
+        if !self.dedup || !node.is_dedupable() {
+            return self.write_value(node);
+        }
+
+        if let Some(index) = self.links.get(node) {
+            self.data.push(b'@');
+            return self.write_len(*index);
+        }
+
+        let index = self.object_count;
+        self.write_value(node)?;
+        self.links.insert(node.clone(), index);
+
+        Ok(())
+    }
+
+    /// Write a node without checking for links.
+    pub(super) fn write_value(&mut self, node: &Node) -> anyhow::Result<()> {
+        match node {
+            Node::Nil => self.data.push(b'0'),
+            Node::True => self.data.push(b'T'),
+            Node::False => self.data.push(b'F'),
+            Node::Fixnum(value) => {
+                self.data.push(b'i');
+                self.write_int(*value);
+            }
+            Node::Symbol(value) => self.write_symbol(value)?,
+            Node::String(value) => {
+                self.object_count += 1;
+                self.data.push(b'"');
+                self.write_byte_string(value)?;
+            }
+            Node::Regexp { value, options } => {
+                self.object_count += 1;
+                self.data.push(b'/');
+                self.write_byte_string(value)?;
+                self.data.push(*options);
+            }
+            Node::Float(value) => {
+                self.object_count += 1;
+                self.data.push(b'f');
+                self.write_byte_string(value)?;
+            }
+            Node::Bignum { sign, value } => {
+                self.object_count += 1;
+                self.data.push(b'l');
+                self.data.push(*sign);
+                self.write_len(value.len() / 2)?;
+                self.data.extend(value);
+            }
+            Node::Class(value) => {
+                self.object_count += 1;
+                self.data.push(b'c');
+                self.write_byte_string(value)?;
+            }
+            Node::Module(value) => {
+                self.object_count += 1;
+                self.data.push(b'm');
+                self.write_byte_string(value)?;
+            }
+            Node::Array(values) => {
+                self.object_count += 1;
+                self.data.push(b'[');
+                self.write_len(values.len())?;
+                for value in values.iter() {
+                    self.write_node(value)?;
+                }
+            }
+            Node::Hash { pairs, default } => {
+                self.object_count += 1;
+                self.data.push(if default.is_some() { b'}' } else { b'{' });
+                self.write_len(pairs.len())?;
+                for (key, value) in pairs.iter() {
+                    self.write_node(key)?;
+                    self.write_node(value)?;
+                }
+                if let Some(default) = default {
+                    self.write_node(default)?;
+                }
+            }
+            Node::Object {
+                name,
+                instance_variables,
+            } => {
+                self.object_count += 1;
+                self.data.push(b'o');
+                self.write_symbol(name)?;
+                self.write_instance_variables(instance_variables)?;
+            }
+            Node::Struct { name, members } => {
+                self.object_count += 1;
+                self.data.push(b'S');
+                self.write_symbol(name)?;
+                self.write_instance_variables(members)?;
+            }
+            Node::UserDefined { name, value } => {
+                self.object_count += 1;
+                self.data.push(b'u');
+                self.write_symbol(name)?;
+                self.write_byte_string(value)?;
+            }
+            Node::UserMarshal { name, value } => {
+                self.object_count += 1;
+                self.data.push(b'U');
+                self.write_symbol(name)?;
+                self.write_node(value)?;
+            }
+            Node::UserClass { name, value } => {
+                self.data.push(b'C');
+                self.write_symbol(name)?;
+                self.write_value(value)?;
+            }
+            Node::Extended { name, value } => {
+                self.data.push(b'e');
+                self.write_symbol(name)?;
+                self.write_value(value)?;
+            }
+            Node::InstanceVariables {
+                value,
+                instance_variables,
+            } => {
+                self.data.push(b'I');
+                self.write_value(value)?;
+                self.write_instance_variables(instance_variables)?;
+            }
+            Node::Link(index) => {
+                self.data.push(b'@');
+                self.write_len(*index)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn int_round_trip() {
+        for value in [
+            0,
+            1,
+            -1,
+            122,
+            123,
+            -123,
+            -124,
+            255,
+            256,
+            -256,
+            -257,
+            65535,
+            i32::MAX,
+            i32::MIN,
+        ] {
+            let mut writer = Writer::new(true);
+            writer.write_int(value);
+
+            let mut parser = Parser::new(&writer.data, false);
+            assert!(parser.read_int().unwrap() == value);
+            assert!(parser.position == writer.data.len());
+        }
+    }
+
+    #[test]
+    fn dedup_strings() {
+        // ["a", "a", ["b", "a"]]
+        let input = b"\x04\x08[\x08\"\x06a\"\x06a[\x07\"\x06b\"\x06a";
+        // ["a", @1, ["b", @1]]
+        let expected = b"\x04\x08[\x08\"\x06a@\x06[\x07\"\x06b@\x06";
+
+        let output = dedup(input).expect("failed to dedup");
+        assert!(output == expected);
+    }
+
+    #[test]
+    fn dedup_objects() {
+        // [AudioFile(@name = "a"), AudioFile(@name = "a"), [1]]
+        let input = b"\x04\x08[\x08o:\x13RPG::AudioFile\x06:\x0a@name\"\x06ao;\x00\x06;\x06\"\x06a[\x06i\x06";
+        // The string within the first object gets index 2,
+        // so the array after the link gets index 3.
+        let expected = b"\x04\x08[\x08o:\x13RPG::AudioFile\x06:\x0a@name\"\x06a@\x06[\x06i\x06";
+
+        let output = dedup(input).expect("failed to dedup");
+        assert!(output == expected);
+
+        // Links in the input are expanded and re-linked.
+        let output = dedup(&output).expect("failed to dedup");
+        assert!(output == expected);
+    }
+
+    #[test]
+    fn dedup_vx_ace_audio() {
+        // [SE(@name = "a", @volume = 80, @pitch = 100), SE(@name = "a", @volume = 80, @pitch = 100)]
+        let input = b"\x04\x08[\x07o:\x0cRPG::SE\x08:\x0a@nameI\"\x06a\x06:\x06ET:\x0c@volumeiU:\x0b@pitchiio;\x00\x08;\x06I\"\x06a\x06;\x07T;\x08iU;\x09ii";
+        // The first object gets index 1.
+        let expected = b"\x04\x08[\x07o:\x0cRPG::SE\x08:\x0a@nameI\"\x06a\x06:\x06ET:\x0c@volumeiU:\x0b@pitchii@\x06";
+
+        let output = dedup(input).expect("failed to dedup");
+        assert!(output == expected);
+    }
+
+    #[test]
+    fn nested_links_are_bounded() {
+        // [[1], [@1, @1], [@2, @2], ...], where each array doubles in size when expanded.
+        let mut input = b"\x04\x08[\x2e[\x06i\x06".to_vec();
+        for index in 1..=40 {
+            input.extend([b'[', 0x07, b'@', index + 5, b'@', index + 5]);
+        }
+
+        let error = dedup(&input).expect_err("exponential expansion was accepted");
+        assert!(error.to_string().contains("expanding object links"));
+
+        // Keeping links does not expand anything.
+        assert!(parse(&input, true).is_ok());
+    }
+}
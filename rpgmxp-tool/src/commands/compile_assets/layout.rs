@@ -0,0 +1,735 @@
+use super::dedup::InstanceVariables;
+use super::dedup::Node;
+use super::dedup::Parser;
+use super::dedup::Writer;
+use super::dedup::MAX_EXPANDED_NODES;
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use base64::engine::general_purpose::URL_SAFE;
+use base64::Engine;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// How to turn a re-encoded Marshal stream back into the original stream.
+///
+/// Nodes are identified by their index in a pre-order walk of the original stream,
+/// where object links count as a single node.
+/// Instance variables and hash pairs are written sorted by name or key,
+/// unless the original stream used a different order.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Layout {
+    nodes: BTreeMap<usize, NodeLayout>,
+}
+
+/// How to write a single node.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum NodeLayout {
+    /// Write a link to the object with this index instead of the node.
+    Link(usize),
+
+    /// Write the instance variables or hash pairs in this order,
+    /// as indices into the sorted instance variables or hash pairs.
+    Order(Vec<usize>),
+
+    /// Write this node instead,
+    /// as a Marshal stream without a version header in url-safe base64.
+    ///
+    /// This is used for values that the encoder cannot reproduce.
+    Replace(String),
+}
+
+/// Record the layout that turns `encoded` into `original`.
+///
+/// `encoded` is the data produced by compiling the extracted data.
+pub fn record(original: &[u8], encoded: &[u8]) -> anyhow::Result<Layout> {
+    let (original_root, objects) = super::dedup::parse(original, true)?;
+    let (encoded_root, _objects) = super::dedup::parse(encoded, false)?;
+
+    let mut recorder = Recorder {
+        link_expander: LinkExpander::new(&objects),
+        index: 0,
+        layout: Layout::default(),
+    };
+    recorder.record_node(&original_root, &encoded_root)?;
+
+    let layout = recorder.layout;
+    let output = apply(encoded, &layout)?;
+    ensure!(
+        output == original,
+        "the layout does not reproduce the original data"
+    );
+
+    Ok(layout)
+}
+
+/// Apply a layout to the data produced by compiling unmodified extracted data.
+pub fn apply(encoded: &[u8], layout: &Layout) -> anyhow::Result<Vec<u8>> {
+    let (root, _objects) = super::dedup::parse(encoded, false)?;
+
+    let mut applier = Applier {
+        layout,
+        index: 0,
+        writer: Writer::new(false),
+    };
+    applier
+        .writer
+        .data
+        .extend([super::dedup::MAJOR_VERSION, super::dedup::MINOR_VERSION]);
+    applier.write_node(&root)?;
+
+    Ok(applier.writer.data)
+}
+
+struct Recorder<'a> {
+    /// Resolves links in the hash keys of the original stream.
+    link_expander: LinkExpander<'a>,
+
+    index: usize,
+    layout: Layout,
+}
+
+impl Recorder<'_> {
+    /// Record the layout of a node of the original stream and its children.
+    fn record_node(&mut self, original: &Node, encoded: &Node) -> anyhow::Result<()> {
+        let index = self.index;
+        self.index += 1;
+
+        if let Node::Link(object_index) = original {
+            self.layout
+                .nodes
+                .insert(index, NodeLayout::Link(*object_index));
+            return Ok(());
+        }
+
+        if !self.record_children(index, original, encoded)? {
+            // Children are only recorded once the node itself matches,
+            // so there is nothing to undo here.
+            self.index = index + count_nodes(original);
+            self.layout
+                .nodes
+                .insert(index, NodeLayout::Replace(encode_node(original)?));
+        }
+
+        Ok(())
+    }
+
+    /// Record the layout of the children of a node.
+    ///
+    /// This returns false if the nodes do not match.
+    fn record_children(
+        &mut self,
+        index: usize,
+        original: &Node,
+        encoded: &Node,
+    ) -> anyhow::Result<bool> {
+        match (original, encoded) {
+            (Node::Array(original), Node::Array(encoded)) => {
+                if original.len() != encoded.len() {
+                    return Ok(false);
+                }
+                for (original, encoded) in original.iter().zip(encoded.iter()) {
+                    self.record_node(original, encoded)?;
+                }
+            }
+            (
+                Node::Hash {
+                    pairs: original_pairs,
+                    default: original_default,
+                },
+                Node::Hash {
+                    pairs: encoded_pairs,
+                    default: encoded_default,
+                },
+            ) => {
+                if original_pairs.len() != encoded_pairs.len()
+                    || original_default.is_some() != encoded_default.is_some()
+                {
+                    return Ok(false);
+                }
+
+                let encoded_pairs = sort_pairs(encoded_pairs)?;
+                let positions: HashMap<Vec<u8>, usize> = encoded_pairs
+                    .iter()
+                    .enumerate()
+                    .map(|(position, (key, _pair))| (key.clone(), position))
+                    .collect();
+                let mut order = Vec::with_capacity(original_pairs.len());
+                for (key, _value) in original_pairs.iter() {
+                    let (key, _size) = self.link_expander.expand(key)?;
+                    let key = encode_key(&key)?;
+                    match positions.get(&key) {
+                        Some(position) => order.push(*position),
+                        None => return Ok(false),
+                    }
+                }
+                if !self.record_order(index, order) {
+                    return Ok(false);
+                }
+
+                for ((original_key, original_value), position) in original_pairs
+                    .iter()
+                    .zip(get_order(&self.layout, index, original_pairs.len()))
+                {
+                    let (_, (encoded_key, encoded_value)) = encoded_pairs[position];
+                    self.record_node(original_key, encoded_key)?;
+                    self.record_node(original_value, encoded_value)?;
+                }
+                if let (Some(original), Some(encoded)) = (original_default, encoded_default) {
+                    self.record_node(original, encoded)?;
+                }
+            }
+            (
+                Node::Object {
+                    name: original_name,
+                    instance_variables: original,
+                },
+                Node::Object {
+                    name: encoded_name,
+                    instance_variables: encoded,
+                },
+            )
+            | (
+                Node::Struct {
+                    name: original_name,
+                    members: original,
+                },
+                Node::Struct {
+                    name: encoded_name,
+                    members: encoded,
+                },
+            ) => {
+                if original_name != encoded_name {
+                    return Ok(false);
+                }
+                return self.record_instance_variables(index, original, encoded);
+            }
+            (
+                Node::InstanceVariables {
+                    value: original_value,
+                    instance_variables: original,
+                },
+                Node::InstanceVariables {
+                    value: encoded_value,
+                    instance_variables: encoded,
+                },
+            ) => {
+                if std::mem::discriminant(&**original_value)
+                    != std::mem::discriminant(&**encoded_value)
+                {
+                    return Ok(false);
+                }
+                let Some(order) = instance_variables_order(original, encoded) else {
+                    return Ok(false);
+                };
+                if !self.record_order(index, order) {
+                    return Ok(false);
+                }
+
+                self.record_node(original_value, encoded_value)?;
+                self.record_instance_variables_values(index, original, encoded)?;
+            }
+            (
+                Node::UserMarshal {
+                    name: original_name,
+                    value: original,
+                },
+                Node::UserMarshal {
+                    name: encoded_name,
+                    value: encoded,
+                },
+            )
+            | (
+                Node::UserClass {
+                    name: original_name,
+                    value: original,
+                },
+                Node::UserClass {
+                    name: encoded_name,
+                    value: encoded,
+                },
+            )
+            | (
+                Node::Extended {
+                    name: original_name,
+                    value: original,
+                },
+                Node::Extended {
+                    name: encoded_name,
+                    value: encoded,
+                },
+            ) => {
+                if original_name != encoded_name
+                    || std::mem::discriminant(&**original) != std::mem::discriminant(&**encoded)
+                {
+                    return Ok(false);
+                }
+                self.record_node(original, encoded)?;
+            }
+            (Node::Link(_), _) => bail!("unexpected object link"),
+            (original, encoded) => return Ok(original == encoded),
+        }
+
+        Ok(true)
+    }
+
+    fn record_instance_variables(
+        &mut self,
+        index: usize,
+        original: &InstanceVariables,
+        encoded: &InstanceVariables,
+    ) -> anyhow::Result<bool> {
+        let Some(order) = instance_variables_order(original, encoded) else {
+            return Ok(false);
+        };
+        if !self.record_order(index, order) {
+            return Ok(false);
+        }
+        self.record_instance_variables_values(index, original, encoded)?;
+
+        Ok(true)
+    }
+
+    fn record_instance_variables_values(
+        &mut self,
+        index: usize,
+        original: &InstanceVariables,
+        encoded: &InstanceVariables,
+    ) -> anyhow::Result<()> {
+        let encoded = sort_instance_variables(encoded);
+        let order = get_order(&self.layout, index, original.len());
+        for ((_key, original), position) in original.iter().zip(order) {
+            let (_key, encoded) = encoded[position];
+            self.record_node(original, encoded)?;
+        }
+
+        Ok(())
+    }
+
+    /// Record the order of the children of a node, if it is not the sorted order.
+    ///
+    /// This returns false if the order is not a permutation.
+    fn record_order(&mut self, index: usize, order: Vec<usize>) -> bool {
+        let mut seen = vec![false; order.len()];
+        for position in order.iter() {
+            if std::mem::replace(&mut seen[*position], true) {
+                return false;
+            }
+        }
+
+        if order.iter().enumerate().any(|(i, position)| i != *position) {
+            self.layout.nodes.insert(index, NodeLayout::Order(order));
+        }
+
+        true
+    }
+}
+
+struct Applier<'a> {
+    layout: &'a Layout,
+    index: usize,
+    writer: Writer,
+}
+
+impl Applier<'_> {
+    fn write_node(&mut self, node: &Node) -> anyhow::Result<()> {
+        let index = self.index;
+        self.index += 1;
+
+        match self.layout.nodes.get(&index) {
+            Some(NodeLayout::Link(object_index)) => {
+                self.writer.data.push(b'@');
+                return self.writer.write_len(*object_index);
+            }
+            Some(NodeLayout::Replace(data)) => {
+                let node = decode_node(data)?;
+                self.index = index + count_nodes(&node);
+                return self.writer.write_value(&node);
+            }
+            Some(NodeLayout::Order(_)) | None => {}
+        }
+
+        match node {
+            Node::Array(values) => {
+                self.writer.data.push(b'[');
+                self.writer.write_len(values.len())?;
+                for value in values.iter() {
+                    self.write_node(value)?;
+                }
+            }
+            Node::Hash { pairs, default } => {
+                let pairs = sort_pairs(pairs)?;
+                let order = get_order(self.layout, index, pairs.len());
+                ensure!(
+                    order.len() == pairs.len(),
+                    "the layout does not match the data"
+                );
+
+                self.writer
+                    .data
+                    .push(if default.is_some() { b'}' } else { b'{' });
+                self.writer.write_len(pairs.len())?;
+                for position in order {
+                    let (_key, (key, value)) = pairs
+                        .get(position)
+                        .context("the layout does not match the data")?;
+                    self.write_node(key)?;
+                    self.write_node(value)?;
+                }
+                if let Some(default) = default {
+                    self.write_node(default)?;
+                }
+            }
+            Node::Object {
+                name,
+                instance_variables,
+            } => {
+                self.writer.data.push(b'o');
+                self.writer.write_symbol(name)?;
+                self.write_instance_variables(index, instance_variables)?;
+            }
+            Node::Struct { name, members } => {
+                self.writer.data.push(b'S');
+                self.writer.write_symbol(name)?;
+                self.write_instance_variables(index, members)?;
+            }
+            Node::UserMarshal { name, value } => {
+                self.writer.data.push(b'U');
+                self.writer.write_symbol(name)?;
+                self.write_node(value)?;
+            }
+            Node::UserClass { name, value } => {
+                self.writer.data.push(b'C');
+                self.writer.write_symbol(name)?;
+                self.write_node(value)?;
+            }
+            Node::Extended { name, value } => {
+                self.writer.data.push(b'e');
+                self.writer.write_symbol(name)?;
+                self.write_node(value)?;
+            }
+            Node::InstanceVariables {
+                value,
+                instance_variables,
+            } => {
+                self.writer.data.push(b'I');
+                self.write_node(value)?;
+                self.write_instance_variables(index, instance_variables)?;
+            }
+            node => self.writer.write_value(node)?,
+        }
+
+        Ok(())
+    }
+
+    fn write_instance_variables(
+        &mut self,
+        index: usize,
+        instance_variables: &InstanceVariables,
+    ) -> anyhow::Result<()> {
+        let instance_variables = sort_instance_variables(instance_variables);
+        let order = get_order(self.layout, index, instance_variables.len());
+        ensure!(
+            order.len() == instance_variables.len(),
+            "the layout does not match the data"
+        );
+
+        self.writer.write_len(instance_variables.len())?;
+        for position in order {
+            let (key, value) = instance_variables
+                .get(position)
+                .context("the layout does not match the data")?;
+            self.writer.write_symbol(key)?;
+            self.write_node(value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the order of the children of a node, defaulting to the sorted order.
+fn get_order(layout: &Layout, index: usize, len: usize) -> Vec<usize> {
+    match layout.nodes.get(&index) {
+        Some(NodeLayout::Order(order)) => order.clone(),
+        _ => (0..len).collect(),
+    }
+}
+
+/// Get the order of the original instance variables, as indices into the sorted encoded ones.
+///
+/// This returns `None` if the instance variable names do not match.
+fn instance_variables_order(
+    original: &InstanceVariables,
+    encoded: &InstanceVariables,
+) -> Option<Vec<usize>> {
+    if original.len() != encoded.len() {
+        return None;
+    }
+
+    let encoded = sort_instance_variables(encoded);
+    original
+        .iter()
+        .map(|(key, _value)| {
+            encoded
+                .binary_search_by(|(encoded_key, _value)| (*encoded_key).cmp(key.as_slice()))
+                .ok()
+        })
+        .collect()
+}
+
+fn sort_instance_variables(instance_variables: &InstanceVariables) -> Vec<(&[u8], &Node)> {
+    let mut instance_variables: Vec<_> = instance_variables
+        .iter()
+        .map(|(key, value)| (key.as_slice(), value))
+        .collect();
+    instance_variables.sort_by_key(|(key, _value)| *key);
+    instance_variables
+}
+
+/// Sort hash pairs by their encoded keys.
+#[allow(clippy::type_complexity)]
+fn sort_pairs(pairs: &[(Node, Node)]) -> anyhow::Result<Vec<(Vec<u8>, (&Node, &Node))>> {
+    let mut pairs = pairs
+        .iter()
+        .map(|(key, value)| Ok((encode_key(key)?, (key, value))))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    pairs.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(pairs)
+}
+
+/// Encode a hash key for sorting and comparing.
+fn encode_key(key: &Node) -> anyhow::Result<Vec<u8>> {
+    let mut writer = Writer::new(false);
+    writer.write_value(key)?;
+    Ok(writer.data)
+}
+
+/// Replaces object links in nodes with the objects they point to.
+///
+/// Each object is expanded once and shared between the links to it,
+/// and an expanded node may have at most [`MAX_EXPANDED_NODES`] nodes.
+struct LinkExpander<'a> {
+    objects: &'a [Option<Node>],
+    expanded: Vec<ExpandedObject>,
+}
+
+/// The expansion of an object.
+#[derive(Debug, Clone)]
+enum ExpandedObject {
+    Unexpanded,
+    Expanding,
+
+    /// The expanded object and its number of nodes.
+    Expanded(Node, usize),
+}
+
+impl<'a> LinkExpander<'a> {
+    fn new(objects: &'a [Option<Node>]) -> Self {
+        Self {
+            objects,
+            expanded: vec![ExpandedObject::Unexpanded; objects.len()],
+        }
+    }
+
+    /// Expand a node.
+    ///
+    /// This also returns the number of nodes of the expanded node.
+    fn expand(&mut self, node: &Node) -> anyhow::Result<(Node, usize)> {
+        let mut size = 1;
+        let node = match node {
+            Node::Link(index) => return self.expand_object(*index),
+            Node::Array(values) => Node::Array(
+                values
+                    .iter()
+                    .map(|value| self.expand_child(value, &mut size))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            Node::Hash { pairs, default } => Node::Hash {
+                pairs: pairs
+                    .iter()
+                    .map(|(key, value)| {
+                        Ok((
+                            self.expand_child(key, &mut size)?,
+                            self.expand_child(value, &mut size)?,
+                        ))
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+                default: default
+                    .as_ref()
+                    .map(|default| self.expand_child(default, &mut size).map(Rc::new))
+                    .transpose()?,
+            },
+            Node::Object {
+                name,
+                instance_variables,
+            } => Node::Object {
+                name: name.clone(),
+                instance_variables: self
+                    .expand_instance_variables(instance_variables, &mut size)?,
+            },
+            Node::Struct { name, members } => Node::Struct {
+                name: name.clone(),
+                members: self.expand_instance_variables(members, &mut size)?,
+            },
+            Node::UserMarshal { name, value } => Node::UserMarshal {
+                name: name.clone(),
+                value: Rc::new(self.expand_child(value, &mut size)?),
+            },
+            Node::UserClass { name, value } => Node::UserClass {
+                name: name.clone(),
+                value: Rc::new(self.expand_child(value, &mut size)?),
+            },
+            Node::Extended { name, value } => Node::Extended {
+                name: name.clone(),
+                value: Rc::new(self.expand_child(value, &mut size)?),
+            },
+            Node::InstanceVariables {
+                value,
+                instance_variables,
+            } => Node::InstanceVariables {
+                value: Rc::new(self.expand_child(value, &mut size)?),
+                instance_variables: self
+                    .expand_instance_variables(instance_variables, &mut size)?,
+            },
+            node => node.clone(),
+        };
+
+        Ok((node, size))
+    }
+
+    /// Expand the object with the given index, or get it if it was already expanded.
+    fn expand_object(&mut self, index: usize) -> anyhow::Result<(Node, usize)> {
+        let object = self
+            .objects
+            .get(index)
+            .and_then(|node| node.as_ref())
+            .with_context(|| format!("invalid object link {index}"))?;
+
+        match &self.expanded[index] {
+            ExpandedObject::Unexpanded => {}
+            ExpandedObject::Expanding => bail!("cyclic object links are not supported"),
+            ExpandedObject::Expanded(node, size) => return Ok((node.clone(), *size)),
+        }
+
+        self.expanded[index] = ExpandedObject::Expanding;
+        let (node, size) = self.expand(object)?;
+        self.expanded[index] = ExpandedObject::Expanded(node.clone(), size);
+
+        Ok((node, size))
+    }
+
+    /// Expand a child node, adding its number of nodes to the size of its parent.
+    fn expand_child(&mut self, node: &Node, size: &mut usize) -> anyhow::Result<Node> {
+        let (node, node_size) = self.expand(node)?;
+        *size = size.saturating_add(node_size);
+        ensure!(
+            *size <= MAX_EXPANDED_NODES,
+            "expanding object links produces more than {MAX_EXPANDED_NODES} nodes"
+        );
+
+        Ok(node)
+    }
+
+    fn expand_instance_variables(
+        &mut self,
+        instance_variables: &InstanceVariables,
+        size: &mut usize,
+    ) -> anyhow::Result<InstanceVariables> {
+        instance_variables
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), self.expand_child(value, size)?)))
+            .collect()
+    }
+}
+
+/// Count a node and its children, where object links count as a single node.
+fn count_nodes(node: &Node) -> usize {
+    let count_instance_variables = |instance_variables: &InstanceVariables| -> usize {
+        instance_variables
+            .iter()
+            .map(|(_key, value)| count_nodes(value))
+            .sum()
+    };
+
+    let children = match node {
+        Node::Array(values) => values.iter().map(count_nodes).sum(),
+        Node::Hash { pairs, default } => {
+            pairs
+                .iter()
+                .map(|(key, value)| count_nodes(key) + count_nodes(value))
+                .sum::<usize>()
+                + default.as_deref().map_or(0, count_nodes)
+        }
+        Node::Object {
+            instance_variables, ..
+        } => count_instance_variables(instance_variables),
+        Node::Struct { members, .. } => count_instance_variables(members),
+        Node::UserMarshal { value, .. }
+        | Node::UserClass { value, .. }
+        | Node::Extended { value, .. } => count_nodes(value),
+        Node::InstanceVariables {
+            value,
+            instance_variables,
+        } => count_nodes(value) + count_instance_variables(instance_variables),
+        _ => 0,
+    };
+
+    children + 1
+}
+
+/// Encode a node, keeping object links.
+fn encode_node(node: &Node) -> anyhow::Result<String> {
+    let mut writer = Writer::new(false);
+    writer.write_value(node)?;
+    Ok(URL_SAFE.encode(writer.data))
+}
+
+/// Decode a node encoded with [`encode_node`].
+fn decode_node(data: &str) -> anyhow::Result<Node> {
+    let data = URL_SAFE
+        .decode(data)
+        .context("invalid base64 in replaced node")?;
+    let mut parser = Parser::new(&data, true);
+    parser.read_node()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        // [o(@b = "s", @a = {2 => 0, 1 => 0}), @2, 1.5]
+        let original = b"\x04\x08[\x08o:\x08Foo\x07:\x07@b\"\x06s:\x07@a{\x07i\x07i\x00i\x06i\x00@\x07f\x081.5";
+        // [o(@a = {1 => 0, 2 => 0}, @b = "s"), "s", 1.50]
+        let encoded = b"\x04\x08[\x08o:\x08Foo\x07:\x07@a{\x07i\x06i\x00i\x07i\x00:\x07@b\"\x06s\"\x06sf\x091.50";
+        // The same data, with the hash in another order.
+        let reencoded = b"\x04\x08[\x08o:\x08Foo\x07:\x07@a{\x07i\x07i\x00i\x06i\x00:\x07@b\"\x06s\"\x06sf\x091.50";
+
+        let layout = record(original, encoded).expect("failed to record layout");
+        let output = apply(reencoded, &layout).expect("failed to apply layout");
+        assert!(output == original);
+    }
+
+    #[test]
+    fn expand_links_is_bounded() {
+        // [[1], [@1, @1], [@2, @2], ...], where each array doubles in size when expanded.
+        let mut input = b"\x04\x08[\x2e[\x06i\x06".to_vec();
+        for index in 1..=40 {
+            input.extend([b'[', 0x07, b'@', index + 5, b'@', index + 5]);
+        }
+        let (_root, objects) = super::super::dedup::parse(&input, true).unwrap();
+
+        let mut link_expander = LinkExpander::new(&objects);
+        let (_node, size) = link_expander.expand(&Node::Link(11)).unwrap();
+        // The array at depth n has 3 * 2^n - 1 nodes.
+        assert!(size == 3 * (1 << 10) - 1);
+
+        let error = link_expander
+            .expand(&Node::Link(41))
+            .expect_err("exponential expansion was accepted");
+        assert!(error.to_string().contains("expanding object links"));
+    }
+}
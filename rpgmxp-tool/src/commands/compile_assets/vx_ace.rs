@@ -1,18 +1,23 @@
 use super::generate_map_infos_data;
 use super::generate_ruby_data;
 use super::set_extension_str;
-use super::FileSink;
+use super::Cache;
+use super::Output;
+use crate::lossless::Original;
 use anyhow::bail;
 use anyhow::ensure;
 use anyhow::Context;
+use rpgmvx_ace_types::CompressedScript;
 use rpgmvx_ace_types::Script;
-use rpgmvx_ace_types::ScriptList;
 use ruby_marshal::IntoValue;
 use std::collections::BTreeMap;
 use std::fs::File;
 use std::path::Path;
 
-fn generate_scripts_data_vx_ace(path: &Path) -> anyhow::Result<Vec<u8>> {
+fn generate_scripts_data_vx_ace(
+    path: &Path,
+    original: Option<&Original>,
+) -> anyhow::Result<Vec<u8>> {
     let mut scripts_map = BTreeMap::new();
 
     for dir_entry in path.read_dir()? {
@@ -53,13 +58,28 @@ fn generate_scripts_data_vx_ace(path: &Path) -> anyhow::Result<Vec<u8>> {
         }
     }
 
-    // TODO: Consider enforcing that script index ranges cannot have holes and must start at 0.
-    let script_list = ScriptList {
-        scripts: scripts_map.into_values().collect(),
-    };
+    // Reuse the original compressed data of unchanged scripts,
+    // since we cannot reproduce the original zlib settings.
+    let original_scripts = original.map_or(&[][..], |original| original.scripts.as_slice());
 
+    // TODO: Consider enforcing that script index ranges cannot have holes and must start at 0.
     let mut arena = ruby_marshal::ValueArena::new();
-    let handle = script_list.into_value(&mut arena)?;
+    let mut handles = Vec::with_capacity(scripts_map.len());
+    for (script_index, script) in scripts_map {
+        let handle = match original_scripts.get(script_index) {
+            Some(original_script) if original_script.matches(&script.name, &script.data) => {
+                CompressedScript {
+                    id: original_script.id,
+                    name: script.name,
+                    data: original_script.compressed_data()?,
+                }
+                .into_value(&mut arena)?
+            }
+            _ => script.into_value(&mut arena)?,
+        };
+        handles.push(handle);
+    }
+    let handle: ruby_marshal::ValueHandle = arena.create_array(handles).into();
     arena.replace_root(handle);
 
     let mut data = Vec::new();
@@ -73,16 +93,17 @@ pub fn compile(
     entry_file_type: std::fs::FileType,
     relative_path: &Path,
     relative_path_components: Vec<&str>,
-    file_sink: &mut FileSink,
+    original: Option<&Original>,
+    cache: &mut Cache,
+    output: &mut Output,
 ) -> anyhow::Result<()> {
     match relative_path_components.as_slice() {
         ["Data", "Scripts.rvdata2"] if entry_file_type.is_dir() => {
             println!("packing \"{}\"", relative_path.display());
 
-            let scripts_data = generate_scripts_data_vx_ace(entry_path)?;
-            let size = u32::try_from(scripts_data.len())?;
+            let scripts_data = generate_scripts_data_vx_ace(entry_path, original)?;
 
-            file_sink.write_file(&relative_path_components, size, &*scripts_data)?;
+            output.write_data(&relative_path_components, scripts_data)?;
         }
         ["Data", "Scripts.rvdata2", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -91,9 +112,8 @@ pub fn compile(
             println!("packing \"{}\"", relative_path.display());
 
             let data = generate_map_infos_data(entry_path)?;
-            let size = u32::try_from(data.len())?;
 
-            file_sink.write_file(&relative_path_components, size, &*data)?;
+            output.write_data(&relative_path_components, data)?;
         }
         ["Data", "MapInfos.rvdata2", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -101,14 +121,13 @@ pub fn compile(
         ["Data", file] if crate::util::is_map_file_name(file, "json") => {
             println!("packing \"{}\"", relative_path.display());
 
-            let map_data = generate_ruby_data::<rpgmvx_ace_types::Map>(entry_path)?;
-            let size = u32::try_from(map_data.len())?;
+            let map_data = generate_ruby_data::<rpgmvx_ace_types::Map>(entry_path, cache)?;
 
             let renamed_file = set_extension_str(file, "rvdata2");
             let mut relative_path_components = relative_path_components.clone();
             *relative_path_components.last_mut().unwrap() = renamed_file.as_str();
 
-            file_sink.write_file(&relative_path_components, size, &*map_data)?;
+            output.write_data(&relative_path_components, map_data)?;
         }
         relative_path_components if entry_file_type.is_file() => {
             // Copy file by default
@@ -120,10 +139,8 @@ pub fn compile(
                     entry_path.display()
                 )
             })?;
-            let metadata = input_file.metadata()?;
-            let size = u32::try_from(metadata.len())?;
 
-            file_sink.write_file(relative_path_components, size, input_file)?;
+            output.write_file(relative_path_components, input_file)?;
         }
         _ => {}
     }
@@ -3,7 +3,9 @@ use super::generate_map_infos_data;
 use super::generate_ruby_data;
 use super::generate_scripts_data;
 use super::set_extension_str;
-use super::FileSink;
+use super::Cache;
+use super::Output;
+use crate::lossless::Original;
 use anyhow::Context;
 use rpgmxp_types::Actor;
 use rpgmxp_types::Animation;
@@ -25,16 +27,17 @@ pub fn compile(
     entry_file_type: std::fs::FileType,
     relative_path: &Path,
     relative_path_components: Vec<&str>,
-    file_sink: &mut FileSink,
+    original: Option<&Original>,
+    cache: &mut Cache,
+    output: &mut Output,
 ) -> anyhow::Result<()> {
     match relative_path_components.as_slice() {
         ["Data", "Scripts.rxdata"] if entry_file_type.is_dir() => {
             println!("packing \"{}\"", relative_path.display());
 
-            let scripts_data = generate_scripts_data(entry_path)?;
-            let size = u32::try_from(scripts_data.len())?;
+            let scripts_data = generate_scripts_data(entry_path, original)?;
 
-            file_sink.write_file(&relative_path_components, size, &*scripts_data)?;
+            output.write_data(&relative_path_components, scripts_data)?;
         }
         ["Data", "Scripts.rxdata", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -42,10 +45,9 @@ pub fn compile(
         ["Data", "CommonEvents.rxdata"] if entry_file_type.is_dir() => {
             println!("packing \"{}\"", relative_path.display());
 
-            let rx_data = generate_arraylike_rx_data::<CommonEvent>(entry_path)?;
-            let size = u32::try_from(rx_data.len())?;
+            let rx_data = generate_arraylike_rx_data::<CommonEvent>(entry_path, cache)?;
 
-            file_sink.write_file(&relative_path_components, size, &*rx_data)?;
+            output.write_data(&relative_path_components, rx_data)?;
         }
         ["Data", "CommonEvents.rxdata", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -53,10 +55,9 @@ pub fn compile(
         ["Data", "Actors.rxdata"] if entry_file_type.is_dir() => {
             println!("packing \"{}\"", relative_path.display());
 
-            let rx_data = generate_arraylike_rx_data::<Actor>(entry_path)?;
-            let size = u32::try_from(rx_data.len())?;
+            let rx_data = generate_arraylike_rx_data::<Actor>(entry_path, cache)?;
 
-            file_sink.write_file(&relative_path_components, size, &*rx_data)?;
+            output.write_data(&relative_path_components, rx_data)?;
         }
         ["Data", "Actors.rxdata", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -64,10 +65,9 @@ pub fn compile(
         ["Data", "Weapons.rxdata"] if entry_file_type.is_dir() => {
             println!("packing \"{}\"", relative_path.display());
 
-            let rx_data = generate_arraylike_rx_data::<Weapon>(entry_path)?;
-            let size = u32::try_from(rx_data.len())?;
+            let rx_data = generate_arraylike_rx_data::<Weapon>(entry_path, cache)?;
 
-            file_sink.write_file(&relative_path_components, size, &*rx_data)?;
+            output.write_data(&relative_path_components, rx_data)?;
         }
         ["Data", "Weapons.rxdata", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -75,10 +75,9 @@ pub fn compile(
         ["Data", "Armors.rxdata"] if entry_file_type.is_dir() => {
             println!("packing \"{}\"", relative_path.display());
 
-            let rx_data = generate_arraylike_rx_data::<Armor>(entry_path)?;
-            let size = u32::try_from(rx_data.len())?;
+            let rx_data = generate_arraylike_rx_data::<Armor>(entry_path, cache)?;
 
-            file_sink.write_file(&relative_path_components, size, &*rx_data)?;
+            output.write_data(&relative_path_components, rx_data)?;
         }
         ["Data", "Armors.rxdata", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -86,10 +85,9 @@ pub fn compile(
         ["Data", "Skills.rxdata"] if entry_file_type.is_dir() => {
             println!("packing \"{}\"", relative_path.display());
 
-            let rx_data = generate_arraylike_rx_data::<Skill>(entry_path)?;
-            let size = u32::try_from(rx_data.len())?;
+            let rx_data = generate_arraylike_rx_data::<Skill>(entry_path, cache)?;
 
-            file_sink.write_file(&relative_path_components, size, &*rx_data)?;
+            output.write_data(&relative_path_components, rx_data)?;
         }
         ["Data", "Skills.rxdata", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -97,10 +95,9 @@ pub fn compile(
         ["Data", "States.rxdata"] if entry_file_type.is_dir() => {
             println!("packing \"{}\"", relative_path.display());
 
-            let rx_data = generate_arraylike_rx_data::<State>(entry_path)?;
-            let size = u32::try_from(rx_data.len())?;
+            let rx_data = generate_arraylike_rx_data::<State>(entry_path, cache)?;
 
-            file_sink.write_file(&relative_path_components, size, &*rx_data)?;
+            output.write_data(&relative_path_components, rx_data)?;
         }
         ["Data", "States.rxdata", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -108,10 +105,9 @@ pub fn compile(
         ["Data", "Items.rxdata"] if entry_file_type.is_dir() => {
             println!("packing \"{}\"", relative_path.display());
 
-            let rx_data = generate_arraylike_rx_data::<Item>(entry_path)?;
-            let size = u32::try_from(rx_data.len())?;
+            let rx_data = generate_arraylike_rx_data::<Item>(entry_path, cache)?;
 
-            file_sink.write_file(&relative_path_components, size, &*rx_data)?;
+            output.write_data(&relative_path_components, rx_data)?;
         }
         ["Data", "Items.rxdata", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -119,10 +115,9 @@ pub fn compile(
         ["Data", "Enemies.rxdata"] if entry_file_type.is_dir() => {
             println!("packing \"{}\"", relative_path.display());
 
-            let rx_data = generate_arraylike_rx_data::<Enemy>(entry_path)?;
-            let size = u32::try_from(rx_data.len())?;
+            let rx_data = generate_arraylike_rx_data::<Enemy>(entry_path, cache)?;
 
-            file_sink.write_file(&relative_path_components, size, &*rx_data)?;
+            output.write_data(&relative_path_components, rx_data)?;
         }
         ["Data", "Enemies.rxdata", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -130,10 +125,9 @@ pub fn compile(
         ["Data", "Classes.rxdata"] if entry_file_type.is_dir() => {
             println!("packing \"{}\"", relative_path.display());
 
-            let rx_data = generate_arraylike_rx_data::<Class>(entry_path)?;
-            let size = u32::try_from(rx_data.len())?;
+            let rx_data = generate_arraylike_rx_data::<Class>(entry_path, cache)?;
 
-            file_sink.write_file(&relative_path_components, size, &*rx_data)?;
+            output.write_data(&relative_path_components, rx_data)?;
         }
         ["Data", "Classes.rxdata", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -141,10 +135,9 @@ pub fn compile(
         ["Data", "Troops.rxdata"] if entry_file_type.is_dir() => {
             println!("packing \"{}\"", relative_path.display());
 
-            let rx_data = generate_arraylike_rx_data::<Troop>(entry_path)?;
-            let size = u32::try_from(rx_data.len())?;
+            let rx_data = generate_arraylike_rx_data::<Troop>(entry_path, cache)?;
 
-            file_sink.write_file(&relative_path_components, size, &*rx_data)?;
+            output.write_data(&relative_path_components, rx_data)?;
         }
         ["Data", "Troops.rxdata", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -152,10 +145,9 @@ pub fn compile(
         ["Data", "Tilesets.rxdata"] if entry_file_type.is_dir() => {
             println!("packing \"{}\"", relative_path.display());
 
-            let rx_data = generate_arraylike_rx_data::<Tileset>(entry_path)?;
-            let size = u32::try_from(rx_data.len())?;
+            let rx_data = generate_arraylike_rx_data::<Tileset>(entry_path, cache)?;
 
-            file_sink.write_file(&relative_path_components, size, &*rx_data)?;
+            output.write_data(&relative_path_components, rx_data)?;
         }
         ["Data", "Tilesets.rxdata", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -164,9 +156,8 @@ pub fn compile(
             println!("packing \"{}\"", relative_path.display());
 
             let rx_data = generate_map_infos_data(entry_path)?;
-            let size = u32::try_from(rx_data.len())?;
 
-            file_sink.write_file(&relative_path_components, size, &*rx_data)?;
+            output.write_data(&relative_path_components, rx_data)?;
         }
         ["Data", "MapInfos.rxdata", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -174,21 +165,19 @@ pub fn compile(
         ["Data", "System.json"] if entry_file_type.is_file() => {
             println!("packing \"{}\"", relative_path.display());
 
-            let data = generate_ruby_data::<rpgmxp_types::System>(entry_path)?;
-            let size = u32::try_from(data.len())?;
+            let data = generate_ruby_data::<rpgmxp_types::System>(entry_path, cache)?;
 
             let mut relative_path_components = relative_path_components.clone();
             *relative_path_components.last_mut().unwrap() = "System.rxdata";
 
-            file_sink.write_file(&relative_path_components, size, &*data)?;
+            output.write_data(&relative_path_components, data)?;
         }
         ["Data", "Animations.rxdata"] if entry_file_type.is_dir() => {
             println!("packing \"{}\"", relative_path.display());
 
-            let rx_data = generate_arraylike_rx_data::<Animation>(entry_path)?;
-            let size = u32::try_from(rx_data.len())?;
+            let rx_data = generate_arraylike_rx_data::<Animation>(entry_path, cache)?;
 
-            file_sink.write_file(&relative_path_components, size, &*rx_data)?;
+            output.write_data(&relative_path_components, rx_data)?;
         }
         ["Data", "Animations.rxdata", ..] => {
             // Ignore entries, we explore them in the above branch.
@@ -196,14 +185,13 @@ pub fn compile(
         ["Data", file] if crate::util::is_map_file_name(file, "json") => {
             println!("packing \"{}\"", relative_path.display());
 
-            let map_data = generate_ruby_data::<rpgmxp_types::Map>(entry_path)?;
-            let size = u32::try_from(map_data.len())?;
+            let map_data = generate_ruby_data::<rpgmxp_types::Map>(entry_path, cache)?;
 
             let renamed_file = set_extension_str(file, "rxdata");
             let mut relative_path_components = relative_path_components.clone();
             *relative_path_components.last_mut().unwrap() = renamed_file.as_str();
 
-            file_sink.write_file(&relative_path_components, size, &*map_data)?;
+            output.write_data(&relative_path_components, map_data)?;
         }
         relative_path_components if entry_file_type.is_file() => {
             // Copy file by default
@@ -215,10 +203,8 @@ pub fn compile(
                     entry_path.display()
                 )
             })?;
-            let metadata = input_file.metadata()?;
-            let size = u32::try_from(metadata.len())?;
 
-            file_sink.write_file(relative_path_components, size, input_file)?;
+            output.write_file(relative_path_components, input_file)?;
         }
         _ => {}
     }
@@ -0,0 +1,340 @@
+use crate::commands::extract_assets::parse_relative_path;
+use crate::commands::extract_assets::DataFileKind;
+use crate::commands::verify::decode_data_file;
+use anyhow::ensure;
+use anyhow::Context;
+use encoding_rs::Encoding;
+use rpgm_archive::FileEntryIter;
+use rpgm_archive::GameKind;
+use sha2::Digest;
+use sha2::Sha256;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The max length of a string value to show in a change.
+const MAX_STRING_DISPLAY_LEN: usize = 64;
+
+/// Containers with readable labels, as the key, label, and whether they are numbered from 1.
+const PATH_LABELS: &[(&str, &str, bool)] = &[
+    ("events", "event", false),
+    ("pages", "page", true),
+    ("list", "command", true),
+    ("parameters", "parameter", false),
+];
+
+#[derive(Debug, argh::FromArgs)]
+#[argh(
+    subcommand,
+    name = "diff",
+    description = "show the changes between two games or archives"
+)]
+pub struct Options {
+    #[argh(
+        positional,
+        description = "the path to the old game folder or rgssad archive"
+    )]
+    pub old: PathBuf,
+
+    #[argh(
+        positional,
+        description = "the path to the new game folder or rgssad archive"
+    )]
+    pub new: PathBuf,
+
+    #[argh(
+        option,
+        long = "key",
+        from_str_fn(crate::util::parse_key),
+        description = "the key to decrypt archives with, as a decimal or 0x-prefixed hex number. Defaults to the standard key."
+    )]
+    pub key: Option<u32>,
+
+    #[argh(
+        option,
+        long = "encoding",
+        from_str_fn(crate::encoding::parse),
+        description = "the encoding of strings in data files and scripts, like \"shift_jis\". Defaults to UTF-8."
+    )]
+    pub encoding: Option<&'static Encoding>,
+}
+
+/// A file in one of the inputs.
+#[derive(Debug)]
+struct InputFile {
+    /// A hash of the file data.
+    hash: [u8; 32],
+
+    /// The kind of data file, if this is a data file.
+    ///
+    /// The data of data files is kept so they can be decoded.
+    data_file: Option<(DataFileKind, Vec<u8>)>,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let (old_game_kind, old_files) = load_files(&options.old, options.key)?;
+    let (new_game_kind, new_files) = load_files(&options.new, options.key)?;
+
+    ensure!(
+        old_game_kind == new_game_kind,
+        "cannot diff a {old_game_kind:?} game with a {new_game_kind:?} game"
+    );
+    let game_kind = old_game_kind;
+    let encoding = options.encoding.unwrap_or(encoding_rs::UTF_8);
+    let _string_context = crate::encoding::string_context(encoding, game_kind).enter();
+
+    let paths: BTreeSet<&String> = old_files.keys().chain(new_files.keys()).collect();
+    for path in paths {
+        let (old_file, new_file) = match (old_files.get(path), new_files.get(path)) {
+            (Some(old_file), Some(new_file)) => (old_file, new_file),
+            (Some(_), None) => {
+                println!("removed \"{path}\"");
+                continue;
+            }
+            (None, Some(_)) => {
+                println!("added \"{path}\"");
+                continue;
+            }
+            (None, None) => unreachable!(),
+        };
+
+        if old_file.hash == new_file.hash {
+            continue;
+        }
+
+        let (Some((data_file_kind, old_data)), Some((_, new_data))) =
+            (old_file.data_file.as_ref(), new_file.data_file.as_ref())
+        else {
+            println!("changed \"{path}\"");
+            continue;
+        };
+
+        let old_value = decode_data_file(game_kind, *data_file_kind, &**old_data)
+            .with_context(|| format!("failed to decode old \"{path}\""))?;
+        let new_value = decode_data_file(game_kind, *data_file_kind, &**new_data)
+            .with_context(|| format!("failed to decode new \"{path}\""))?;
+
+        let mut changes = Vec::new();
+        diff_values(&mut Vec::new(), &old_value, &new_value, &mut changes);
+
+        // The data may only differ in its encoding.
+        if changes.is_empty() {
+            println!("changed \"{path}\" (encoding only)");
+            continue;
+        }
+
+        println!("changed \"{path}\":");
+        for change in changes {
+            println!("  {change}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Load the files of an input, keyed by their path.
+fn load_files(
+    path: &Path,
+    key: Option<u32>,
+) -> anyhow::Result<(GameKind, BTreeMap<String, InputFile>)> {
+    let mut file_entry_iter = FileEntryIter::new(path, None, key)?;
+    let game_kind = file_entry_iter.game_kind();
+
+    let mut files = BTreeMap::new();
+    while let Some(mut entry) = file_entry_iter.next_file_entry()? {
+        let raw_relative_path = entry.relative_path().to_path_buf();
+        let relative_path_components = parse_relative_path(&raw_relative_path)?;
+
+        let mut data = Vec::new();
+        entry
+            .read_to_end(&mut data)
+            .with_context(|| format!("failed to read \"{raw_relative_path}\""))?;
+
+        let hash = Sha256::digest(&data).into();
+        let data_file = DataFileKind::detect(game_kind, &relative_path_components)
+            .map(|data_file_kind| (data_file_kind, data));
+
+        files.insert(
+            relative_path_components.join("/"),
+            InputFile { hash, data_file },
+        );
+    }
+
+    Ok((game_kind, files))
+}
+
+/// Find the changes between two decoded values.
+///
+/// Each change is described with the path to the changed value, see [`format_path`].
+fn diff_values(
+    path: &mut Vec<String>,
+    old: &serde_json::Value,
+    new: &serde_json::Value,
+    changes: &mut Vec<String>,
+) {
+    use serde_json::Value;
+
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+            keys.sort_by(|a, b| compare_keys(a, b));
+            keys.dedup();
+
+            for key in keys {
+                path.push(key.clone());
+                match (old.get(key), new.get(key)) {
+                    (Some(old), Some(new)) => diff_values(path, old, new, changes),
+                    (Some(_), None) => changes.push(format!("{} removed", format_path(path))),
+                    (None, Some(_)) => changes.push(format!("{} added", format_path(path))),
+                    (None, None) => unreachable!(),
+                }
+                path.pop();
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for index in 0..std::cmp::max(old.len(), new.len()) {
+                path.push(index.to_string());
+                match (old.get(index), new.get(index)) {
+                    (Some(old), Some(new)) => diff_values(path, old, new, changes),
+                    (Some(_), None) => changes.push(format!("{} removed", format_path(path))),
+                    (None, Some(_)) => changes.push(format!("{} added", format_path(path))),
+                    (None, None) => unreachable!(),
+                }
+                path.pop();
+            }
+        }
+        (old, new) if old != new => {
+            let path = format_path(path);
+
+            match (display_value(old), display_value(new)) {
+                (Some(old), Some(new)) => {
+                    changes.push(format!("{path} changed from {old} to {new}"));
+                }
+                _ => changes.push(format!("{path} changed")),
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Format the path to a value.
+///
+/// Event ids, event pages, event commands, and command parameters are labelled,
+/// like "event 7 page 2 command 14 parameter 0".
+/// Pages and commands are numbered from 1, like the editor shows them.
+/// Other keys and indices are shown as they are in the extracted json.
+fn format_path(path: &[String]) -> String {
+    if path.is_empty() {
+        return "value".to_string();
+    }
+
+    let mut parts = Vec::with_capacity(path.len());
+    let mut iter = path.iter().peekable();
+    while let Some(key) = iter.next() {
+        let label = PATH_LABELS
+            .iter()
+            .find(|(label_key, _, _)| label_key == key);
+        let index = iter.peek().and_then(|index| index.parse::<usize>().ok());
+
+        match (label, index) {
+            (Some((_, label, one_based)), Some(index)) => {
+                iter.next();
+                let index = if *one_based { index + 1 } else { index };
+                parts.push(format!("{label} {index}"));
+            }
+            _ => parts.push(key.clone()),
+        }
+    }
+
+    parts.join(" ")
+}
+
+/// Compare object keys, ordering numeric keys by their value.
+fn compare_keys(a: &str, b: &str) -> Ordering {
+    match (a.parse::<i64>(), b.parse::<i64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+/// Format a value for a change, if it is short enough to show.
+fn display_value(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(value)
+            if value.len() > MAX_STRING_DISPLAY_LEN || value.contains('\n') =>
+        {
+            None
+        }
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => None,
+        value => Some(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn diff(old: serde_json::Value, new: serde_json::Value) -> Vec<String> {
+        let mut changes = Vec::new();
+        diff_values(&mut Vec::new(), &old, &new, &mut changes);
+        changes
+    }
+
+    #[test]
+    fn diff_event_command() {
+        let old = json!({
+            "events": {
+                "7": {
+                    "pages": [
+                        { "list": [] },
+                        { "list": [{ "code": 101, "parameters": [5] }] },
+                    ],
+                },
+            },
+        });
+        let mut new = old.clone();
+        new["events"]["7"]["pages"][1]["list"][0]["parameters"][0] = json!(6);
+
+        let changes = diff(old, new);
+        assert!(changes == ["event 7 page 2 command 1 parameter 0 changed from 5 to 6"]);
+    }
+
+    #[test]
+    fn diff_added_and_removed() {
+        let old = json!({ "name": "a", "list": [1, 2] });
+        let new = json!({ "name": "a", "note": "", "list": [1] });
+
+        let changes = diff(old, new);
+        assert!(changes == ["command 2 removed", "note added"]);
+    }
+
+    #[test]
+    fn diff_numeric_keys_in_order() {
+        let old = json!({ "2": 0, "10": 0 });
+        let new = json!({ "2": 1, "10": 1 });
+
+        let changes = diff(old, new);
+        assert!(changes == ["2 changed from 0 to 1", "10 changed from 0 to 1"]);
+    }
+
+    #[test]
+    fn diff_long_string() {
+        let old = json!({ "script": "a\nb" });
+        let new = json!({ "script": "a\nc" });
+
+        let changes = diff(old, new);
+        assert!(changes == ["script changed"]);
+    }
+
+    #[test]
+    fn diff_equal() {
+        let value = json!({ "events": { "1": { "name": "EV001" } } });
+
+        let changes = diff(value.clone(), value);
+        assert!(changes.is_empty());
+    }
+}
@@ -0,0 +1,304 @@
+use anyhow::Context;
+use rpgm_archive::code_page_name;
+use rpgm_archive::format_version;
+use rpgm_archive::guess_game_kind_from_exe;
+use rpgm_archive::language_name;
+use rpgm_archive::read_manifest;
+use rpgm_archive::read_version_info;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// The info of an exe.
+///
+/// Each section that fails to be read has its error recorded instead.
+#[derive(Debug, serde::Serialize)]
+struct ExeInfo {
+    game_kind: Option<String>,
+    game_kind_error: Option<String>,
+    fixed_file_info: Option<FixedFileInfo>,
+    translations: Vec<Translation>,
+    string_tables: Vec<StringTable>,
+    version_info_error: Option<String>,
+    assembly_identity: Option<AssemblyIdentity>,
+    manifest_description: Option<String>,
+    manifest_error: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct FixedFileInfo {
+    struct_version: u32,
+    file_version: String,
+    product_version: String,
+    file_flags_mask: u32,
+    file_flags: u32,
+    file_os: u32,
+    file_type: u32,
+    file_subtype: u32,
+    file_date: u64,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Translation {
+    language: u16,
+    language_name: Option<&'static str>,
+    code_page: u16,
+    code_page_name: Option<&'static str>,
+}
+
+impl Translation {
+    fn new(language: u16, code_page: u16) -> Self {
+        Self {
+            language,
+            language_name: language_name(language),
+            code_page,
+            code_page_name: code_page_name(code_page),
+        }
+    }
+}
+
+impl std::fmt::Display for Translation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "language 0x{:04X} ({}), code page {} ({})",
+            self.language,
+            self.language_name.unwrap_or("unknown"),
+            self.code_page,
+            self.code_page_name.unwrap_or("unknown")
+        )
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+struct StringTable {
+    key: String,
+    translation: Option<Translation>,
+    strings: Vec<(String, String)>,
+    errors: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct AssemblyIdentity {
+    name: String,
+    version: String,
+    processor_architecture: Option<String>,
+    #[serde(rename = "type")]
+    type_: String,
+}
+
+#[derive(Debug, argh::FromArgs)]
+#[argh(
+    subcommand,
+    name = "exe-info",
+    description = "show the version info and manifest of a game exe"
+)]
+pub struct Options {
+    #[argh(positional, description = "the path to the game folder or exe")]
+    pub input: PathBuf,
+
+    #[argh(switch, long = "json", description = "whether to output json")]
+    pub json: bool,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let path = if options.input.is_dir() {
+        options.input.join("Game.exe")
+    } else {
+        options.input
+    };
+    let game_exe =
+        std::fs::read(&path).with_context(|| format!("failed to read \"{}\"", path.display()))?;
+
+    let mut exe_info = ExeInfo {
+        game_kind: None,
+        game_kind_error: None,
+        fixed_file_info: None,
+        translations: Vec::new(),
+        string_tables: Vec::new(),
+        version_info_error: None,
+        assembly_identity: None,
+        manifest_description: None,
+        manifest_error: None,
+    };
+
+    match guess_game_kind_from_exe(&game_exe) {
+        Ok(game_kind) => {
+            exe_info.game_kind = game_kind.map(|game_kind| format!("{game_kind:?}"));
+        }
+        Err(error) => {
+            exe_info.game_kind_error = Some(format!("{error:#}"));
+        }
+    }
+
+    match read_version_info(&game_exe) {
+        Ok(Some(version_info)) => {
+            exe_info.fixed_file_info =
+                version_info
+                    .fixed_file_info
+                    .map(|fixed_file_info| FixedFileInfo {
+                        struct_version: fixed_file_info.struct_version,
+                        file_version: format_version(fixed_file_info.file_version),
+                        product_version: format_version(fixed_file_info.product_version),
+                        file_flags_mask: fixed_file_info.file_flags_mask,
+                        file_flags: fixed_file_info.file_flags,
+                        file_os: fixed_file_info.file_os,
+                        file_type: fixed_file_info.file_type,
+                        file_subtype: fixed_file_info.file_subtype,
+                        file_date: fixed_file_info.file_date,
+                    });
+
+            if let Some(var_file_info) = version_info.var_file_info {
+                exe_info.translations = var_file_info
+                    .translations
+                    .iter()
+                    .map(|translation| {
+                        Translation::new(translation.language, translation.code_page)
+                    })
+                    .collect();
+            }
+
+            if let Some(string_file_info) = version_info.string_file_info {
+                for table in string_file_info.children.iter() {
+                    let mut strings = Vec::with_capacity(table.children.len());
+                    let mut errors = Vec::new();
+                    for string in table.children.iter() {
+                        let key = string.key_str().to_string();
+                        match string.value_string() {
+                            Ok(value) => strings.push((key, value)),
+                            Err(error) => {
+                                errors.push(format!("failed to read \"{key}\": {error:#}"));
+                            }
+                        }
+                    }
+
+                    exe_info.string_tables.push(StringTable {
+                        key: table.key.clone(),
+                        translation: table
+                            .language()
+                            .zip(table.code_page())
+                            .map(|(language, code_page)| Translation::new(language, code_page)),
+                        strings,
+                        errors,
+                    });
+                }
+            }
+        }
+        Ok(None) => {}
+        Err(error) => {
+            exe_info.version_info_error = Some(format!("{error:#}"));
+        }
+    }
+
+    match read_manifest(&game_exe) {
+        Ok(Some(manifest)) => {
+            exe_info.assembly_identity =
+                manifest
+                    .assembly_identity
+                    .map(|assembly_identity| AssemblyIdentity {
+                        name: assembly_identity.name,
+                        version: assembly_identity.version,
+                        processor_architecture: assembly_identity.processor_architecture,
+                        type_: assembly_identity.type_,
+                    });
+            exe_info.manifest_description =
+                manifest.description.map(|description| description.value);
+        }
+        Ok(None) => {}
+        Err(error) => {
+            exe_info.manifest_error = Some(format!("{error:#}"));
+        }
+    }
+
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+
+    if options.json {
+        serde_json::to_writer_pretty(&mut stdout, &exe_info)?;
+        writeln!(stdout)?;
+    } else {
+        write_text(&mut stdout, &exe_info)?;
+    }
+
+    stdout.flush()?;
+
+    Ok(())
+}
+
+/// Write the exe info as text.
+fn write_text(mut writer: impl Write, exe_info: &ExeInfo) -> anyhow::Result<()> {
+    match exe_info.game_kind_error.as_ref() {
+        Some(error) => writeln!(writer, "failed to guess game type: {error}")?,
+        None => writeln!(
+            writer,
+            "game type: {}",
+            exe_info.game_kind.as_deref().unwrap_or("unknown")
+        )?,
+    }
+
+    if let Some(fixed_file_info) = exe_info.fixed_file_info.as_ref() {
+        writeln!(writer, "file version: {}", fixed_file_info.file_version)?;
+        writeln!(
+            writer,
+            "product version: {}",
+            fixed_file_info.product_version
+        )?;
+        writeln!(
+            writer,
+            "file flags: 0x{:08X} (mask 0x{:08X})",
+            fixed_file_info.file_flags, fixed_file_info.file_flags_mask
+        )?;
+        writeln!(writer, "file os: 0x{:08X}", fixed_file_info.file_os)?;
+        writeln!(
+            writer,
+            "file type: 0x{:08X} (subtype 0x{:08X})",
+            fixed_file_info.file_type, fixed_file_info.file_subtype
+        )?;
+        writeln!(writer, "file date: {}", fixed_file_info.file_date)?;
+        writeln!(
+            writer,
+            "fixed file info struct version: 0x{:08X}",
+            fixed_file_info.struct_version
+        )?;
+    }
+
+    for translation in exe_info.translations.iter() {
+        writeln!(writer, "translation: {translation}")?;
+    }
+
+    for table in exe_info.string_tables.iter() {
+        match table.translation.as_ref() {
+            Some(translation) => writeln!(writer, "string table {}: {translation}", table.key)?,
+            None => writeln!(writer, "string table {:?}: invalid key", table.key)?,
+        }
+        for (key, value) in table.strings.iter() {
+            writeln!(writer, "  {key}: {value:?}")?;
+        }
+        for error in table.errors.iter() {
+            writeln!(writer, "  {error}")?;
+        }
+    }
+
+    if let Some(error) = exe_info.version_info_error.as_ref() {
+        writeln!(writer, "failed to read version info: {error}")?;
+    }
+
+    if let Some(assembly_identity) = exe_info.assembly_identity.as_ref() {
+        writeln!(writer, "manifest assembly identity:")?;
+        writeln!(writer, "  name: {}", assembly_identity.name)?;
+        writeln!(writer, "  version: {}", assembly_identity.version)?;
+        if let Some(processor_architecture) = assembly_identity.processor_architecture.as_ref() {
+            writeln!(writer, "  processor architecture: {processor_architecture}")?;
+        }
+        writeln!(writer, "  type: {}", assembly_identity.type_)?;
+    }
+
+    if let Some(manifest_description) = exe_info.manifest_description.as_ref() {
+        writeln!(writer, "manifest description: {manifest_description}")?;
+    }
+
+    if let Some(error) = exe_info.manifest_error.as_ref() {
+        writeln!(writer, "failed to read manifest: {error}")?;
+    }
+
+    Ok(())
+}
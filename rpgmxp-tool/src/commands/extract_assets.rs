@@ -1,22 +1,117 @@
-mod file_entry_iter;
 mod vx;
 mod vx_ace;
 mod xp;
 
-use self::file_entry_iter::FileEntry;
-use self::file_entry_iter::FileEntryIter;
 use crate::util::ArrayLikeElement;
-use crate::GameKind;
+use crate::util::FilePathSet;
+use crate::util::GlobPattern;
 use anyhow::bail;
 use anyhow::ensure;
 use anyhow::Context;
 use camino::Utf8Path;
+use encoding_rs::Encoding;
+use rpgm_archive::game_ini::GameIni;
+use rpgm_archive::FileEntryIter;
+use rpgm_archive::GameKind;
+use rpgm_common_types::StringContext;
 use ruby_marshal::FromValueContext;
 use std::collections::BTreeMap;
 use std::fs::File;
+use std::io::Read;
 use std::io::Write;
+use std::num::NonZeroUsize;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// A kind of data file that can be converted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DataFileKind {
+    Scripts,
+    CommonEvents,
+    Actors,
+    Weapons,
+    Armors,
+    Skills,
+    States,
+    Items,
+    Enemies,
+    Classes,
+    Troops,
+    Tilesets,
+    MapInfos,
+    System,
+    Animations,
+    Map,
+}
+
+impl DataFileKind {
+    /// Detect the kind of data file from the given path components.
+    ///
+    /// # Returns
+    /// Returns `None` if the path is not a data file that can be converted for the given game.
+    pub fn detect(game_kind: GameKind, relative_path_components: &[&str]) -> Option<Self> {
+        match game_kind {
+            GameKind::Xp => self::xp::detect_data_file_kind(relative_path_components),
+            GameKind::Vx => self::vx::detect_data_file_kind(relative_path_components),
+            GameKind::VxAce => self::vx_ace::detect_data_file_kind(relative_path_components),
+        }
+    }
+
+    /// Get the name of this kind.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Scripts => "Scripts",
+            Self::CommonEvents => "CommonEvents",
+            Self::Actors => "Actors",
+            Self::Weapons => "Weapons",
+            Self::Armors => "Armors",
+            Self::Skills => "Skills",
+            Self::States => "States",
+            Self::Items => "Items",
+            Self::Enemies => "Enemies",
+            Self::Classes => "Classes",
+            Self::Troops => "Troops",
+            Self::Tilesets => "Tilesets",
+            Self::MapInfos => "MapInfos",
+            Self::System => "System",
+            Self::Animations => "Animations",
+            Self::Map => "Map",
+        }
+    }
+}
+
+/// Serialize a value as pretty json.
+///
+/// Strings that were extracted as bytes are reported with their json pointers.
+fn to_json<T>(value: &T, path: &Path) -> anyhow::Result<Vec<u8>>
+where
+    T: serde::Serialize,
+{
+    let json = serde_json::to_vec_pretty(value)?;
+
+    // Finding the locations is slow, so only do it if there is a byte string.
+    let prefix = rpgm_common_types::BYTE_STRING_PREFIX.as_bytes();
+    if json.windows(prefix.len()).any(|window| window == prefix) {
+        let value = serde_json::to_value(value)?;
+        for pointer in crate::util::find_byte_strings(&value) {
+            warn_byte_string(path, &pointer);
+        }
+    }
+
+    Ok(json)
+}
+
+/// Report a string that was not valid in the string encoding, and was extracted as bytes.
+fn warn_byte_string(path: &Path, location: &str) {
+    eprintln!(
+        "  \"{}\" at \"{location}\" is not valid {}, so it was extracted as bytes",
+        path.display(),
+        StringContext::current().encoding.name()
+    );
+}
 
 fn extract_ruby_data<T>(file: impl std::io::Read, path: impl AsRef<Path>) -> anyhow::Result<()>
 where
@@ -32,8 +127,9 @@ where
     // TODO: Lock?
     // TODO: Drop delete guard for file?
     let temp_path = nd_util::with_push_extension(&path, "temp");
+    let json = to_json(&data, &path)?;
     let mut file = File::create_new(&temp_path)?;
-    serde_json::to_writer_pretty(&mut file, &data)?;
+    file.write_all(&json)?;
     file.flush()?;
     file.sync_all()?;
     std::fs::rename(temp_path, path)?;
@@ -74,8 +170,9 @@ where
 
         // TODO: Lock?
         // TODO: Drop delete guard for file?
+        let json = to_json(value, &out_path)?;
         let mut output_file = File::create_new(&temp_path)?;
-        serde_json::to_writer_pretty(&mut output_file, value)?;
+        output_file.write_all(&json)?;
         output_file.flush()?;
         output_file.sync_all()?;
         drop(output_file);
@@ -104,11 +201,18 @@ where
     for (script_index, script) in script_list.scripts.iter().enumerate() {
         println!("  extracting script \"{}\"", script.name);
 
-        let escaped_script_name = crate::util::percent_escape_file_name(&script.name);
-
-        let out_path = temp_dir_path.join(format!("{script_index:03}-{escaped_script_name}.rb"));
+        let file_name = format!("{script_index:03}-{}.rb", script.name);
+        let file_name = crate::util::percent_escape_file_name(&file_name);
+        let out_path = temp_dir_path.join(&file_name);
         let temp_path = nd_util::with_push_extension(&out_path, "temp");
 
+        if rpgm_common_types::is_byte_string(&script.name) {
+            warn_byte_string(&dir_path.join(&file_name), "name");
+        }
+        if rpgm_common_types::is_byte_string(&script.data) {
+            warn_byte_string(&dir_path.join(&file_name), "data");
+        }
+
         // TODO: Lock?
         // TODO: Drop delete guard for file?
         std::fs::write(&temp_path, &script.data)?;
@@ -137,13 +241,16 @@ where
 
         println!("  extracting map info \"{name}\"");
 
-        let out_path = dir_path.join(format!("{index:03}-{name}.json"));
+        let file_name = format!("{index:03}-{name}.json");
+        let file_name = crate::util::percent_escape_file_name(&file_name);
+        let out_path = dir_path.join(file_name);
         let temp_path = nd_util::with_push_extension(&out_path, "temp");
 
         // TODO: Lock?
         // TODO: Drop delete guard for file?
+        let json = to_json(value, &out_path)?;
         let mut output_file = File::create_new(&temp_path)?;
-        serde_json::to_writer_pretty(&mut output_file, value)?;
+        output_file.write_all(&json)?;
         output_file.flush()?;
         output_file.sync_all()?;
         drop(output_file);
@@ -177,6 +284,65 @@ pub struct Options {
     )]
     pub overwrite: bool,
 
+    #[argh(
+        option,
+        long = "game",
+        short = 'g',
+        description = "the game type. Defaults to detecting from the input."
+    )]
+    pub game: Option<GameKind>,
+
+    #[argh(
+        option,
+        long = "encoding",
+        from_str_fn(crate::encoding::parse),
+        description = "the encoding of strings in data files and scripts, like \"shift_jis\". Defaults to UTF-8."
+    )]
+    pub encoding: Option<&'static Encoding>,
+
+    #[argh(
+        option,
+        long = "include",
+        description = "a glob of files to extract, like \"Data/Map0*.rxdata\". May be repeated. If not provided, all files are included."
+    )]
+    pub include: Vec<GlobPattern>,
+
+    #[argh(
+        option,
+        long = "exclude",
+        description = "a glob of files to not extract, like \"Graphics/Characters/**\". May be repeated."
+    )]
+    pub exclude: Vec<GlobPattern>,
+
+    #[argh(
+        option,
+        long = "key",
+        from_str_fn(crate::util::parse_key),
+        description = "the key to decrypt archives with, as a decimal or 0x-prefixed hex number. Defaults to the standard key."
+    )]
+    pub key: Option<u32>,
+
+    #[argh(
+        switch,
+        long = "lossless",
+        description = "whether to record how data files were encoded, so that compiling unmodified files reproduces them exactly"
+    )]
+    pub lossless: bool,
+
+    #[argh(
+        switch,
+        long = "recover-key",
+        description = "whether to recover the archive key instead of using the standard key"
+    )]
+    pub recover_key: bool,
+
+    #[argh(
+        option,
+        long = "jobs",
+        description = "the number of threads to decode data files with. Defaults to 1."
+    )]
+    pub jobs: Option<NonZeroUsize>,
+
     #[argh(
         switch,
         long = "skip-extract-scripts",
@@ -290,6 +456,23 @@ pub struct Options {
     pub skip_extract_maps: bool,
 }
 
+impl Options {
+    /// Check if a file should be extracted, based on the include and exclude globs.
+    fn should_extract(&self, relative_path_components: &[&str]) -> bool {
+        let included = self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|glob| glob.matches(relative_path_components));
+        let excluded = self
+            .exclude
+            .iter()
+            .any(|glob| glob.matches(relative_path_components));
+
+        included && !excluded
+    }
+}
+
 pub fn exec(mut options: Options) -> anyhow::Result<()> {
     options.input = options
         .input
@@ -313,43 +496,312 @@ pub fn exec(mut options: Options) -> anyhow::Result<()> {
         .canonicalize()
         .context("failed to canonicalize output path")?;
 
-    let mut file_entry_iter = FileEntryIter::new(&options.input)?;
+    // Record the encoding, so that compiling uses the same one.
+    let encoding = options.encoding.unwrap_or(encoding_rs::UTF_8);
+    crate::encoding::record(&options.output, encoding)?;
+
+    let key = if options.recover_key {
+        ensure!(
+            options.key.is_none(),
+            "--key and --recover-key cannot be used together"
+        );
+
+        let key = crate::commands::recover_key::recover_key_path(&options.input)?;
+        eprintln!("recovered key 0x{key:08X}");
+        Some(key)
+    } else {
+        options.key
+    };
+
+    let mut file_entry_iter = FileEntryIter::new(&options.input, options.game, key)?;
     let game_kind = file_entry_iter.game_kind();
+    let string_context = crate::encoding::string_context(encoding, game_kind);
+    let _string_context = string_context.clone().enter();
+
+    // If the input is an archive, the ini file is next to it.
+    let game_dir = if options.input.is_dir() {
+        Some(options.input.as_path())
+    } else {
+        options.input.parent()
+    };
+    let game_ini = game_dir
+        .map(|game_dir| GameIni::load(game_dir, options.encoding))
+        .transpose()?
+        .flatten();
+    if let Some(game_ini) = game_ini {
+        if let Some(title) = game_ini.title.as_deref() {
+            println!("extracting game \"{title}\"");
+        }
 
-    while let Some(mut entry) = file_entry_iter.next_file_entry()? {
-        let raw_relative_path = entry.relative_path().to_path_buf();
-        let relative_path_components = parse_relative_path(&raw_relative_path)?;
-        let relative_path_display = relative_path_components.join("/");
-        let output_path = {
-            let mut output_path = options.output.clone();
-            output_path.extend(relative_path_components.clone());
-            output_path
-        };
+        if game_ini.encoding_is_ambiguous {
+            eprintln!(
+                "{} was guessed to be {}, pass --encoding if the title is wrong",
+                rpgm_archive::game_ini::FILE_NAME,
+                game_ini.encoding.name()
+            );
+        }
 
-        println!("extracting \"{relative_path_display}\"");
+        if let Some(scripts_path_components) = game_ini.scripts_path_components() {
+            if DataFileKind::detect(game_kind, &scripts_path_components)
+                != Some(DataFileKind::Scripts)
+            {
+                eprintln!(
+                    "{} uses the non-standard scripts path \"{}\", which will be extracted as a plain file",
+                    rpgm_archive::game_ini::FILE_NAME,
+                    scripts_path_components.join("/")
+                );
+            }
+        }
+    }
 
-        if let Some(parent) = output_path.parent() {
-            std::fs::create_dir_all(parent)
-                .with_context(|| format!("failed to create dir at \"{}\"", parent.display()))?;
+    let jobs = options.jobs.map_or(1, NonZeroUsize::get);
+
+    // Check that the extracted files do not overwrite each other on any platform.
+    let mut paths = FilePathSet::new();
+
+    if jobs == 1 {
+        while let Some(mut entry) = file_entry_iter.next_file_entry()? {
+            let raw_relative_path = entry.relative_path().to_path_buf();
+            let Some((relative_path_components, output_path)) =
+                prepare_entry(&options, &mut paths, &raw_relative_path)?
+            else {
+                continue;
+            };
+
+            let is_data_file = DataFileKind::detect(game_kind, &relative_path_components).is_some();
+            if options.lossless && is_data_file {
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data)?;
+
+                extract_data_file(
+                    &options,
+                    game_kind,
+                    relative_path_components,
+                    output_path,
+                    &data,
+                )?;
+            } else {
+                extract_file(
+                    &options,
+                    game_kind,
+                    &mut entry,
+                    relative_path_components,
+                    output_path,
+                )?;
+            }
         }
 
-        match game_kind {
-            GameKind::Xp => {
-                self::xp::extract(&options, &mut entry, relative_path_components, output_path)?
+        return Ok(());
+    }
+
+    // Entries must be read in order, but data files can be decoded independently.
+    // Every data file is extracted to its own path,
+    // so the output does not depend on the order that workers finish in.
+    std::thread::scope(|scope| {
+        let (job_tx, job_rx) = std::sync::mpsc::sync_channel::<DataFileJob>(jobs);
+        let job_rx = Arc::new(Mutex::new(job_rx));
+
+        let options = &options;
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                let job_rx = job_rx.clone();
+                let string_context = string_context.clone();
+                scope.spawn(move || {
+                    let _string_context = string_context.enter();
+                    run_worker(options, game_kind, &job_rx)
+                })
+            })
+            .collect();
+        // Only the workers should hold the receiver,
+        // so sending fails if every worker has exited.
+        drop(job_rx);
+
+        let mut result = Ok(());
+        loop {
+            let mut entry = match file_entry_iter.next_file_entry() {
+                Ok(Some(entry)) => entry,
+                Ok(None) => break,
+                Err(error) => {
+                    result = Err(error);
+                    break;
+                }
+            };
+
+            let raw_relative_path = entry.relative_path().to_path_buf();
+            let prepared = match prepare_entry(options, &mut paths, &raw_relative_path) {
+                Ok(Some(prepared)) => prepared,
+                Ok(None) => continue,
+                Err(error) => {
+                    result = Err(error);
+                    break;
+                }
+            };
+            let (relative_path_components, output_path) = prepared;
+
+            if DataFileKind::detect(game_kind, &relative_path_components).is_none() {
+                if let Err(error) = extract_file(
+                    options,
+                    game_kind,
+                    &mut entry,
+                    relative_path_components,
+                    output_path,
+                ) {
+                    result = Err(error);
+                    break;
+                }
+                continue;
             }
-            GameKind::Vx => {
-                self::vx::extract(&options, &mut entry, relative_path_components, output_path)?
+
+            let mut data = Vec::new();
+            if let Err(error) = entry.read_to_end(&mut data) {
+                result = Err(error.into());
+                break;
+            }
+
+            let job = DataFileJob {
+                relative_path_components: relative_path_components
+                    .iter()
+                    .map(|component| component.to_string())
+                    .collect(),
+                output_path,
+                data,
+            };
+            if job_tx.send(job).is_err() {
+                // All workers exited early, so one of them has the error.
+                break;
             }
-            GameKind::VxAce => {
-                self::vx_ace::extract(&options, &mut entry, relative_path_components, output_path)?
+        }
+        drop(job_tx);
+
+        for worker in workers {
+            let worker_result = match worker.join() {
+                Ok(worker_result) => worker_result,
+                Err(panic) => std::panic::resume_unwind(panic),
+            };
+
+            if result.is_ok() {
+                result = worker_result;
             }
         }
+
+        result
+    })
+}
+
+/// Parse the path of an entry and create the parent dir of its escaped output path.
+///
+/// # Returns
+/// Returns `None` if the entry should not be extracted.
+fn prepare_entry<'a>(
+    options: &Options,
+    paths: &mut FilePathSet,
+    raw_relative_path: &'a Utf8Path,
+) -> anyhow::Result<Option<(Vec<&'a str>, PathBuf)>> {
+    let relative_path_components = parse_relative_path(raw_relative_path)?;
+
+    if !options.should_extract(&relative_path_components) {
+        return Ok(None);
+    }
+
+    let relative_path_display = relative_path_components.join("/");
+    let escaped_relative_path_components = paths
+        .insert(&relative_path_components)
+        .with_context(|| format!("cannot extract \"{relative_path_display}\""))?;
+    let output_path = {
+        let mut output_path = options.output.clone();
+        output_path.extend(escaped_relative_path_components);
+        output_path
+    };
+
+    println!("extracting \"{relative_path_display}\"");
+
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create dir at \"{}\"", parent.display()))?;
+    }
+
+    Ok(Some((relative_path_components, output_path)))
+}
+
+/// A data file that was read from an archive, waiting to be extracted by a worker.
+struct DataFileJob {
+    relative_path_components: Vec<String>,
+    output_path: PathBuf,
+    data: Vec<u8>,
+}
+
+/// Extract data files until there are no more jobs.
+fn run_worker(
+    options: &Options,
+    game_kind: GameKind,
+    job_rx: &Mutex<Receiver<DataFileJob>>,
+) -> anyhow::Result<()> {
+    loop {
+        // Only hold the lock while waiting for a job, so other workers can run.
+        let job = match job_rx.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return Ok(()),
+        };
+
+        let relative_path_components: Vec<&str> = job
+            .relative_path_components
+            .iter()
+            .map(|component| component.as_str())
+            .collect();
+        let relative_path_display = relative_path_components.join("/");
+        extract_data_file(
+            options,
+            game_kind,
+            relative_path_components,
+            job.output_path,
+            &job.data,
+        )
+        .with_context(|| format!("failed to extract \"{relative_path_display}\""))?;
+    }
+}
+
+/// Extract a data file that was already read into memory.
+///
+/// This records how the data was encoded if the extraction is lossless.
+fn extract_data_file(
+    options: &Options,
+    game_kind: GameKind,
+    relative_path_components: Vec<&str>,
+    output_path: PathBuf,
+    data: &[u8],
+) -> anyhow::Result<()> {
+    extract_file(
+        options,
+        game_kind,
+        &mut &*data,
+        relative_path_components.clone(),
+        output_path,
+    )?;
+
+    if options.lossless {
+        crate::lossless::record(&options.output, game_kind, &relative_path_components, data)?;
     }
 
     Ok(())
 }
 
-fn parse_relative_path(path: &Utf8Path) -> anyhow::Result<Vec<&str>> {
+fn extract_file(
+    options: &Options,
+    game_kind: GameKind,
+    entry: &mut impl std::io::Read,
+    relative_path_components: Vec<&str>,
+    output_path: PathBuf,
+) -> anyhow::Result<()> {
+    match game_kind {
+        GameKind::Xp => self::xp::extract(options, entry, relative_path_components, output_path),
+        GameKind::Vx => self::vx::extract(options, entry, relative_path_components, output_path),
+        GameKind::VxAce => {
+            self::vx_ace::extract(options, entry, relative_path_components, output_path)
+        }
+    }
+}
+
+pub fn parse_relative_path(path: &Utf8Path) -> anyhow::Result<Vec<&str>> {
     let mut components = Vec::with_capacity(4);
 
     // There is a lot of problems with using proper path parsing here.
@@ -381,3 +833,55 @@ fn parse_relative_path(path: &Utf8Path) -> anyhow::Result<Vec<&str>> {
 
     Ok(components)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use argh::FromArgs;
+    use rpgm_archive::FileSink;
+
+    #[test]
+    fn lossless_round_trip() {
+        // {2 => MapInfo(@name = "Map", ...), 1 => MapInfo(@name = @2, ...)},
+        // with instance variables in a different order than the encoder uses.
+        let original = b"\x04\x08{\x07i\x07o:\x11RPG::MapInfo\x0b:\x0a@name\"\x08Map:\x0f@parent_idi\x00:\x0b@orderi\x07:\x0e@expandedF:\x0e@scroll_xi\x00:\x0e@scroll_yi\x00i\x06o;\x00\x0b;\x06@\x07;\x07i\x00;\x08i\x06;\x09F;\x0ai\x00;\x0bi\x00";
+
+        let base_path = std::env::temp_dir().join(format!(
+            "rpgmxp-tool-lossless-round-trip-{}",
+            std::process::id()
+        ));
+        let extracted_path = base_path.join("extracted");
+        let compiled_path = base_path.join("compiled");
+        std::fs::create_dir_all(extracted_path.join("Data")).expect("failed to create dir");
+
+        let options = Options::from_args(
+            &["extract-assets"],
+            &["input", extracted_path.to_str().unwrap(), "--lossless"],
+        )
+        .expect("failed to parse options");
+        extract_data_file(
+            &options,
+            GameKind::Xp,
+            vec!["Data", "MapInfos.rxdata"],
+            extracted_path.join("Data").join("MapInfos.rxdata"),
+            original,
+        )
+        .expect("failed to extract");
+
+        let mut file_sink = FileSink::new_dir(&compiled_path, false).expect("failed to open sink");
+        crate::commands::compile_assets::compile_dir(
+            &extracted_path,
+            GameKind::Xp,
+            &mut crate::commands::compile_assets::Cache::new_disabled(),
+            &mut file_sink,
+        )
+        .expect("failed to compile");
+        file_sink.finish().expect("failed to finish");
+
+        let compiled = std::fs::read(compiled_path.join("Data").join("MapInfos.rxdata"))
+            .expect("failed to read compiled data");
+        std::fs::remove_dir_all(&base_path).expect("failed to remove dir");
+
+        assert!(compiled == original);
+    }
+}
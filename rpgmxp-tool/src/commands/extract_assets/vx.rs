@@ -1,31 +1,40 @@
 use super::extract_map_infos;
 use super::extract_ruby_data;
 use super::extract_scripts;
-use super::FileEntry;
+use super::DataFileKind;
 use super::Options;
 use anyhow::Context;
 use std::fs::File;
 use std::path::PathBuf;
 
+/// Detect the kind of data file from the given path components.
+pub fn detect_data_file_kind(relative_path_components: &[&str]) -> Option<DataFileKind> {
+    match relative_path_components {
+        ["Data", "Scripts.rvdata"] => Some(DataFileKind::Scripts),
+        ["Data", "MapInfos.rvdata"] => Some(DataFileKind::MapInfos),
+        ["Data", "System.rvdata"] => Some(DataFileKind::System),
+        ["Data", file] if crate::util::is_map_file_name(file, "rvdata") => Some(DataFileKind::Map),
+        _ => None,
+    }
+}
+
 pub fn extract(
     options: &Options,
-    entry: &mut FileEntry<'_>,
+    entry: &mut impl std::io::Read,
     relative_path_components: Vec<&str>,
     output_path: PathBuf,
 ) -> anyhow::Result<()> {
-    match relative_path_components.as_slice() {
-        ["Data", "Scripts.rvdata"] if !options.skip_extract_scripts => {
+    match detect_data_file_kind(&relative_path_components) {
+        Some(DataFileKind::Scripts) if !options.skip_extract_scripts => {
             extract_scripts(entry, output_path)?;
         }
-        ["Data", "MapInfos.rvdata"] if !options.skip_extract_map_infos => {
+        Some(DataFileKind::MapInfos) if !options.skip_extract_map_infos => {
             extract_map_infos(entry, output_path)?;
         }
-        ["Data", "System.rvdata"] if !options.skip_extract_system => {
+        Some(DataFileKind::System) if !options.skip_extract_system => {
             extract_ruby_data::<rpgmvx_types::System>(entry, output_path)?;
         }
-        ["Data", file]
-            if !options.skip_extract_maps && crate::util::is_map_file_name(file, "rvdata") =>
-        {
+        Some(DataFileKind::Map) if !options.skip_extract_maps => {
             extract_ruby_data::<rpgmvx_types::Map>(entry, output_path)?;
         }
         _ => {
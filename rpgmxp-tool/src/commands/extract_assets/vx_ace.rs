@@ -1,6 +1,7 @@
 use super::extract_map_infos;
 use super::extract_ruby_data;
-use super::FileEntry;
+use super::warn_byte_string;
+use super::DataFileKind;
 use super::Options;
 use anyhow::Context;
 use ruby_marshal::FromValueContext;
@@ -26,11 +27,18 @@ where
     for (script_index, script) in script_list.scripts.iter().enumerate() {
         println!("  extracting script \"{}\"", script.name);
 
-        let escaped_script_name = crate::util::percent_escape_file_name(&script.name);
-
-        let out_path = temp_dir_path.join(format!("{script_index:03}-{escaped_script_name}.rb"));
+        let file_name = format!("{script_index:03}-{}.rb", script.name);
+        let file_name = crate::util::percent_escape_file_name(&file_name);
+        let out_path = temp_dir_path.join(&file_name);
         let temp_path = nd_util::with_push_extension(&out_path, "temp");
 
+        if rpgm_common_types::is_byte_string(&script.name) {
+            warn_byte_string(&dir_path.join(&file_name), "name");
+        }
+        if rpgm_common_types::is_byte_string(&script.data) {
+            warn_byte_string(&dir_path.join(&file_name), "data");
+        }
+
         // TODO: Lock?
         // TODO: Drop delete guard for file?
         std::fs::write(&temp_path, &script.data)?;
@@ -42,22 +50,30 @@ where
     Ok(())
 }
 
+/// Detect the kind of data file from the given path components.
+pub fn detect_data_file_kind(relative_path_components: &[&str]) -> Option<DataFileKind> {
+    match relative_path_components {
+        ["Data", "Scripts.rvdata2"] => Some(DataFileKind::Scripts),
+        ["Data", "MapInfos.rvdata2"] => Some(DataFileKind::MapInfos),
+        ["Data", file] if crate::util::is_map_file_name(file, "rvdata2") => Some(DataFileKind::Map),
+        _ => None,
+    }
+}
+
 pub fn extract(
     options: &Options,
-    entry: &mut FileEntry<'_>,
+    entry: &mut impl std::io::Read,
     relative_path_components: Vec<&str>,
     output_path: PathBuf,
 ) -> anyhow::Result<()> {
-    match relative_path_components.as_slice() {
-        ["Data", "Scripts.rvdata2"] if !options.skip_extract_scripts => {
+    match detect_data_file_kind(&relative_path_components) {
+        Some(DataFileKind::Scripts) if !options.skip_extract_scripts => {
             extract_scripts_vx_ace(entry, output_path)?;
         }
-        ["Data", "MapInfos.rvdata2"] if !options.skip_extract_map_infos => {
+        Some(DataFileKind::MapInfos) if !options.skip_extract_map_infos => {
             extract_map_infos(entry, output_path)?;
         }
-        ["Data", file]
-            if !options.skip_extract_maps && crate::util::is_map_file_name(file, "rvdata2") =>
-        {
+        Some(DataFileKind::Map) if !options.skip_extract_maps => {
             extract_ruby_data::<rpgmvx_ace_types::Map>(entry, output_path)?;
         }
         _ => {
@@ -2,7 +2,7 @@ use super::extract_arraylike;
 use super::extract_map_infos;
 use super::extract_ruby_data;
 use super::extract_scripts;
-use super::FileEntry;
+use super::DataFileKind;
 use super::Options;
 use anyhow::Context;
 use rpgmxp_types::Actor;
@@ -20,61 +20,82 @@ use rpgmxp_types::Weapon;
 use std::fs::File;
 use std::path::PathBuf;
 
+/// Detect the kind of data file from the given path components.
+pub fn detect_data_file_kind(relative_path_components: &[&str]) -> Option<DataFileKind> {
+    match relative_path_components {
+        ["Data", "Scripts.rxdata"] => Some(DataFileKind::Scripts),
+        ["Data", "CommonEvents.rxdata"] => Some(DataFileKind::CommonEvents),
+        ["Data", "Actors.rxdata"] => Some(DataFileKind::Actors),
+        ["Data", "Weapons.rxdata"] => Some(DataFileKind::Weapons),
+        ["Data", "Armors.rxdata"] => Some(DataFileKind::Armors),
+        ["Data", "Skills.rxdata"] => Some(DataFileKind::Skills),
+        ["Data", "States.rxdata"] => Some(DataFileKind::States),
+        ["Data", "Items.rxdata"] => Some(DataFileKind::Items),
+        ["Data", "Enemies.rxdata"] => Some(DataFileKind::Enemies),
+        ["Data", "Classes.rxdata"] => Some(DataFileKind::Classes),
+        ["Data", "Troops.rxdata"] => Some(DataFileKind::Troops),
+        ["Data", "Tilesets.rxdata"] => Some(DataFileKind::Tilesets),
+        ["Data", "MapInfos.rxdata"] => Some(DataFileKind::MapInfos),
+        ["Data", "System.rxdata"] => Some(DataFileKind::System),
+        ["Data", "Animations.rxdata"] => Some(DataFileKind::Animations),
+        ["Data", file] if crate::util::is_map_file_name(file, "rxdata") => Some(DataFileKind::Map),
+        _ => None,
+    }
+}
+
 pub fn extract(
     options: &Options,
-    entry: &mut FileEntry<'_>,
+    entry: &mut impl std::io::Read,
     relative_path_components: Vec<&str>,
     output_path: PathBuf,
 ) -> anyhow::Result<()> {
-    match relative_path_components.as_slice() {
-        ["Data", "Scripts.rxdata"] if !options.skip_extract_scripts => {
+    match detect_data_file_kind(&relative_path_components) {
+        Some(DataFileKind::Scripts) if !options.skip_extract_scripts => {
             extract_scripts(entry, output_path)?;
         }
-        ["Data", "CommonEvents.rxdata"] if !options.skip_extract_common_events => {
+        Some(DataFileKind::CommonEvents) if !options.skip_extract_common_events => {
             extract_arraylike::<CommonEvent>(entry, output_path)?;
         }
-        ["Data", "Actors.rxdata"] if !options.skip_extract_actors => {
+        Some(DataFileKind::Actors) if !options.skip_extract_actors => {
             extract_arraylike::<Actor>(entry, output_path)?;
         }
-        ["Data", "Weapons.rxdata"] if !options.skip_extract_weapons => {
+        Some(DataFileKind::Weapons) if !options.skip_extract_weapons => {
             extract_arraylike::<Weapon>(entry, output_path)?;
         }
-        ["Data", "Armors.rxdata"] if !options.skip_extract_armors => {
+        Some(DataFileKind::Armors) if !options.skip_extract_armors => {
             extract_arraylike::<Armor>(entry, output_path)?;
         }
-        ["Data", "Skills.rxdata"] if !options.skip_extract_skills => {
+        Some(DataFileKind::Skills) if !options.skip_extract_skills => {
             extract_arraylike::<Skill>(entry, output_path)?;
         }
-        ["Data", "States.rxdata"] if !options.skip_extract_states => {
+        Some(DataFileKind::States) if !options.skip_extract_states => {
             extract_arraylike::<State>(entry, output_path)?;
         }
-        ["Data", "Items.rxdata"] if !options.skip_extract_items => {
+        Some(DataFileKind::Items) if !options.skip_extract_items => {
             extract_arraylike::<Item>(entry, output_path)?;
         }
-        ["Data", "Enemies.rxdata"] if !options.skip_extract_enemies => {
+        Some(DataFileKind::Enemies) if !options.skip_extract_enemies => {
             extract_arraylike::<Enemy>(entry, output_path)?;
         }
-        ["Data", "Classes.rxdata"] if !options.skip_extract_classes => {
+        Some(DataFileKind::Classes) if !options.skip_extract_classes => {
             extract_arraylike::<Class>(entry, output_path)?;
         }
-        ["Data", "Troops.rxdata"] if !options.skip_extract_troops => {
+        Some(DataFileKind::Troops) if !options.skip_extract_troops => {
             extract_arraylike::<Troop>(entry, output_path)?;
         }
-        ["Data", "Tilesets.rxdata"] if !options.skip_extract_tilesets => {
+        Some(DataFileKind::Tilesets) if !options.skip_extract_tilesets => {
             extract_arraylike::<Tileset>(entry, output_path)?;
         }
-        ["Data", "MapInfos.rxdata"] if !options.skip_extract_map_infos => {
+        Some(DataFileKind::MapInfos) if !options.skip_extract_map_infos => {
             extract_map_infos(entry, output_path)?;
         }
-        ["Data", "System.rxdata"] if !options.skip_extract_system => {
+        Some(DataFileKind::System) if !options.skip_extract_system => {
             extract_ruby_data::<rpgmxp_types::System>(entry, output_path)?;
         }
-        ["Data", "Animations.rxdata"] if !options.skip_extract_animations => {
+        Some(DataFileKind::Animations) if !options.skip_extract_animations => {
             extract_arraylike::<Animation>(entry, output_path)?;
         }
-        ["Data", file]
-            if !options.skip_extract_maps && crate::util::is_map_file_name(file, "rxdata") =>
-        {
+        Some(DataFileKind::Map) if !options.skip_extract_maps => {
             extract_ruby_data::<rpgmxp_types::Map>(entry, output_path)?;
         }
         _ => {
@@ -0,0 +1,108 @@
+use crate::ico::parse_group_icon;
+use crate::ico::Icon;
+use crate::ico::IconImage;
+use anyhow::Context;
+use rpgm_archive::read_resources;
+use rpgm_archive::ResourceId;
+use std::path::PathBuf;
+
+/// The resource type of icon images.
+pub const RT_ICON: u16 = 3;
+
+/// The resource type of icon groups.
+pub const RT_GROUP_ICON: u16 = 14;
+
+#[derive(Debug, argh::FromArgs)]
+#[argh(
+    subcommand,
+    name = "extract-icon",
+    description = "extract the icon of a game exe"
+)]
+pub struct Options {
+    #[argh(positional, description = "the path to the game folder or exe")]
+    pub input: PathBuf,
+
+    #[argh(positional, description = "the path to write the ico file to")]
+    pub output: PathBuf,
+
+    #[argh(
+        option,
+        long = "png-dir",
+        description = "a folder to write each image of the icon to as a png"
+    )]
+    pub png_dir: Option<PathBuf>,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let path = if options.input.is_dir() {
+        options.input.join("Game.exe")
+    } else {
+        options.input
+    };
+    let game_exe =
+        std::fs::read(&path).with_context(|| format!("failed to read \"{}\"", path.display()))?;
+
+    let icon = read_icon(&game_exe)?;
+
+    let mut ico = Vec::new();
+    icon.write_ico(&mut ico)?;
+    std::fs::write(&options.output, ico)
+        .with_context(|| format!("failed to write \"{}\"", options.output.display()))?;
+
+    if let Some(png_dir) = options.png_dir {
+        std::fs::create_dir_all(&png_dir)
+            .with_context(|| format!("failed to create \"{}\"", png_dir.display()))?;
+
+        for image in icon.images.iter() {
+            let width = image.pixel_width();
+            let height = image.pixel_height();
+            let file_name = format!("{width}x{height}-{}bpp.png", image.bit_count);
+            let path = png_dir.join(file_name);
+
+            println!("writing \"{}\"", path.display());
+
+            let png = image
+                .to_png()
+                .with_context(|| format!("failed to convert {width}x{height} image to png"))?;
+            std::fs::write(&path, png)
+                .with_context(|| format!("failed to write \"{}\"", path.display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the main icon of an exe.
+///
+/// This is the first icon group, which is the one Windows shows for the exe.
+pub fn read_icon(game_exe: &[u8]) -> anyhow::Result<Icon> {
+    let group_icons = read_resources(game_exe, RT_GROUP_ICON)?;
+    let group_icon = group_icons.first().context("exe has no icon")?;
+    let entries = parse_group_icon(group_icon.data)
+        .with_context(|| format!("failed to parse icon group {}", group_icon.id))?;
+
+    let icon_images = read_resources(game_exe, RT_ICON)?;
+
+    let mut images = Vec::with_capacity(entries.len());
+    for entry in entries {
+        // Prefer the image with the same language as the group.
+        let id = ResourceId::Id(entry.id);
+        let icon_image = icon_images
+            .iter()
+            .find(|icon_image| icon_image.id == id && icon_image.language == group_icon.language)
+            .or_else(|| icon_images.iter().find(|icon_image| icon_image.id == id))
+            .with_context(|| format!("missing icon image {id}"))?;
+
+        images.push(IconImage {
+            width: entry.width,
+            height: entry.height,
+            color_count: entry.color_count,
+            reserved: entry.reserved,
+            planes: entry.planes,
+            bit_count: entry.bit_count,
+            data: icon_image.data.to_vec(),
+        });
+    }
+
+    Ok(Icon { images })
+}
@@ -0,0 +1,95 @@
+use crate::commands::extract_assets::parse_relative_path;
+use crate::commands::extract_assets::DataFileKind;
+use anyhow::Context;
+use rpgm_archive::FileEntryIter;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// A listed file entry.
+#[derive(Debug, serde::Serialize)]
+struct ListEntry {
+    path: String,
+    size: u64,
+    offset: Option<u64>,
+    kind: Option<&'static str>,
+}
+
+#[derive(Debug, argh::FromArgs)]
+#[argh(
+    subcommand,
+    name = "list",
+    description = "list the files in a game folder or archive"
+)]
+pub struct Options {
+    #[argh(
+        positional,
+        description = "the path to the game folder or rgssad archive"
+    )]
+    pub input: PathBuf,
+
+    #[argh(switch, long = "json", description = "whether to output json")]
+    pub json: bool,
+
+    #[argh(
+        option,
+        long = "key",
+        from_str_fn(crate::util::parse_key),
+        description = "the key to decrypt archives with, as a decimal or 0x-prefixed hex number. Defaults to the standard key."
+    )]
+    pub key: Option<u32>,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let mut file_entry_iter = FileEntryIter::new(&options.input, None, options.key)?;
+    let game_kind = file_entry_iter.game_kind();
+
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+
+    let mut entries = Vec::new();
+    if !options.json {
+        writeln!(
+            stdout,
+            "{:>10} {:>10} {:<12} path",
+            "offset", "size", "kind"
+        )?;
+    }
+    while let Some(entry) = file_entry_iter.next_file_entry()? {
+        let raw_relative_path = entry.relative_path().to_path_buf();
+        let relative_path_components = parse_relative_path(&raw_relative_path)
+            .with_context(|| format!("invalid path \"{raw_relative_path}\""))?;
+
+        let list_entry = ListEntry {
+            path: relative_path_components.join("/"),
+            size: entry.size(),
+            offset: entry.offset(),
+            kind: DataFileKind::detect(game_kind, &relative_path_components)
+                .map(DataFileKind::as_str),
+        };
+
+        if options.json {
+            entries.push(list_entry);
+        } else {
+            let offset = list_entry
+                .offset
+                .map(|offset| offset.to_string())
+                .unwrap_or_else(|| "-".to_string());
+            let kind = list_entry.kind.unwrap_or("-");
+
+            writeln!(
+                stdout,
+                "{offset:>10} {:>10} {kind:<12} {}",
+                list_entry.size, list_entry.path
+            )?;
+        }
+    }
+
+    if options.json {
+        serde_json::to_writer_pretty(&mut stdout, &entries)?;
+        writeln!(stdout)?;
+    }
+
+    stdout.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,240 @@
+use crate::commands::compile_assets::compile_dir;
+use crate::commands::compile_assets::Cache;
+use crate::commands::extract_assets::parse_relative_path;
+use crate::util::GlobPattern;
+use anyhow::ensure;
+use anyhow::Context;
+use encoding_rs::Encoding;
+use rpgm_archive::FileEntryIter;
+use rpgm_archive::FileSink;
+use rpgm_archive::GameKind;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+use std::path::PathBuf;
+use walkdir::WalkDir;
+
+/// A compiled file that will be added to the archive.
+#[derive(Debug)]
+struct PatchEntry {
+    relative_path_components: Vec<String>,
+    path: PathBuf,
+}
+
+/// Load the compiled patch files.
+///
+/// The returned map is keyed by the lowercase, '/'-joined relative path,
+/// since archive paths are case-insensitive.
+fn load_patch_entries(path: &Path) -> anyhow::Result<BTreeMap<String, PatchEntry>> {
+    let mut entries = BTreeMap::new();
+
+    for entry in WalkDir::new(path) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let entry_path = entry.into_path();
+        let relative_path_components = entry_path
+            .strip_prefix(path)?
+            .iter()
+            .map(|component| {
+                component
+                    .to_str()
+                    .map(String::from)
+                    .context("non-unicode path")
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let key = relative_path_components.join("/").to_ascii_lowercase();
+        entries.insert(
+            key,
+            PatchEntry {
+                relative_path_components,
+                path: entry_path,
+            },
+        );
+    }
+
+    Ok(entries)
+}
+
+fn write_patch_entry(
+    file_sink: &mut FileSink,
+    relative_path_components: &[&str],
+    path: &Path,
+) -> anyhow::Result<()> {
+    let file = File::open(path)
+        .with_context(|| format!("failed to open patch file from \"{}\"", path.display()))?;
+
+    file_sink.write_file(relative_path_components, file)?;
+
+    Ok(())
+}
+
+#[derive(Debug, argh::FromArgs)]
+#[argh(
+    subcommand,
+    name = "patch-archive",
+    description = "add, replace, or remove files in an archive, copying everything else unchanged"
+)]
+pub struct Options {
+    #[argh(positional, description = "the path to the archive to patch")]
+    pub input: PathBuf,
+
+    #[argh(positional, description = "the path to write the patched archive to")]
+    pub output: PathBuf,
+
+    #[argh(
+        option,
+        long = "patch",
+        description = "a folder of files to add or replace, in the same layout as extracted assets. Extracted json files are compiled."
+    )]
+    pub patch: Option<PathBuf>,
+
+    #[argh(
+        option,
+        long = "remove",
+        description = "a glob of files to remove from the archive. May be repeated."
+    )]
+    pub remove: Vec<GlobPattern>,
+
+    #[argh(
+        option,
+        long = "key",
+        from_str_fn(crate::util::parse_key),
+        description = "the key to decrypt the input archive and encrypt the output archive with, as a decimal or 0x-prefixed hex number. Defaults to the standard key."
+    )]
+    pub key: Option<u32>,
+
+    #[argh(
+        option,
+        long = "encoding",
+        from_str_fn(crate::encoding::parse),
+        description = "the encoding of strings in data files and scripts, like \"shift_jis\". Defaults to the encoding recorded in the patch folder, or UTF-8."
+    )]
+    pub encoding: Option<&'static Encoding>,
+
+    #[argh(
+        switch,
+        long = "overwrite",
+        description = "whether overwrite the output if it exists"
+    )]
+    pub overwrite: bool,
+}
+
+pub fn exec(mut options: Options) -> anyhow::Result<()> {
+    options.input = options
+        .input
+        .canonicalize()
+        .context("failed to canonicalize input path")?;
+
+    if let Ok(output) = options.output.canonicalize() {
+        ensure!(
+            output != options.input,
+            "the output path cannot be the same as the input path"
+        );
+    }
+
+    let mut file_entry_iter = FileEntryIter::new_rgssad_path(&options.input, None, options.key)?;
+    let game_kind = file_entry_iter.game_kind();
+
+    // Compile the patch with the normal converters first,
+    // so that the archive can be patched with plain files.
+    let patch_temp_path = nd_util::with_push_extension(&options.output, "patch.temp");
+    let _patch_temp_dir = options.patch.as_ref().map(|_| TempDirGuard {
+        path: patch_temp_path.clone(),
+    });
+    let mut patch_entries = match options.patch.as_ref() {
+        Some(patch_path) => {
+            let patch_path = patch_path
+                .canonicalize()
+                .context("failed to canonicalize patch path")?;
+
+            let encoding = match options.encoding {
+                Some(encoding) => encoding,
+                None => crate::encoding::load(&patch_path)?.unwrap_or(encoding_rs::UTF_8),
+            };
+            let _string_context = crate::encoding::string_context(encoding, game_kind).enter();
+
+            let mut file_sink = FileSink::new_dir(&patch_temp_path, true)?;
+            compile_dir(
+                &patch_path,
+                game_kind,
+                &mut Cache::new_disabled(),
+                &mut file_sink,
+            )?;
+            file_sink.finish()?;
+
+            load_patch_entries(&patch_temp_path)?
+        }
+        None => BTreeMap::new(),
+    };
+
+    let mut file_sink = match game_kind {
+        GameKind::Xp | GameKind::Vx => {
+            FileSink::new_rgssad(&options.output, options.overwrite, options.key)?
+        }
+        GameKind::VxAce => FileSink::new_rgss3a(&options.output, options.overwrite, options.key)?,
+    };
+
+    while let Some(mut entry) = file_entry_iter.next_file_entry()? {
+        let raw_relative_path = entry.relative_path().to_path_buf();
+        let relative_path_components = parse_relative_path(&raw_relative_path)?;
+        let relative_path_display = relative_path_components.join("/");
+
+        // Keep the original name when replacing a file, in case the case differs.
+        let key = relative_path_display.to_ascii_lowercase();
+        if let Some(patch_entry) = patch_entries.remove(&key) {
+            println!("replacing \"{relative_path_display}\"");
+
+            write_patch_entry(&mut file_sink, &relative_path_components, &patch_entry.path)?;
+            continue;
+        }
+
+        if options
+            .remove
+            .iter()
+            .any(|glob| glob.matches(&relative_path_components))
+        {
+            println!("removing \"{relative_path_display}\"");
+
+            continue;
+        }
+
+        file_sink.write_file(&relative_path_components, &mut entry)?;
+    }
+
+    for patch_entry in patch_entries.into_values() {
+        let relative_path_components: Vec<&str> = patch_entry
+            .relative_path_components
+            .iter()
+            .map(|component| component.as_str())
+            .collect();
+        println!("adding \"{}\"", relative_path_components.join("/"));
+
+        write_patch_entry(&mut file_sink, &relative_path_components, &patch_entry.path)?;
+    }
+
+    file_sink.finish()?;
+
+    Ok(())
+}
+
+/// A temporary dir that is deleted when dropped, even if patching fails.
+struct TempDirGuard {
+    path: PathBuf,
+}
+
+impl Drop for TempDirGuard {
+    fn drop(&mut self) {
+        if let Err(error) = std::fs::remove_dir_all(&self.path) {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                eprintln!(
+                    "failed to remove temp dir at \"{}\": {error}",
+                    self.path.display()
+                );
+            }
+        }
+    }
+}
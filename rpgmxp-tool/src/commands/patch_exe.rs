@@ -0,0 +1,220 @@
+use crate::commands::extract_icon::RT_GROUP_ICON;
+use crate::commands::extract_icon::RT_ICON;
+use crate::ico::parse_group_icon;
+use crate::ico::Icon;
+use crate::pe_resources::replace_resources;
+use crate::pe_resources::ResourceEntry;
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use object::pe::RT_VERSION;
+use rpgm_archive::parse_version_info;
+use rpgm_archive::read_all_resources;
+use rpgm_archive::ResourceId;
+use std::path::PathBuf;
+
+/// The language of new resources, if there is nothing to copy it from.
+const LANG_NEUTRAL: u16 = 0;
+
+#[derive(Debug, argh::FromArgs)]
+#[argh(
+    subcommand,
+    name = "patch-exe",
+    description = "replace the icon and version strings of a game exe"
+)]
+pub struct Options {
+    #[argh(positional, description = "the path to the game folder or exe")]
+    pub input: PathBuf,
+
+    #[argh(positional, description = "the path to write the patched exe to")]
+    pub output: PathBuf,
+
+    #[argh(
+        option,
+        long = "icon",
+        description = "the path to an ico file to replace the icon with"
+    )]
+    pub icon: Option<PathBuf>,
+
+    #[argh(
+        option,
+        long = "string",
+        from_str_fn(parse_string_value),
+        description = "a version string to set, like \"ProductName=My Game\". May be repeated."
+    )]
+    pub strings: Vec<(String, String)>,
+
+    #[argh(
+        switch,
+        long = "overwrite",
+        description = "whether to overwrite the output if it exists"
+    )]
+    pub overwrite: bool,
+}
+
+/// Parse a version string value, like "ProductName=My Game".
+fn parse_string_value(value: &str) -> Result<(String, String), String> {
+    let (key, value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("\"{value}\" is not in the form KEY=VALUE"))?;
+    if key.is_empty() {
+        return Err("version string key is empty".into());
+    }
+
+    Ok((key.into(), value.into()))
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let path = if options.input.is_dir() {
+        options.input.join("Game.exe")
+    } else {
+        options.input
+    };
+    ensure!(
+        options.icon.is_some() || !options.strings.is_empty(),
+        "nothing to patch. Use --icon or --string."
+    );
+    ensure!(
+        options.overwrite || !options.output.try_exists()?,
+        "output path exists"
+    );
+
+    let game_exe =
+        std::fs::read(&path).with_context(|| format!("failed to read \"{}\"", path.display()))?;
+
+    let mut resources: Vec<ResourceEntry> = read_all_resources(&game_exe)?
+        .into_iter()
+        .map(|resource| ResourceEntry {
+            kind: resource.kind,
+            id: resource.id,
+            language: resource.language,
+            data: resource.data.to_vec(),
+        })
+        .collect();
+
+    if let Some(icon_path) = options.icon.as_ref() {
+        let ico = std::fs::read(icon_path)
+            .with_context(|| format!("failed to read \"{}\"", icon_path.display()))?;
+        let icon = Icon::parse_ico(&ico)
+            .with_context(|| format!("failed to parse \"{}\"", icon_path.display()))?;
+        ensure!(!icon.images.is_empty(), "the icon has no images");
+
+        replace_icon(&mut resources, &icon)?;
+    }
+
+    if !options.strings.is_empty() {
+        set_version_strings(&mut resources, &options.strings)?;
+    }
+
+    let output = replace_resources(&game_exe, &resources)?;
+    std::fs::write(&options.output, output)
+        .with_context(|| format!("failed to write \"{}\"", options.output.display()))?;
+
+    Ok(())
+}
+
+/// Replace the main icon group and its images.
+///
+/// If the exe has no icon, one is added.
+fn replace_icon(resources: &mut Vec<ResourceEntry>, icon: &Icon) -> anyhow::Result<()> {
+    let group_kind = ResourceId::Id(RT_GROUP_ICON);
+    let image_kind = ResourceId::Id(RT_ICON);
+
+    // The images of the old group are removed, unless another group uses them.
+    let group_index = resources
+        .iter()
+        .position(|resource| resource.kind == group_kind);
+    let (group_id, language) = match group_index {
+        Some(group_index) => {
+            let group = resources.remove(group_index);
+            let old_ids: Vec<u16> = parse_group_icon(&group.data)
+                .with_context(|| format!("failed to parse icon group {}", group.id))?
+                .iter()
+                .map(|entry| entry.id)
+                .collect();
+
+            let mut shared_ids = Vec::new();
+            for resource in resources
+                .iter()
+                .filter(|resource| resource.kind == group_kind)
+            {
+                if let Ok(entries) = parse_group_icon(&resource.data) {
+                    shared_ids.extend(entries.iter().map(|entry| entry.id));
+                }
+            }
+
+            resources.retain(|resource| {
+                let ResourceId::Id(id) = resource.id else {
+                    return true;
+                };
+                resource.kind != image_kind || !old_ids.contains(&id) || shared_ids.contains(&id)
+            });
+
+            (group.id, group.language)
+        }
+        None => (ResourceId::Id(1), LANG_NEUTRAL),
+    };
+
+    let mut ids = Vec::with_capacity(icon.images.len());
+    let mut next_id: u16 = 1;
+    for image in icon.images.iter() {
+        while resources
+            .iter()
+            .any(|resource| resource.kind == image_kind && resource.id == ResourceId::Id(next_id))
+        {
+            next_id = next_id.checked_add(1).context("out of icon image ids")?;
+        }
+
+        ids.push(next_id);
+        resources.push(ResourceEntry {
+            kind: image_kind.clone(),
+            id: ResourceId::Id(next_id),
+            language,
+            data: image.data.clone(),
+        });
+    }
+
+    resources.push(ResourceEntry {
+        kind: group_kind,
+        id: group_id,
+        language,
+        data: icon.to_group_icon(&ids)?,
+    });
+
+    Ok(())
+}
+
+/// Set strings in every string table of the version resource.
+fn set_version_strings(
+    resources: &mut [ResourceEntry],
+    strings: &[(String, String)],
+) -> anyhow::Result<()> {
+    let version_kind = ResourceId::Id(RT_VERSION);
+
+    let mut found = false;
+    for resource in resources
+        .iter_mut()
+        .filter(|resource| resource.kind == version_kind)
+    {
+        let mut version_info = parse_version_info(&resource.data)
+            .with_context(|| format!("failed to parse version info {}", resource.id))?;
+        let Some(string_file_info) = version_info.string_file_info.as_mut() else {
+            continue;
+        };
+
+        for table in string_file_info.children.iter_mut() {
+            for (key, value) in strings.iter() {
+                table.set_value(key, value);
+            }
+        }
+
+        resource.data = version_info.to_bytes()?;
+        found = true;
+    }
+
+    if !found {
+        bail!("the exe has no version strings to update");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use anyhow::bail;
+use anyhow::Context;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::path::PathBuf;
+
+#[derive(Debug, argh::FromArgs)]
+#[argh(
+    subcommand,
+    name = "recover-key",
+    description = "recover the key of an archive that uses a non-standard key"
+)]
+pub struct Options {
+    #[argh(
+        positional,
+        description = "the path to the game folder or rgssad archive"
+    )]
+    pub input: PathBuf,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let key = recover_key_path(&options.input)?;
+    println!("0x{key:08X}");
+
+    Ok(())
+}
+
+/// Recover the key of the archive at the given path.
+///
+/// If the path is a game folder, its archive is used.
+/// For rgss3a archives, this is the base key.
+pub fn recover_key_path(path: &Path) -> anyhow::Result<u32> {
+    let path = if path.is_dir() {
+        find_archive(path)?
+    } else {
+        path.to_path_buf()
+    };
+
+    let file =
+        File::open(&path).with_context(|| format!("failed to open \"{}\"", path.display()))?;
+    let key = rpgm_archive::recover_key(BufReader::new(file))?;
+
+    key.with_context(|| format!("failed to recover the key for \"{}\"", path.display()))
+}
+
+/// Find the archive in a game folder.
+fn find_archive(path: &Path) -> anyhow::Result<PathBuf> {
+    for file_name in ["Game.rgssad", "Game.rgss2a", "Game.rgss3a"] {
+        let archive_path = path.join(file_name);
+        if archive_path
+            .try_exists()
+            .with_context(|| format!("failed to access \"{}\"", archive_path.display()))?
+        {
+            return Ok(archive_path);
+        }
+    }
+
+    bail!("no archive found in \"{}\"", path.display());
+}
@@ -0,0 +1,176 @@
+use crate::commands::extract_assets::parse_relative_path;
+use crate::commands::extract_assets::DataFileKind;
+use crate::util::FilePathSet;
+use anyhow::bail;
+use anyhow::Context;
+use encoding_rs::Encoding;
+use rpgm_archive::FileEntryIter;
+use rpgm_archive::GameKind;
+use ruby_marshal::FromValueContext;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Debug, argh::FromArgs)]
+#[argh(
+    subcommand,
+    name = "verify",
+    description = "check an archive for corruption, exiting with an error if any problems are found"
+)]
+pub struct Options {
+    #[argh(positional, description = "the path to the rgssad archive")]
+    pub input: PathBuf,
+
+    #[argh(
+        option,
+        long = "key",
+        from_str_fn(crate::util::parse_key),
+        description = "the key to decrypt the archive with, as a decimal or 0x-prefixed hex number. Defaults to the standard key."
+    )]
+    pub key: Option<u32>,
+
+    #[argh(
+        option,
+        long = "encoding",
+        from_str_fn(crate::encoding::parse),
+        description = "the encoding of strings in data files and scripts, like \"shift_jis\". Defaults to UTF-8."
+    )]
+    pub encoding: Option<&'static Encoding>,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let archive_len = std::fs::metadata(&options.input)
+        .with_context(|| format!("failed to get metadata for \"{}\"", options.input.display()))?
+        .len();
+    let mut file_entry_iter = FileEntryIter::new_rgssad_path(&options.input, None, options.key)?;
+    let game_kind = file_entry_iter.game_kind();
+    let encoding = options.encoding.unwrap_or(encoding_rs::UTF_8);
+    let _string_context = crate::encoding::string_context(encoding, game_kind).enter();
+
+    let mut problems = Vec::new();
+    let mut paths = FilePathSet::new();
+    // Start at the end of an empty archive.
+    let mut data_end = if game_kind.is_vx_ace() {
+        8 + 4 + (4 * 4)
+    } else {
+        8
+    };
+    loop {
+        let mut entry = match file_entry_iter.next_file_entry() {
+            Ok(Some(entry)) => entry,
+            Ok(None) => break,
+            Err(error) => {
+                // The position of the next entry is unknown, so we cannot continue.
+                problems.push(format!(
+                    "failed to read the entry after offset {data_end}: {error:#}"
+                ));
+                break;
+            }
+        };
+
+        let raw_relative_path = entry.relative_path().to_string();
+        let offset = entry.offset().context("entry is missing an offset")?;
+        let size = entry.size();
+        data_end = std::cmp::max(data_end, offset + size);
+
+        if offset + size > archive_len {
+            problems.push(format!(
+                "\"{raw_relative_path}\": data at offset {offset} with size {size} overruns the archive size of {archive_len}"
+            ));
+            continue;
+        }
+
+        let relative_path_components = match parse_relative_path(entry.relative_path()) {
+            Ok(relative_path_components) => relative_path_components,
+            Err(error) => {
+                problems.push(format!("\"{raw_relative_path}\": invalid path: {error:#}"));
+                continue;
+            }
+        };
+
+        if let Err(error) = paths.insert(&relative_path_components) {
+            problems.push(format!("\"{raw_relative_path}\": {error:#}"));
+        }
+
+        if let Some(data_file_kind) = DataFileKind::detect(game_kind, &relative_path_components) {
+            if let Err(error) = decode_data_file(game_kind, data_file_kind, &mut entry) {
+                problems.push(format!(
+                    "\"{raw_relative_path}\": failed to decode as {}: {error:#}",
+                    data_file_kind.as_str()
+                ));
+            }
+        }
+    }
+
+    if data_end < archive_len {
+        problems.push(format!(
+            "found {} bytes of trailing garbage at offset {data_end}",
+            archive_len - data_end
+        ));
+    }
+
+    for problem in problems.iter() {
+        println!("{problem}");
+    }
+
+    if !problems.is_empty() {
+        bail!("found {} problem(s)", problems.len());
+    }
+
+    println!("no problems found");
+
+    Ok(())
+}
+
+/// Decode a data file as its expected type, returning it as json.
+pub fn decode_data_file(
+    game_kind: GameKind,
+    data_file_kind: DataFileKind,
+    file: impl std::io::Read,
+) -> anyhow::Result<serde_json::Value> {
+    let value = match (game_kind, data_file_kind) {
+        (GameKind::Xp, DataFileKind::Scripts) => decode::<rpgm_common_types::ScriptList>(file)?,
+        (GameKind::Xp, DataFileKind::CommonEvents) => {
+            decode::<Vec<Option<rpgmxp_types::CommonEvent>>>(file)?
+        }
+        (GameKind::Xp, DataFileKind::Actors) => decode::<Vec<Option<rpgmxp_types::Actor>>>(file)?,
+        (GameKind::Xp, DataFileKind::Weapons) => decode::<Vec<Option<rpgmxp_types::Weapon>>>(file)?,
+        (GameKind::Xp, DataFileKind::Armors) => decode::<Vec<Option<rpgmxp_types::Armor>>>(file)?,
+        (GameKind::Xp, DataFileKind::Skills) => decode::<Vec<Option<rpgmxp_types::Skill>>>(file)?,
+        (GameKind::Xp, DataFileKind::States) => decode::<Vec<Option<rpgmxp_types::State>>>(file)?,
+        (GameKind::Xp, DataFileKind::Items) => decode::<Vec<Option<rpgmxp_types::Item>>>(file)?,
+        (GameKind::Xp, DataFileKind::Enemies) => decode::<Vec<Option<rpgmxp_types::Enemy>>>(file)?,
+        (GameKind::Xp, DataFileKind::Classes) => decode::<Vec<Option<rpgmxp_types::Class>>>(file)?,
+        (GameKind::Xp, DataFileKind::Troops) => decode::<Vec<Option<rpgmxp_types::Troop>>>(file)?,
+        (GameKind::Xp, DataFileKind::Tilesets) => {
+            decode::<Vec<Option<rpgmxp_types::Tileset>>>(file)?
+        }
+        (GameKind::Xp, DataFileKind::System) => decode::<rpgmxp_types::System>(file)?,
+        (GameKind::Xp, DataFileKind::Animations) => {
+            decode::<Vec<Option<rpgmxp_types::Animation>>>(file)?
+        }
+        (GameKind::Xp, DataFileKind::Map) => decode::<rpgmxp_types::Map>(file)?,
+        (GameKind::Vx, DataFileKind::Scripts) => decode::<rpgm_common_types::ScriptList>(file)?,
+        (GameKind::Vx, DataFileKind::System) => decode::<rpgmvx_types::System>(file)?,
+        (GameKind::Vx, DataFileKind::Map) => decode::<rpgmvx_types::Map>(file)?,
+        (GameKind::VxAce, DataFileKind::Scripts) => decode::<rpgmvx_ace_types::ScriptList>(file)?,
+        (GameKind::VxAce, DataFileKind::Map) => decode::<rpgmvx_ace_types::Map>(file)?,
+        (_, DataFileKind::MapInfos) => decode::<BTreeMap<i32, rpgm_common_types::MapInfo>>(file)?,
+        (game_kind, data_file_kind) => {
+            bail!("{data_file_kind:?} is not a data file for {game_kind:?}");
+        }
+    };
+
+    Ok(value)
+}
+
+fn decode<T>(file: impl std::io::Read) -> anyhow::Result<serde_json::Value>
+where
+    T: serde::Serialize + for<'a> ruby_marshal::FromValue<'a>,
+{
+    let arena = ruby_marshal::load(file).context("failed to load ruby data")?;
+    let ctx = FromValueContext::new(&arena);
+    let data: T = ctx.from_value(arena.root())?;
+    let value = serde_json::to_value(data)?;
+
+    Ok(value)
+}
@@ -0,0 +1,83 @@
+use anyhow::Context;
+use encoding_rs::Encoding;
+use rpgm_archive::GameKind;
+use rpgm_common_types::StringContext;
+use rpgm_common_types::StringEncodingMarker;
+use std::path::Path;
+
+/// The name of the file in an extracted game that holds the string encoding.
+pub const FILE_NAME: &str = ".encoding";
+
+/// Parse a string encoding label, like "shift_jis" or "windows-1252".
+pub fn parse(input: &str) -> Result<&'static Encoding, String> {
+    let encoding = Encoding::for_label(input.as_bytes())
+        .ok_or_else(|| format!("unknown encoding \"{input}\""))?;
+
+    // Encodings like UTF-16 are decode-only, as they encode to UTF-8.
+    if encoding.output_encoding() != encoding {
+        return Err(format!(
+            "the encoding \"{}\" cannot be used to encode strings",
+            encoding.name()
+        ));
+    }
+
+    Ok(encoding)
+}
+
+/// Record the string encoding of an extracted game.
+pub fn record(base_path: &Path, encoding: &'static Encoding) -> anyhow::Result<()> {
+    let path = base_path.join(FILE_NAME);
+    std::fs::write(&path, encoding.name())
+        .with_context(|| format!("failed to write \"{}\"", path.display()))?;
+
+    Ok(())
+}
+
+/// Load the recorded string encoding of an extracted game.
+///
+/// # Returns
+/// Returns `None` if no encoding was recorded.
+pub fn load(base_path: &Path) -> anyhow::Result<Option<&'static Encoding>> {
+    let path = base_path.join(FILE_NAME);
+    let label = match std::fs::read_to_string(&path) {
+        Ok(label) => label,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => {
+            return Err(error).with_context(|| format!("failed to read \"{}\"", path.display()));
+        }
+    };
+
+    let encoding = parse(label.trim())
+        .map_err(anyhow::Error::msg)
+        .with_context(|| format!("invalid encoding in \"{}\"", path.display()))?;
+
+    Ok(Some(encoding))
+}
+
+/// Get the string context for the data files of a game kind.
+///
+/// VX Ace strings get the encoding marker that the editor adds.
+pub fn string_context(encoding: &'static Encoding, game_kind: GameKind) -> StringContext {
+    let default_marker = match game_kind {
+        GameKind::Xp | GameKind::Vx => StringEncodingMarker::None,
+        GameKind::VxAce => rpgmvx_ace_types::STRING_ENCODING_MARKER,
+    };
+
+    StringContext::new(encoding, default_marker)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_sanity() {
+        assert!(parse("shift_jis") == Ok(encoding_rs::SHIFT_JIS));
+        assert!(parse("sjis") == Ok(encoding_rs::SHIFT_JIS));
+        assert!(parse("GBK") == Ok(encoding_rs::GBK));
+        assert!(parse("windows-1252") == Ok(encoding_rs::WINDOWS_1252));
+        assert!(parse("utf-8") == Ok(encoding_rs::UTF_8));
+        assert!(parse("utf-16le").is_err());
+        assert!(parse("not an encoding").is_err());
+    }
+}
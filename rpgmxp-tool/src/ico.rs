@@ -0,0 +1,474 @@
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use std::io::Write;
+
+/// The size of an icon dir header.
+const ICON_DIR_SIZE: usize = 6;
+
+/// The size of an entry in an ico file.
+const ICON_DIR_ENTRY_SIZE: usize = 16;
+
+/// The size of an entry in a group icon resource.
+const GROUP_ICON_DIR_ENTRY_SIZE: usize = 14;
+
+/// The type of an icon, as opposed to a cursor.
+const ICON_TYPE: u16 = 1;
+
+/// The magic of a png file.
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1A\n";
+
+/// The size of a BITMAPINFOHEADER.
+const BITMAP_INFO_HEADER_SIZE: usize = 40;
+
+/// The compression value for uncompressed bitmaps.
+const BI_RGB: u32 = 0;
+
+/// An entry of a group icon resource.
+///
+/// The image size is not kept, as it is the size of the icon image resource.
+///
+/// See: https://learn.microsoft.com/en-us/windows/win32/menurc/grpicondirentry
+#[derive(Debug, Clone)]
+pub struct GroupIconEntry {
+    pub width: u8,
+    pub height: u8,
+    pub color_count: u8,
+    pub reserved: u8,
+    pub planes: u16,
+    pub bit_count: u16,
+
+    /// The id of the RT_ICON resource with the image data.
+    pub id: u16,
+}
+
+/// Parse the entries of a group icon resource.
+pub fn parse_group_icon(data: &[u8]) -> anyhow::Result<Vec<GroupIconEntry>> {
+    let count = parse_icon_dir(data)?;
+
+    let mut entries = Vec::with_capacity(count);
+    for index in 0..count {
+        let offset = ICON_DIR_SIZE + (index * GROUP_ICON_DIR_ENTRY_SIZE);
+        let entry = data
+            .get(offset..offset + GROUP_ICON_DIR_ENTRY_SIZE)
+            .with_context(|| format!("group icon entry {index} is out of bounds"))?;
+
+        entries.push(GroupIconEntry {
+            width: entry[0],
+            height: entry[1],
+            color_count: entry[2],
+            reserved: entry[3],
+            planes: read_u16(entry, 4),
+            bit_count: read_u16(entry, 6),
+            id: read_u16(entry, 12),
+        });
+    }
+
+    Ok(entries)
+}
+
+/// An image of an icon.
+#[derive(Debug, Clone)]
+pub struct IconImage {
+    pub width: u8,
+    pub height: u8,
+    pub color_count: u8,
+    pub reserved: u8,
+    pub planes: u16,
+    pub bit_count: u16,
+
+    /// The image data, either a DIB without a file header or a png.
+    pub data: Vec<u8>,
+}
+
+impl IconImage {
+    /// Get the width in pixels.
+    pub fn pixel_width(&self) -> u32 {
+        if self.width == 0 {
+            256
+        } else {
+            self.width.into()
+        }
+    }
+
+    /// Get the height in pixels.
+    pub fn pixel_height(&self) -> u32 {
+        if self.height == 0 {
+            256
+        } else {
+            self.height.into()
+        }
+    }
+
+    /// Returns true if the image data is a png.
+    pub fn is_png(&self) -> bool {
+        self.data.starts_with(PNG_SIGNATURE)
+    }
+
+    /// Convert this image into a png file.
+    ///
+    /// Png images are returned as-is.
+    pub fn to_png(&self) -> anyhow::Result<Vec<u8>> {
+        if self.is_png() {
+            return Ok(self.data.clone());
+        }
+
+        let (width, height, rgba) = decode_dib(&self.data)?;
+        encode_png(width, height, &rgba)
+    }
+}
+
+/// An icon, made of multiple images.
+#[derive(Debug, Clone, Default)]
+pub struct Icon {
+    pub images: Vec<IconImage>,
+}
+
+impl Icon {
+    /// Parse an ico file.
+    pub fn parse_ico(data: &[u8]) -> anyhow::Result<Self> {
+        let count = parse_icon_dir(data)?;
+
+        let mut images = Vec::with_capacity(count);
+        for index in 0..count {
+            let offset = ICON_DIR_SIZE + (index * ICON_DIR_ENTRY_SIZE);
+            let entry = data
+                .get(offset..offset + ICON_DIR_ENTRY_SIZE)
+                .with_context(|| format!("icon entry {index} is out of bounds"))?;
+
+            let size = usize::try_from(read_u32(entry, 8))?;
+            let image_offset = usize::try_from(read_u32(entry, 12))?;
+            let image_data = image_offset
+                .checked_add(size)
+                .and_then(|end| data.get(image_offset..end))
+                .with_context(|| format!("icon image {index} is out of bounds"))?;
+
+            images.push(IconImage {
+                width: entry[0],
+                height: entry[1],
+                color_count: entry[2],
+                reserved: entry[3],
+                planes: read_u16(entry, 4),
+                bit_count: read_u16(entry, 6),
+                data: image_data.to_vec(),
+            });
+        }
+
+        Ok(Self { images })
+    }
+
+    /// Serialize this icon as a group icon resource.
+    ///
+    /// The ids are the ids of the RT_ICON resources for each image.
+    pub fn to_group_icon(&self, ids: &[u16]) -> anyhow::Result<Vec<u8>> {
+        ensure!(
+            ids.len() == self.images.len(),
+            "expected {} icon image ids, got {}",
+            self.images.len(),
+            ids.len()
+        );
+        let count = u16::try_from(self.images.len()).context("too many icon images")?;
+
+        let mut buffer =
+            Vec::with_capacity(ICON_DIR_SIZE + (self.images.len() * GROUP_ICON_DIR_ENTRY_SIZE));
+        buffer.extend(0_u16.to_le_bytes());
+        buffer.extend(ICON_TYPE.to_le_bytes());
+        buffer.extend(count.to_le_bytes());
+
+        for (image, id) in self.images.iter().zip(ids.iter()) {
+            let size = u32::try_from(image.data.len()).context("icon image is too large")?;
+
+            buffer.extend([image.width, image.height, image.color_count, image.reserved]);
+            buffer.extend(image.planes.to_le_bytes());
+            buffer.extend(image.bit_count.to_le_bytes());
+            buffer.extend(size.to_le_bytes());
+            buffer.extend(id.to_le_bytes());
+        }
+
+        Ok(buffer)
+    }
+
+    /// Write this icon as an ico file.
+    pub fn write_ico<W>(&self, mut writer: W) -> anyhow::Result<()>
+    where
+        W: Write,
+    {
+        let count = u16::try_from(self.images.len()).context("too many icon images")?;
+
+        writer.write_all(&0_u16.to_le_bytes())?;
+        writer.write_all(&ICON_TYPE.to_le_bytes())?;
+        writer.write_all(&count.to_le_bytes())?;
+
+        let mut offset = ICON_DIR_SIZE + (self.images.len() * ICON_DIR_ENTRY_SIZE);
+        for image in self.images.iter() {
+            let size = u32::try_from(image.data.len()).context("icon image is too large")?;
+
+            writer.write_all(&[image.width, image.height, image.color_count, image.reserved])?;
+            writer.write_all(&image.planes.to_le_bytes())?;
+            writer.write_all(&image.bit_count.to_le_bytes())?;
+            writer.write_all(&size.to_le_bytes())?;
+            writer.write_all(&u32::try_from(offset)?.to_le_bytes())?;
+
+            offset += image.data.len();
+        }
+
+        for image in self.images.iter() {
+            writer.write_all(&image.data)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse an icon dir header, returning the number of entries.
+fn parse_icon_dir(data: &[u8]) -> anyhow::Result<usize> {
+    let header = data
+        .get(..ICON_DIR_SIZE)
+        .context("icon dir header is out of bounds")?;
+
+    let reserved = read_u16(header, 0);
+    ensure!(reserved == 0, "icon dir reserved field is {reserved}");
+
+    let kind = read_u16(header, 2);
+    ensure!(kind == ICON_TYPE, "icon dir type is {kind}, not an icon");
+
+    Ok(read_u16(header, 4).into())
+}
+
+/// Decode a DIB icon image into its width, height, and rgba pixels.
+///
+/// The height of the bitmap header includes the AND mask that follows the color data.
+fn decode_dib(data: &[u8]) -> anyhow::Result<(u32, u32, Vec<u8>)> {
+    ensure!(
+        data.len() >= BITMAP_INFO_HEADER_SIZE,
+        "bitmap header is out of bounds"
+    );
+
+    let header_size = usize::try_from(read_u32(data, 0))?;
+    ensure!(
+        header_size >= BITMAP_INFO_HEADER_SIZE,
+        "bitmap header size {header_size} is too small"
+    );
+    let width = i32::from_le_bytes(data[4..8].try_into().unwrap());
+    let height = i32::from_le_bytes(data[8..12].try_into().unwrap());
+    let bit_count = read_u16(data, 14);
+    let compression = read_u32(data, 16);
+    let colors_used = read_u32(data, 32);
+
+    ensure!(
+        compression == BI_RGB,
+        "bitmap compression {compression} is not supported"
+    );
+    ensure!(width > 0, "bitmap width {width} is invalid");
+    let width = width.unsigned_abs();
+    let height = height.unsigned_abs() / 2;
+    ensure!(height > 0, "bitmap height is invalid");
+
+    let palette_len = match bit_count {
+        1 | 4 | 8 if colors_used == 0 => 1 << bit_count,
+        1 | 4 | 8 => usize::try_from(colors_used)?,
+        24 | 32 => 0,
+        _ => bail!("bitmap bit count {bit_count} is not supported"),
+    };
+    let palette = data
+        .get(header_size..header_size + (palette_len * 4))
+        .context("bitmap palette is out of bounds")?;
+
+    let width_usize = usize::try_from(width)?;
+    let height_usize = usize::try_from(height)?;
+    let xor_stride = (width_usize * usize::from(bit_count)).div_ceil(32) * 4;
+    let and_stride = width_usize.div_ceil(32) * 4;
+
+    let xor_offset = header_size + palette.len();
+    let xor_data = data
+        .get(xor_offset..xor_offset + (xor_stride * height_usize))
+        .context("bitmap color data is out of bounds")?;
+
+    // Some icons omit the AND mask, which means they are fully opaque.
+    let and_offset = xor_offset + xor_data.len();
+    let and_data = data.get(and_offset..and_offset + (and_stride * height_usize));
+
+    let mut rgba = vec![0; width_usize * height_usize * 4];
+    for y in 0..height_usize {
+        // Rows are stored bottom-up.
+        let row = &xor_data[(height_usize - 1 - y) * xor_stride..][..xor_stride];
+        for x in 0..width_usize {
+            let pixel = match bit_count {
+                1 | 4 | 8 => {
+                    let bit_count = usize::from(bit_count);
+                    let bit_offset = x * bit_count;
+                    let byte = row[bit_offset / 8];
+                    let shift = 8 - bit_count - (bit_offset % 8);
+                    let index = usize::from((byte >> shift) & ((1 << bit_count) - 1) as u8);
+                    let color = palette.get(index * 4..(index * 4) + 4).with_context(|| {
+                        format!("bitmap palette index {index} is out of bounds")
+                    })?;
+
+                    [color[2], color[1], color[0], 0xFF]
+                }
+                24 => {
+                    let color = &row[x * 3..(x * 3) + 3];
+                    [color[2], color[1], color[0], 0xFF]
+                }
+                32 => {
+                    let color = &row[x * 4..(x * 4) + 4];
+                    [color[2], color[1], color[0], color[3]]
+                }
+                _ => unreachable!(),
+            };
+
+            rgba[((y * width_usize) + x) * 4..][..4].copy_from_slice(&pixel);
+        }
+    }
+
+    // 32 bit images carry their own alpha, unless it is unused.
+    let has_alpha = bit_count == 32 && rgba.chunks_exact(4).any(|pixel| pixel[3] != 0);
+    if !has_alpha {
+        for y in 0..height_usize {
+            for x in 0..width_usize {
+                let transparent = and_data.is_some_and(|and_data| {
+                    let byte = and_data[((height_usize - 1 - y) * and_stride) + (x / 8)];
+                    (byte >> (7 - (x % 8))) & 1 == 1
+                });
+
+                rgba[(((y * width_usize) + x) * 4) + 3] = if transparent { 0 } else { 0xFF };
+            }
+        }
+    }
+
+    Ok((width, height, rgba))
+}
+
+/// Encode rgba pixels as a png file.
+fn encode_png(width: u32, height: u32, rgba: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let stride = usize::try_from(width)? * 4;
+    ensure!(
+        rgba.len() == stride * usize::try_from(height)?,
+        "pixel data does not match the image size"
+    );
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend(width.to_be_bytes());
+    ihdr.extend(height.to_be_bytes());
+    // Bit depth 8, color type rgba, default compression, filter, and no interlacing.
+    ihdr.extend([8, 6, 0, 0, 0]);
+
+    // Each scanline is prefixed with its filter type, which is always none.
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    for row in rgba.chunks_exact(stride) {
+        encoder.write_all(&[0])?;
+        encoder.write_all(row)?;
+    }
+    let idat = encoder.finish()?;
+
+    let mut png = Vec::new();
+    png.extend(PNG_SIGNATURE);
+    write_png_chunk(&mut png, b"IHDR", &ihdr)?;
+    write_png_chunk(&mut png, b"IDAT", &idat)?;
+    write_png_chunk(&mut png, b"IEND", &[])?;
+
+    Ok(png)
+}
+
+/// Write a png chunk.
+fn write_png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) -> anyhow::Result<()> {
+    let len = u32::try_from(data.len()).context("png chunk is too large")?;
+
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(kind);
+    hasher.update(data);
+
+    png.extend(len.to_be_bytes());
+    png.extend(kind);
+    png.extend(data);
+    png.extend(hasher.finalize().to_be_bytes());
+
+    Ok(())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A 2x2 8 bit icon image, with the top left pixel transparent.
+    fn dib_image() -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(40_u32.to_le_bytes());
+        data.extend(2_i32.to_le_bytes());
+        data.extend(4_i32.to_le_bytes());
+        data.extend(1_u16.to_le_bytes());
+        data.extend(8_u16.to_le_bytes());
+        data.extend([0; 16]);
+        data.extend(2_u32.to_le_bytes());
+        data.extend([0; 4]);
+
+        // Palette of red and blue.
+        data.extend([0x00, 0x00, 0xFF, 0x00]);
+        data.extend([0xFF, 0x00, 0x00, 0x00]);
+
+        // Bottom row then top row, padded to 4 bytes.
+        data.extend([1, 1, 0, 0]);
+        data.extend([0, 1, 0, 0]);
+
+        // AND mask, bottom row then top row.
+        data.extend([0x00, 0, 0, 0]);
+        data.extend([0x80, 0, 0, 0]);
+
+        data
+    }
+
+    #[test]
+    fn ico_round_trip() {
+        let icon = Icon {
+            images: vec![IconImage {
+                width: 2,
+                height: 2,
+                color_count: 2,
+                reserved: 0,
+                planes: 1,
+                bit_count: 8,
+                data: dib_image(),
+            }],
+        };
+
+        let mut ico = Vec::new();
+        icon.write_ico(&mut ico).expect("failed to write");
+        let parsed = Icon::parse_ico(&ico).expect("failed to parse");
+
+        assert!(parsed.images.len() == 1);
+        assert!(parsed.images[0].width == 2);
+        assert!(parsed.images[0].bit_count == 8);
+        assert!(parsed.images[0].data == icon.images[0].data);
+
+        let group_icon = icon
+            .to_group_icon(&[7])
+            .expect("failed to write group icon");
+        let entries = parse_group_icon(&group_icon).expect("failed to parse group icon");
+        assert!(entries.len() == 1);
+        assert!(entries[0].width == 2);
+        assert!(entries[0].id == 7);
+    }
+
+    #[test]
+    fn decode_dib_sanity() {
+        let (width, height, rgba) = decode_dib(&dib_image()).expect("failed to decode");
+        assert!(width == 2);
+        assert!(height == 2);
+        assert!(
+            rgba == [
+                0xFF, 0x00, 0x00, 0x00, //
+                0x00, 0x00, 0xFF, 0xFF, //
+                0x00, 0x00, 0xFF, 0xFF, //
+                0x00, 0x00, 0xFF, 0xFF, //
+            ]
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use crate::commands::compile_assets::Layout;
+use anyhow::ensure;
+use anyhow::Context;
+use base64::engine::general_purpose::URL_SAFE;
+use base64::Engine;
+use rpgm_archive::GameKind;
+use ruby_marshal::FromValueContext;
+use sha2::Digest;
+use sha2::Sha256;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use walkdir::WalkDir;
+
+/// The name of the dir in an extracted game that holds lossless metadata.
+pub const DIR_NAME: &str = ".lossless";
+
+/// Lossless metadata about an extracted data file.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Original {
+    /// The path of the file in the archive, using "/" as a separator.
+    pub archive_path: String,
+
+    /// A hash of the extracted file or dir.
+    ///
+    /// This is used to tell if the extracted data was modified.
+    pub fingerprint: String,
+
+    /// The original scripts, if this is a scripts file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scripts: Vec<OriginalScript>,
+
+    /// The layout that turns the data compiled from the unmodified extracted data
+    /// into the original data.
+    pub layout: Layout,
+}
+
+/// An original script.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct OriginalScript {
+    /// The script id.
+    pub id: i32,
+
+    /// The name of the script.
+    pub name: String,
+
+    /// A hash of the decompressed script data.
+    pub data_hash: String,
+
+    /// The zlib-compressed script data, as url-safe base64.
+    pub compressed_data: String,
+}
+
+impl OriginalScript {
+    /// Check if a script is unchanged.
+    pub fn matches(&self, name: &str, data: &str) -> bool {
+        self.name == name && self.data_hash == hash_script_data(data)
+    }
+
+    /// Get the zlib-compressed script data.
+    pub fn compressed_data(&self) -> anyhow::Result<Vec<u8>> {
+        URL_SAFE
+            .decode(&self.compressed_data)
+            .context("invalid base64 in compressed script data")
+    }
+}
+
+fn hash_script_data(data: &str) -> String {
+    format!("{:x}", Sha256::digest(data.as_bytes()))
+}
+
+/// Get the metadata path for an extracted path.
+fn get_metadata_path(base_path: &Path, relative_path: &Path) -> PathBuf {
+    let path = base_path.join(DIR_NAME).join(relative_path);
+    nd_util::with_push_extension(&path, "json")
+}
+
+/// Record how to reproduce a data file after it was extracted.
+///
+/// Data files are extracted either to a dir or file with the same name as the original,
+/// or to a json file with the extension replaced.
+/// The extracted data is compiled again to find the differences from the original data.
+pub fn record(
+    base_path: &Path,
+    game_kind: GameKind,
+    archive_path_components: &[&str],
+    original: &[u8],
+) -> anyhow::Result<()> {
+    let mut relative_path: PathBuf = archive_path_components.iter().collect();
+    if !base_path.join(&relative_path).exists() {
+        relative_path.set_extension("json");
+    }
+    let extracted_path = base_path.join(&relative_path);
+    ensure!(
+        extracted_path.exists(),
+        "failed to locate the extracted data for \"{}\"",
+        archive_path_components.join("/")
+    );
+
+    let scripts = match archive_path_components {
+        ["Data", "Scripts.rxdata" | "Scripts.rvdata" | "Scripts.rvdata2"] => {
+            load_scripts(game_kind, original)?
+        }
+        _ => Vec::new(),
+    };
+
+    let mut metadata = Original {
+        archive_path: archive_path_components.join("/"),
+        fingerprint: fingerprint(&extracted_path)?,
+        scripts,
+        layout: Layout::default(),
+    };
+
+    let encoded = crate::commands::compile_assets::compile_data_file(
+        base_path,
+        &relative_path,
+        game_kind,
+        &metadata,
+    )?;
+    // Files that are not compiled are copied, so they are already reproduced exactly.
+    let Some(encoded) = encoded else {
+        return Ok(());
+    };
+    metadata.layout = crate::commands::compile_assets::layout::record(original, &encoded)
+        .context("failed to record the layout of the original data")?;
+
+    let metadata_path = get_metadata_path(base_path, &relative_path);
+    if let Some(parent) = metadata_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // TODO: Lock?
+    // TODO: Drop delete guard for file?
+    let temp_path = nd_util::with_push_extension(&metadata_path, "temp");
+    let mut file = File::create_new(&temp_path)?;
+    serde_json::to_writer(&mut file, &metadata)?;
+    file.flush()?;
+    file.sync_all()?;
+    std::fs::rename(temp_path, metadata_path)?;
+
+    Ok(())
+}
+
+/// Load the original scripts from a scripts file.
+fn load_scripts(game_kind: GameKind, data: &[u8]) -> anyhow::Result<Vec<OriginalScript>> {
+    let arena = ruby_marshal::load(data)?;
+    let ctx = FromValueContext::new(&arena);
+
+    let scripts = match game_kind {
+        GameKind::Xp | GameKind::Vx => {
+            let compressed: rpgm_common_types::CompressedScriptList =
+                ctx.from_value(arena.root())?;
+            let decompressed: rpgm_common_types::ScriptList = ctx.from_value(arena.root())?;
+
+            compressed
+                .scripts
+                .into_iter()
+                .zip(decompressed.scripts)
+                .map(|(compressed, decompressed)| OriginalScript {
+                    id: compressed.id,
+                    name: decompressed.name,
+                    data_hash: hash_script_data(&decompressed.data),
+                    compressed_data: URL_SAFE.encode(compressed.data),
+                })
+                .collect()
+        }
+        GameKind::VxAce => {
+            let compressed: rpgmvx_ace_types::CompressedScriptList =
+                ctx.from_value(arena.root())?;
+            let decompressed: rpgmvx_ace_types::ScriptList = ctx.from_value(arena.root())?;
+
+            compressed
+                .scripts
+                .into_iter()
+                .zip(decompressed.scripts)
+                .map(|(compressed, decompressed)| OriginalScript {
+                    id: compressed.id,
+                    name: decompressed.name,
+                    data_hash: hash_script_data(&decompressed.data),
+                    compressed_data: URL_SAFE.encode(compressed.data),
+                })
+                .collect()
+        }
+    };
+
+    Ok(scripts)
+}
+
+/// Load the lossless metadata for an extracted file or dir, if it was recorded.
+pub fn load(base_path: &Path, relative_path: &Path) -> anyhow::Result<Option<Original>> {
+    let metadata_path = get_metadata_path(base_path, relative_path);
+
+    let metadata = match std::fs::read_to_string(&metadata_path) {
+        Ok(metadata) => metadata,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => {
+            return Err(error)
+                .with_context(|| format!("failed to read \"{}\"", metadata_path.display()));
+        }
+    };
+    let metadata: Original = serde_json::from_str(&metadata)
+        .with_context(|| format!("failed to parse \"{}\"", metadata_path.display()))?;
+
+    Ok(Some(metadata))
+}
+
+/// Hash a file or dir.
+///
+/// Dirs are hashed in a stable order, including the relative paths of their files.
+pub fn fingerprint(path: &Path) -> anyhow::Result<String> {
+    let mut hasher = Sha256::new();
+
+    for entry in WalkDir::new(path).sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+
+        let relative_path = entry.path().strip_prefix(path)?;
+        for component in relative_path.iter() {
+            let component = component.to_str().context("non-unicode path")?;
+            hasher.update(component.as_bytes());
+            hasher.update(b"/");
+        }
+
+        let data = std::fs::read(entry.path())
+            .with_context(|| format!("failed to read \"{}\"", entry.path().display()))?;
+        hasher.update(u64::try_from(data.len())?.to_le_bytes());
+        hasher.update(&data);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -1,57 +1,10 @@
 mod commands;
+mod encoding;
+mod ico;
+mod lossless;
+mod pe_resources;
 mod util;
 
-use anyhow::bail;
-use std::str::FromStr;
-
-/// The game kind
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-pub enum GameKind {
-    Xp,
-    Vx,
-    VxAce,
-}
-
-impl GameKind {
-    /// Returns true if this is xp.
-    pub fn is_xp(self) -> bool {
-        matches!(self, Self::Xp)
-    }
-
-    /// Returns true if this is vx.
-    pub fn is_vx(self) -> bool {
-        matches!(self, Self::Vx)
-    }
-
-    /// Returns true if this is vx ace.
-    pub fn is_vx_ace(self) -> bool {
-        matches!(self, Self::VxAce)
-    }
-}
-
-impl FromStr for GameKind {
-    type Err = anyhow::Error;
-
-    fn from_str(input: &str) -> Result<Self, Self::Err> {
-        if input.eq_ignore_ascii_case("xp") || input.eq_ignore_ascii_case("rgssad") {
-            return Ok(Self::Xp);
-        }
-
-        if input.eq_ignore_ascii_case("vx") || input.eq_ignore_ascii_case("rgss2a") {
-            return Ok(Self::Vx);
-        }
-
-        if input.eq_ignore_ascii_case("vx-ace")
-            || input.eq_ignore_ascii_case("rgss3a")
-            || input.eq_ignore_ascii_case("ace")
-        {
-            return Ok(Self::Vx);
-        }
-
-        bail!("\"{input}\" is not a valid game kind");
-    }
-}
-
 #[derive(Debug, argh::FromArgs)]
 #[argh(description = "a cli tool to interact with rpgmaker xp and vx games")]
 struct Options {
@@ -64,6 +17,14 @@ struct Options {
 enum Subcommand {
     ExtractAssets(self::commands::extract_assets::Options),
     CompileAssets(self::commands::compile_assets::Options),
+    List(self::commands::list::Options),
+    PatchArchive(self::commands::patch_archive::Options),
+    RecoverKey(self::commands::recover_key::Options),
+    Verify(self::commands::verify::Options),
+    Diff(self::commands::diff::Options),
+    ExeInfo(self::commands::exe_info::Options),
+    ExtractIcon(self::commands::extract_icon::Options),
+    PatchExe(self::commands::patch_exe::Options),
 }
 
 fn main() -> anyhow::Result<()> {
@@ -71,6 +32,14 @@ fn main() -> anyhow::Result<()> {
     match options.subcommand {
         Subcommand::ExtractAssets(options) => self::commands::extract_assets::exec(options)?,
         Subcommand::CompileAssets(options) => self::commands::compile_assets::exec(options)?,
+        Subcommand::List(options) => self::commands::list::exec(options)?,
+        Subcommand::PatchArchive(options) => self::commands::patch_archive::exec(options)?,
+        Subcommand::RecoverKey(options) => self::commands::recover_key::exec(options)?,
+        Subcommand::Verify(options) => self::commands::verify::exec(options)?,
+        Subcommand::Diff(options) => self::commands::diff::exec(options)?,
+        Subcommand::ExeInfo(options) => self::commands::exe_info::exec(options)?,
+        Subcommand::ExtractIcon(options) => self::commands::extract_icon::exec(options)?,
+        Subcommand::PatchExe(options) => self::commands::patch_exe::exec(options)?,
     }
 
     Ok(())
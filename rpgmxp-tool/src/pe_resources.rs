@@ -0,0 +1,855 @@
+use anyhow::bail;
+use anyhow::ensure;
+use anyhow::Context;
+use rpgm_archive::ResourceId;
+use std::cmp::Ordering;
+
+/// The size of an IMAGE_RESOURCE_DIRECTORY.
+const RESOURCE_DIRECTORY_SIZE: usize = 16;
+
+/// The size of an IMAGE_RESOURCE_DIRECTORY_ENTRY.
+const RESOURCE_DIRECTORY_ENTRY_SIZE: usize = 8;
+
+/// The size of an IMAGE_RESOURCE_DATA_ENTRY.
+const RESOURCE_DATA_ENTRY_SIZE: usize = 16;
+
+/// The flag for directory entries that point to a name or a subdirectory.
+const RESOURCE_HIGH_BIT: u32 = 0x8000_0000;
+
+/// The alignment of resource data in the resource section.
+const RESOURCE_DATA_ALIGNMENT: usize = 8;
+
+/// The size of an IMAGE_SECTION_HEADER.
+const SECTION_HEADER_SIZE: usize = 40;
+
+/// The index of the resource table in the data directories.
+const RESOURCE_DATA_DIRECTORY: usize = 2;
+
+/// The index of the certificate table in the data directories.
+const SECURITY_DATA_DIRECTORY: usize = 4;
+
+/// IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ
+const RESOURCE_SECTION_CHARACTERISTICS: u32 = 0x4000_0040;
+
+/// An owned resource, for rebuilding a resource section.
+#[derive(Debug, Clone)]
+pub struct ResourceEntry {
+    /// The type of the resource, like `RT_ICON`.
+    pub kind: ResourceId,
+
+    /// The name or id of the resource.
+    pub id: ResourceId,
+
+    /// The language of the resource.
+    pub language: u16,
+
+    /// The data of the resource.
+    pub data: Vec<u8>,
+}
+
+/// The resources of a type with the same name or id, one for each language.
+type NameGroup<'a> = (&'a ResourceId, Vec<&'a ResourceEntry>);
+
+/// Compare resource ids in the order required by the resource directory.
+///
+/// Named entries come first, ordered case-insensitively, followed by ids in ascending order.
+fn compare_resource_ids(a: &ResourceId, b: &ResourceId) -> Ordering {
+    match (a, b) {
+        (ResourceId::Name(a), ResourceId::Name(b)) => a
+            .to_uppercase()
+            .encode_utf16()
+            .cmp(b.to_uppercase().encode_utf16()),
+        (ResourceId::Name(_), ResourceId::Id(_)) => Ordering::Less,
+        (ResourceId::Id(_), ResourceId::Name(_)) => Ordering::Greater,
+        (ResourceId::Id(a), ResourceId::Id(b)) => a.cmp(b),
+    }
+}
+
+/// Build the data of a resource section.
+///
+/// The section RVA is needed since data entries point to their data with RVAs.
+pub fn build_resource_section(
+    entries: &[ResourceEntry],
+    section_rva: u32,
+) -> anyhow::Result<Vec<u8>> {
+    let mut entries: Vec<&ResourceEntry> = entries.iter().collect();
+    entries.sort_by(|a, b| {
+        compare_resource_ids(&a.kind, &b.kind)
+            .then_with(|| compare_resource_ids(&a.id, &b.id))
+            .then_with(|| a.language.cmp(&b.language))
+    });
+    for window in entries.windows(2) {
+        ensure!(
+            window[0].kind != window[1].kind
+                || window[0].id != window[1].id
+                || window[0].language != window[1].language,
+            "duplicate resource {} {} language {}",
+            window[0].kind,
+            window[0].id,
+            window[0].language
+        );
+    }
+
+    // Group the entries into the three levels of the tree: type, name, and language.
+    let mut kinds: Vec<(&ResourceId, Vec<NameGroup>)> = Vec::new();
+    for entry in entries.iter().copied() {
+        match kinds.last_mut() {
+            Some((kind, ids)) if *kind == &entry.kind => match ids.last_mut() {
+                Some((id, languages)) if *id == &entry.id => languages.push(entry),
+                _ => ids.push((&entry.id, vec![entry])),
+            },
+            _ => kinds.push((&entry.kind, vec![(&entry.id, vec![entry])])),
+        }
+    }
+
+    // Lay out the tables, then the data entries, then the names, then the data.
+    let table_size = |len: usize| RESOURCE_DIRECTORY_SIZE + (len * RESOURCE_DIRECTORY_ENTRY_SIZE);
+    let mut offset = table_size(kinds.len());
+    let mut id_table_offsets = Vec::with_capacity(kinds.len());
+    for (_kind, ids) in kinds.iter() {
+        id_table_offsets.push(offset);
+        offset += table_size(ids.len());
+    }
+    let mut language_table_offsets = Vec::new();
+    for (_kind, ids) in kinds.iter() {
+        for (_id, languages) in ids.iter() {
+            language_table_offsets.push(offset);
+            offset += table_size(languages.len());
+        }
+    }
+    let data_entries_offset = offset;
+    offset += entries.len() * RESOURCE_DATA_ENTRY_SIZE;
+
+    let mut names = Vec::new();
+    for (kind, ids) in kinds.iter() {
+        names.push(*kind);
+        names.extend(ids.iter().map(|(id, _languages)| *id));
+    }
+    let mut name_offsets = Vec::new();
+    for name in names {
+        let ResourceId::Name(name) = name else {
+            continue;
+        };
+        if name_offsets.iter().any(|(existing, _)| existing == name) {
+            continue;
+        }
+
+        name_offsets.push((name.as_str(), offset));
+        offset += 2 + (name.encode_utf16().count() * 2);
+    }
+
+    let mut data_offsets = Vec::with_capacity(entries.len());
+    for entry in entries.iter() {
+        offset = offset.next_multiple_of(RESOURCE_DATA_ALIGNMENT);
+        data_offsets.push(offset);
+        offset += entry.data.len();
+    }
+
+    let mut buffer = vec![0; offset];
+    let name_or_id = |id: &ResourceId| -> anyhow::Result<u32> {
+        match id {
+            ResourceId::Id(id) => Ok(u32::from(*id)),
+            ResourceId::Name(name) => {
+                let (_, offset) = name_offsets
+                    .iter()
+                    .find(|(existing, _)| *existing == name)
+                    .context("missing resource name")?;
+                Ok(RESOURCE_HIGH_BIT | u32::try_from(*offset)?)
+            }
+        }
+    };
+
+    let root = kinds
+        .iter()
+        .map(|(kind, _ids)| *kind)
+        .zip(id_table_offsets.iter().map(|offset| (*offset, true)));
+    write_table(&mut buffer, 0, root, &name_or_id)?;
+
+    let mut language_table_index = 0;
+    let mut data_entry_index = 0;
+    for ((_kind, ids), id_table_offset) in kinds.iter().zip(id_table_offsets.iter()) {
+        let table = ids.iter().map(|(id, _languages)| *id).zip(
+            language_table_offsets[language_table_index..]
+                .iter()
+                .map(|offset| (*offset, true)),
+        );
+        write_table(&mut buffer, *id_table_offset, table, &name_or_id)?;
+
+        for (_id, languages) in ids.iter() {
+            let languages: Vec<ResourceId> = languages
+                .iter()
+                .map(|entry| ResourceId::Id(entry.language))
+                .collect();
+            let table = languages.iter().zip((data_entry_index..).map(|index| {
+                (
+                    data_entries_offset + (index * RESOURCE_DATA_ENTRY_SIZE),
+                    false,
+                )
+            }));
+            write_table(
+                &mut buffer,
+                language_table_offsets[language_table_index],
+                table,
+                &name_or_id,
+            )?;
+
+            language_table_index += 1;
+            data_entry_index += languages.len();
+        }
+    }
+
+    for (index, (entry, data_offset)) in entries.iter().zip(data_offsets.iter()).enumerate() {
+        let rva = section_rva
+            .checked_add(u32::try_from(*data_offset)?)
+            .context("resource data rva overflowed")?;
+        let size = u32::try_from(entry.data.len()).context("resource data is too large")?;
+
+        let offset = data_entries_offset + (index * RESOURCE_DATA_ENTRY_SIZE);
+        write_u32(&mut buffer, offset, rva)?;
+        write_u32(&mut buffer, offset + 4, size)?;
+
+        buffer[*data_offset..*data_offset + entry.data.len()].copy_from_slice(&entry.data);
+    }
+
+    for (name, offset) in name_offsets.iter() {
+        let name: Vec<u16> = name.encode_utf16().collect();
+        let len = u16::try_from(name.len()).context("resource name is too long")?;
+
+        write_u16(&mut buffer, *offset, len)?;
+        for (index, c) in name.iter().enumerate() {
+            write_u16(&mut buffer, offset + 2 + (index * 2), *c)?;
+        }
+    }
+
+    Ok(buffer)
+}
+
+/// Write a resource directory table.
+///
+/// Each entry is an id and the offset of what it points to,
+/// along with whether that is a subdirectory.
+fn write_table<'a, I, F>(
+    buffer: &mut [u8],
+    offset: usize,
+    entries: I,
+    name_or_id: &F,
+) -> anyhow::Result<()>
+where
+    I: Iterator<Item = (&'a ResourceId, (usize, bool))>,
+    F: Fn(&ResourceId) -> anyhow::Result<u32>,
+{
+    let mut number_of_named_entries: u16 = 0;
+    let mut number_of_id_entries: u16 = 0;
+
+    let mut entry_offset = offset + RESOURCE_DIRECTORY_SIZE;
+    for (id, (target_offset, is_table)) in entries {
+        match id {
+            ResourceId::Name(_) => number_of_named_entries += 1,
+            ResourceId::Id(_) => number_of_id_entries += 1,
+        }
+
+        let mut target_offset = u32::try_from(target_offset)?;
+        if is_table {
+            target_offset |= RESOURCE_HIGH_BIT;
+        }
+
+        write_u32(buffer, entry_offset, name_or_id(id)?)?;
+        write_u32(buffer, entry_offset + 4, target_offset)?;
+        entry_offset += RESOURCE_DIRECTORY_ENTRY_SIZE;
+    }
+
+    // The characteristics, time stamp, and version are left as 0.
+    write_u16(buffer, offset + 12, number_of_named_entries)?;
+    write_u16(buffer, offset + 14, number_of_id_entries)?;
+
+    Ok(())
+}
+
+/// The locations of the headers of a PE file that need to be patched.
+struct PeHeaders {
+    number_of_sections_offset: usize,
+    size_of_initialized_data_offset: usize,
+    section_alignment: u32,
+    file_alignment: u32,
+    size_of_image_offset: usize,
+    size_of_headers: u32,
+    check_sum_offset: usize,
+    data_directories_offset: usize,
+    number_of_data_directories: usize,
+    section_table_offset: usize,
+    number_of_sections: usize,
+}
+
+impl PeHeaders {
+    fn parse(game_exe: &[u8]) -> anyhow::Result<Self> {
+        let pe_offset = usize::try_from(read_u32(game_exe, 0x3C)?)?;
+        ensure!(
+            game_exe.get(pe_offset..pe_offset + 4) == Some(b"PE\0\0"),
+            "missing PE signature"
+        );
+
+        let file_header_offset = pe_offset + 4;
+        let number_of_sections_offset = file_header_offset + 2;
+        let number_of_sections = usize::from(read_u16(game_exe, number_of_sections_offset)?);
+        let size_of_optional_header = usize::from(read_u16(game_exe, file_header_offset + 16)?);
+
+        let optional_header_offset = file_header_offset + 20;
+        let magic = read_u16(game_exe, optional_header_offset)?;
+        let data_directories_offset = match magic {
+            0x10B => optional_header_offset + 96,
+            0x20B => optional_header_offset + 112,
+            _ => bail!("unknown optional header magic 0x{magic:04X}"),
+        };
+        let number_of_data_directories =
+            usize::try_from(read_u32(game_exe, data_directories_offset - 4)?)?;
+
+        Ok(Self {
+            number_of_sections_offset,
+            size_of_initialized_data_offset: optional_header_offset + 8,
+            section_alignment: read_u32(game_exe, optional_header_offset + 32)?,
+            file_alignment: read_u32(game_exe, optional_header_offset + 36)?,
+            size_of_image_offset: optional_header_offset + 56,
+            size_of_headers: read_u32(game_exe, optional_header_offset + 60)?,
+            check_sum_offset: optional_header_offset + 64,
+            data_directories_offset,
+            number_of_data_directories,
+            section_table_offset: optional_header_offset + size_of_optional_header,
+            number_of_sections,
+        })
+    }
+
+    fn data_directory_offset(&self, index: usize) -> anyhow::Result<usize> {
+        ensure!(
+            index < self.number_of_data_directories,
+            "missing data directory {index}"
+        );
+        Ok(self.data_directories_offset + (index * 8))
+    }
+
+    fn section_header_offset(&self, index: usize) -> usize {
+        self.section_table_offset + (index * SECTION_HEADER_SIZE)
+    }
+}
+
+/// The parts of a section header needed to move sections.
+struct SectionHeader {
+    virtual_size: u32,
+    virtual_address: u32,
+    size_of_raw_data: u32,
+    pointer_to_raw_data: u32,
+}
+
+impl SectionHeader {
+    /// Get the end of this section in memory.
+    ///
+    /// Some linkers leave the virtual size as 0, so the raw size is used if it is larger.
+    fn virtual_end(&self) -> anyhow::Result<u32> {
+        self.virtual_address
+            .checked_add(std::cmp::max(self.virtual_size, self.size_of_raw_data))
+            .context("section virtual end overflowed")
+    }
+
+    /// Get the end of this section in the file.
+    fn raw_end(&self) -> anyhow::Result<u32> {
+        self.pointer_to_raw_data
+            .checked_add(self.size_of_raw_data)
+            .context("section raw end overflowed")
+    }
+}
+
+/// Replace all resources of a PE file.
+///
+/// If the resource section is the last section, it is rebuilt in place.
+/// Otherwise, a new resource section is appended and the old one is left unused.
+/// Any authenticode signature is removed, as it would no longer be valid.
+/// The section sizes, image size, and checksum are updated to match.
+pub fn replace_resources(game_exe: &[u8], entries: &[ResourceEntry]) -> anyhow::Result<Vec<u8>> {
+    let headers = PeHeaders::parse(game_exe)?;
+    ensure!(
+        headers.section_alignment.is_power_of_two() && headers.file_alignment.is_power_of_two(),
+        "invalid section or file alignment"
+    );
+
+    let resource_directory_offset = headers.data_directory_offset(RESOURCE_DATA_DIRECTORY)?;
+    let resource_rva = read_u32(game_exe, resource_directory_offset)?;
+
+    let mut sections = Vec::with_capacity(headers.number_of_sections);
+    for index in 0..headers.number_of_sections {
+        let offset = headers.section_header_offset(index);
+        sections.push(SectionHeader {
+            virtual_size: read_u32(game_exe, offset + 8)?,
+            virtual_address: read_u32(game_exe, offset + 12)?,
+            size_of_raw_data: read_u32(game_exe, offset + 16)?,
+            pointer_to_raw_data: read_u32(game_exe, offset + 20)?,
+        });
+    }
+    let last_index = sections
+        .len()
+        .checked_sub(1)
+        .context("exe has no sections")?;
+    let mut raw_end = 0;
+    for section in sections.iter() {
+        raw_end = std::cmp::max(raw_end, section.raw_end()?);
+    }
+    let raw_end = usize::try_from(raw_end)?;
+    ensure!(
+        raw_end <= game_exe.len(),
+        "sections extend past the end of the file"
+    );
+
+    // Data after the sections may be a signature, which is invalidated by this change.
+    let security_directory_offset = headers.data_directory_offset(SECURITY_DATA_DIRECTORY)?;
+    let security_offset = usize::try_from(read_u32(game_exe, security_directory_offset)?)?;
+    let mut overlay = &game_exe[raw_end..];
+    if security_offset != 0 {
+        ensure!(
+            security_offset >= raw_end,
+            "the signature is not after the sections"
+        );
+        ensure!(
+            security_offset <= game_exe.len(),
+            "the signature is past the end of the file"
+        );
+        overlay = &game_exe[raw_end..security_offset];
+    }
+
+    let last_section = &sections[last_index];
+    let replace_last_section = resource_rva != 0
+        && resource_rva == last_section.virtual_address
+        && usize::try_from(last_section.raw_end()?)? == raw_end;
+
+    let mut output;
+    let section_index;
+    let old_size_of_raw_data;
+    let section_rva;
+    let pointer_to_raw_data;
+    if replace_last_section {
+        section_index = last_index;
+        old_size_of_raw_data = last_section.size_of_raw_data;
+        section_rva = last_section.virtual_address;
+        pointer_to_raw_data = last_section.pointer_to_raw_data;
+
+        // The headers are kept from before the section, so they must not overlap it.
+        ensure!(
+            pointer_to_raw_data >= headers.size_of_headers,
+            "the resource section overlaps the headers"
+        );
+        output = game_exe[..usize::try_from(pointer_to_raw_data)?].to_vec();
+    } else {
+        section_index = sections.len();
+        old_size_of_raw_data = 0;
+        let mut virtual_end = 0;
+        for section in sections.iter() {
+            virtual_end = std::cmp::max(virtual_end, section.virtual_end()?);
+        }
+        section_rva = virtual_end
+            .checked_next_multiple_of(headers.section_alignment)
+            .context("section rva overflowed")?;
+        pointer_to_raw_data = u32::try_from(raw_end)?
+            .checked_next_multiple_of(headers.file_alignment)
+            .context("section file offset overflowed")?;
+
+        // The new section header must fit before the first section data.
+        let header_offset = headers.section_header_offset(section_index);
+        let first_raw_data = sections
+            .iter()
+            .map(|section| section.pointer_to_raw_data)
+            .filter(|pointer| *pointer != 0)
+            .min()
+            .unwrap_or(headers.size_of_headers);
+        let header_end = u32::try_from(header_offset + SECTION_HEADER_SIZE)?;
+        ensure!(
+            header_end <= headers.size_of_headers && header_end <= first_raw_data,
+            "there is no room for a new section header"
+        );
+        let header_space = game_exe
+            .get(header_offset..header_offset + SECTION_HEADER_SIZE)
+            .context("there is no room for a new section header")?;
+        ensure!(
+            header_space.iter().all(|b| *b == 0),
+            "the space for a new section header is in use"
+        );
+
+        output = game_exe[..raw_end].to_vec();
+        output.resize(usize::try_from(pointer_to_raw_data)?, 0);
+
+        let mut name = [0; 8];
+        name[..5].copy_from_slice(b".rsrc");
+        output
+            .get_mut(header_offset..header_offset + 8)
+            .context("the new section header is out of bounds")?
+            .copy_from_slice(&name);
+        write_u32(
+            &mut output,
+            header_offset + 36,
+            RESOURCE_SECTION_CHARACTERISTICS,
+        )?;
+
+        let number_of_sections = u16::try_from(section_index + 1).context("too many sections")?;
+        write_u16(
+            &mut output,
+            headers.number_of_sections_offset,
+            number_of_sections,
+        )?;
+    }
+
+    let section_data = build_resource_section(entries, section_rva)?;
+    let virtual_size = u32::try_from(section_data.len()).context("resources are too large")?;
+    let size_of_raw_data = virtual_size
+        .checked_next_multiple_of(headers.file_alignment)
+        .context("resources are too large")?;
+    let section_raw_end = pointer_to_raw_data
+        .checked_add(size_of_raw_data)
+        .context("resources are too large")?;
+    output.extend(&section_data);
+    output.resize(usize::try_from(section_raw_end)?, 0);
+    output.extend(overlay);
+
+    let header_offset = headers.section_header_offset(section_index);
+    write_u32(&mut output, header_offset + 8, virtual_size)?;
+    write_u32(&mut output, header_offset + 12, section_rva)?;
+    write_u32(&mut output, header_offset + 16, size_of_raw_data)?;
+    write_u32(&mut output, header_offset + 20, pointer_to_raw_data)?;
+
+    write_u32(&mut output, resource_directory_offset, section_rva)?;
+    write_u32(&mut output, resource_directory_offset + 4, virtual_size)?;
+    write_u32(&mut output, security_directory_offset, 0)?;
+    write_u32(&mut output, security_directory_offset + 4, 0)?;
+
+    let size_of_initialized_data = read_u32(&output, headers.size_of_initialized_data_offset)?
+        .saturating_sub(old_size_of_raw_data)
+        .saturating_add(size_of_raw_data);
+    write_u32(
+        &mut output,
+        headers.size_of_initialized_data_offset,
+        size_of_initialized_data,
+    )?;
+
+    let mut size_of_image = section_rva
+        .checked_add(virtual_size)
+        .context("resources are too large")?;
+    for (index, section) in sections.iter().enumerate() {
+        if index != section_index {
+            size_of_image = std::cmp::max(size_of_image, section.virtual_end()?);
+        }
+    }
+    let size_of_image = size_of_image
+        .checked_next_multiple_of(headers.section_alignment)
+        .context("image size overflowed")?;
+    write_u32(&mut output, headers.size_of_image_offset, size_of_image)?;
+
+    write_u32(&mut output, headers.check_sum_offset, 0)?;
+    let check_sum = pe_check_sum(&output);
+    write_u32(&mut output, headers.check_sum_offset, check_sum)?;
+
+    Ok(output)
+}
+
+/// Calculate the checksum of a PE file, with the checksum field zeroed.
+///
+/// This is the algorithm of `CheckSumMappedFile`.
+fn pe_check_sum(data: &[u8]) -> u32 {
+    let mut sum: u32 = 0;
+    for chunk in data.chunks(2) {
+        let word = u16::from_le_bytes([chunk[0], chunk.get(1).copied().unwrap_or(0)]);
+        sum += u32::from(word);
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    sum = (sum & 0xFFFF) + (sum >> 16);
+
+    // PE files larger than 4GB are not valid.
+    sum.wrapping_add(data.len() as u32)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> anyhow::Result<u16> {
+    let bytes = data
+        .get(offset..offset + 2)
+        .with_context(|| format!("offset {offset} is out of bounds"))?;
+    Ok(u16::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> anyhow::Result<u32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .with_context(|| format!("offset {offset} is out of bounds"))?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn write_u16(data: &mut [u8], offset: usize, value: u16) -> anyhow::Result<()> {
+    data.get_mut(offset..offset + 2)
+        .with_context(|| format!("offset {offset} is out of bounds"))?
+        .copy_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+fn write_u32(data: &mut [u8], offset: usize, value: u32) -> anyhow::Result<()> {
+    data.get_mut(offset..offset + 4)
+        .with_context(|| format!("offset {offset} is out of bounds"))?
+        .copy_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The offset of the optional header in a test exe.
+    const TEST_OPTIONAL_HEADER_OFFSET: usize = 0x58;
+
+    /// The offset of the section table in a test exe.
+    const TEST_SECTION_TABLE_OFFSET: usize = TEST_OPTIONAL_HEADER_OFFSET + 224;
+
+    /// Build a minimal PE32 exe with a code section and a resource section.
+    ///
+    /// Each section is one page in memory and 0x200 bytes in the file.
+    fn build_test_exe(resources: &[ResourceEntry], resources_last: bool) -> Vec<u8> {
+        let (text_index, rsrc_index) = if resources_last { (0, 1) } else { (1, 0) };
+        let section_rva = |index: u32| 0x1000 * (index + 1);
+        let pointer_to_raw_data = |index: u32| 0x200 * (index + 1);
+
+        let resource_data =
+            build_resource_section(resources, section_rva(rsrc_index)).expect("failed to build");
+        assert!(resource_data.len() <= 0x200);
+
+        let mut exe = vec![0; 0x600];
+        exe[..2].copy_from_slice(b"MZ");
+        write_u32(&mut exe, 0x3C, 0x40).unwrap();
+        exe[0x40..0x44].copy_from_slice(b"PE\0\0");
+
+        // IMAGE_FILE_HEADER
+        write_u16(&mut exe, 0x44, 0x14C).unwrap();
+        write_u16(&mut exe, 0x46, 2).unwrap();
+        write_u16(&mut exe, 0x54, 224).unwrap();
+        write_u16(&mut exe, 0x56, 0x102).unwrap();
+
+        // IMAGE_OPTIONAL_HEADER32
+        let offset = TEST_OPTIONAL_HEADER_OFFSET;
+        write_u16(&mut exe, offset, 0x10B).unwrap();
+        write_u32(&mut exe, offset + 8, 0x200).unwrap();
+        write_u32(&mut exe, offset + 16, section_rva(text_index)).unwrap();
+        write_u32(&mut exe, offset + 28, 0x40_0000).unwrap();
+        write_u32(&mut exe, offset + 32, 0x1000).unwrap();
+        write_u32(&mut exe, offset + 36, 0x200).unwrap();
+        write_u16(&mut exe, offset + 40, 4).unwrap();
+        write_u16(&mut exe, offset + 48, 4).unwrap();
+        write_u32(&mut exe, offset + 56, 0x3000).unwrap();
+        write_u32(&mut exe, offset + 60, 0x200).unwrap();
+        write_u16(&mut exe, offset + 68, 2).unwrap();
+        write_u32(&mut exe, offset + 92, 16).unwrap();
+        let resource_directory_offset = offset + 96 + (RESOURCE_DATA_DIRECTORY * 8);
+        write_u32(&mut exe, resource_directory_offset, section_rva(rsrc_index)).unwrap();
+        write_u32(
+            &mut exe,
+            resource_directory_offset + 4,
+            u32::try_from(resource_data.len()).unwrap(),
+        )
+        .unwrap();
+
+        let sections = [
+            (text_index, b".text", 0x10, 0x6000_0020),
+            (rsrc_index, b".rsrc", resource_data.len(), 0x4000_0040),
+        ];
+        for (index, name, virtual_size, characteristics) in sections {
+            let offset = TEST_SECTION_TABLE_OFFSET + (usize::try_from(index).unwrap() * 40);
+            exe[offset..offset + 5].copy_from_slice(name);
+            write_u32(&mut exe, offset + 8, u32::try_from(virtual_size).unwrap()).unwrap();
+            write_u32(&mut exe, offset + 12, section_rva(index)).unwrap();
+            write_u32(&mut exe, offset + 16, 0x200).unwrap();
+            write_u32(&mut exe, offset + 20, pointer_to_raw_data(index)).unwrap();
+            write_u32(&mut exe, offset + 36, characteristics).unwrap();
+        }
+
+        let text_offset = usize::try_from(pointer_to_raw_data(text_index)).unwrap();
+        exe[text_offset] = 0xC3;
+        let rsrc_offset = usize::try_from(pointer_to_raw_data(rsrc_index)).unwrap();
+        exe[rsrc_offset..rsrc_offset + resource_data.len()].copy_from_slice(&resource_data);
+
+        let check_sum = pe_check_sum(&exe);
+        write_u32(&mut exe, TEST_OPTIONAL_HEADER_OFFSET + 64, check_sum).unwrap();
+
+        exe
+    }
+
+    fn test_entries(data: &[u8]) -> Vec<ResourceEntry> {
+        vec![
+            ResourceEntry {
+                kind: ResourceId::Id(16),
+                id: ResourceId::Id(1),
+                language: 0x0411,
+                data: data.to_vec(),
+            },
+            ResourceEntry {
+                kind: ResourceId::Id(14),
+                id: ResourceId::Name("ICON".into()),
+                language: 0x0411,
+                data: b"group".to_vec(),
+            },
+        ]
+    }
+
+    /// Check that an exe has the given resources and a valid checksum.
+    fn check_exe(exe: &[u8], entries: &[ResourceEntry]) {
+        object::read::pe::PeFile32::parse(exe).expect("failed to parse exe");
+
+        let resources = rpgm_archive::read_all_resources(exe).expect("failed to read");
+        assert!(resources.len() == entries.len());
+        for entry in entries {
+            let resource = resources
+                .iter()
+                .find(|resource| resource.kind == entry.kind && resource.id == entry.id)
+                .expect("missing resource");
+            assert!(resource.language == entry.language);
+            assert!(resource.data == entry.data);
+        }
+
+        let headers = PeHeaders::parse(exe).expect("failed to parse headers");
+        let check_sum = read_u32(exe, headers.check_sum_offset).unwrap();
+        let mut zeroed = exe.to_vec();
+        write_u32(&mut zeroed, headers.check_sum_offset, 0).unwrap();
+        assert!(check_sum == pe_check_sum(&zeroed));
+    }
+
+    #[test]
+    fn replace_resources_in_place() {
+        let exe = build_test_exe(&test_entries(b"old"), true);
+        check_exe(&exe, &test_entries(b"old"));
+
+        let new_entries = test_entries(&[0xAB; 0x300]);
+        let output = replace_resources(&exe, &new_entries).expect("failed to replace");
+        check_exe(&output, &new_entries);
+
+        let headers = PeHeaders::parse(&output).unwrap();
+        assert!(headers.number_of_sections == 2);
+        assert!(read_u32(&output, headers.size_of_image_offset).unwrap() == 0x3000);
+
+        // The resource section grows to fit, at the same place.
+        let section_offset = headers.section_header_offset(1);
+        assert!(read_u32(&output, section_offset + 12).unwrap() == 0x2000);
+        assert!(read_u32(&output, section_offset + 16).unwrap() == 0x400);
+        assert!(read_u32(&output, section_offset + 20).unwrap() == 0x400);
+        assert!(output.len() == 0x800);
+
+        // The code is untouched.
+        assert!(output[0x200] == 0xC3);
+    }
+
+    #[test]
+    fn replace_resources_appends_section() {
+        let exe = build_test_exe(&test_entries(b"old"), false);
+        check_exe(&exe, &test_entries(b"old"));
+
+        let new_entries = test_entries(b"new");
+        let output = replace_resources(&exe, &new_entries).expect("failed to replace");
+        check_exe(&output, &new_entries);
+
+        let headers = PeHeaders::parse(&output).unwrap();
+        assert!(headers.number_of_sections == 3);
+        assert!(read_u32(&output, headers.size_of_image_offset).unwrap() == 0x4000);
+
+        let section_offset = headers.section_header_offset(2);
+        assert!(&output[section_offset..section_offset + 8] == b".rsrc\0\0\0");
+        assert!(read_u32(&output, section_offset + 12).unwrap() == 0x3000);
+        assert!(read_u32(&output, section_offset + 20).unwrap() == 0x600);
+
+        // The code is untouched.
+        assert!(output[0x400] == 0xC3);
+    }
+
+    #[test]
+    fn replace_resources_overflow() {
+        let mut exe = build_test_exe(&test_entries(b"old"), true);
+        write_u32(&mut exe, TEST_SECTION_TABLE_OFFSET + 20, 0xFFFF_FF00).unwrap();
+
+        let error = replace_resources(&exe, &test_entries(b"new"))
+            .expect_err("overflowing section was accepted");
+        assert!(error.to_string().contains("overflowed"));
+    }
+
+    #[test]
+    fn replace_resources_truncated_signature() {
+        let mut exe = build_test_exe(&test_entries(b"old"), true);
+        let security_directory_offset =
+            TEST_OPTIONAL_HEADER_OFFSET + 96 + (SECURITY_DATA_DIRECTORY * 8);
+        let security_offset = u32::try_from(exe.len() + 0x100).unwrap();
+        write_u32(&mut exe, security_directory_offset, security_offset).unwrap();
+        write_u32(&mut exe, security_directory_offset + 4, 0x100).unwrap();
+
+        let error = replace_resources(&exe, &test_entries(b"new"))
+            .expect_err("truncated signature was accepted");
+        assert!(error.to_string().contains("past the end"));
+    }
+
+    #[test]
+    fn replace_resources_section_overlaps_headers() {
+        let mut exe = build_test_exe(&test_entries(b"old"), true);
+        write_u32(&mut exe, TEST_SECTION_TABLE_OFFSET + 16, 0x8).unwrap();
+        write_u32(&mut exe, TEST_SECTION_TABLE_OFFSET + 20, 0x8).unwrap();
+        write_u32(&mut exe, TEST_SECTION_TABLE_OFFSET + 40 + 20, 0x10).unwrap();
+
+        let error = replace_resources(&exe, &test_entries(b"new"))
+            .expect_err("overlapping section was accepted");
+        assert!(error.to_string().contains("overlaps the headers"));
+    }
+
+    #[test]
+    fn build_resource_section_sanity() {
+        let entries = [
+            ResourceEntry {
+                kind: ResourceId::Id(16),
+                id: ResourceId::Id(1),
+                language: 0x0409,
+                data: b"version".to_vec(),
+            },
+            ResourceEntry {
+                kind: ResourceId::Id(3),
+                id: ResourceId::Name("ICON".into()),
+                language: 0x0411,
+                data: b"icon".to_vec(),
+            },
+        ];
+
+        let section_rva = 0x3000;
+        let data = build_resource_section(&entries, section_rva).expect("failed to build");
+
+        let resource_directory = object::read::pe::ResourceDirectory::new(&data);
+        let root = resource_directory.root().expect("failed to parse root");
+        let kinds: Vec<_> = root
+            .entries
+            .iter()
+            .map(|entry| entry.name_or_id().id())
+            .collect();
+        assert!(kinds == [Some(3), Some(16)]);
+
+        let icon_table = root.entries[0]
+            .data(resource_directory)
+            .expect("failed to get icon table")
+            .table()
+            .expect("icon entry is not a table");
+        let object::read::pe::ResourceNameOrId::Name(name) = icon_table.entries[0].name_or_id()
+        else {
+            panic!("icon entry is not named");
+        };
+        assert!(
+            name.to_string_lossy(resource_directory)
+                .expect("failed to read name")
+                == "ICON"
+        );
+
+        let language_table = icon_table.entries[0]
+            .data(resource_directory)
+            .expect("failed to get language table")
+            .table()
+            .expect("icon name entry is not a table");
+        assert!(language_table.entries[0].name_or_id().id() == Some(0x0411));
+
+        let data_entry = language_table.entries[0]
+            .data(resource_directory)
+            .expect("failed to get data entry")
+            .data()
+            .expect("icon language entry is not data");
+        let offset =
+            usize::try_from(data_entry.offset_to_data.get(object::LittleEndian) - section_rva)
+                .unwrap();
+        assert!(&data[offset..offset + 4] == b"icon");
+    }
+}
@@ -1,4 +1,5 @@
 use anyhow::bail;
+use anyhow::ensure;
 use anyhow::Context;
 use rpgmxp_types::Actor;
 use rpgmxp_types::Animation;
@@ -12,7 +13,10 @@ use rpgmxp_types::State;
 use rpgmxp_types::Tileset;
 use rpgmxp_types::Troop;
 use rpgmxp_types::Weapon;
+use std::collections::HashMap;
 use std::fmt::Write;
+use std::str::FromStr;
+use unicode_normalization::UnicodeNormalization;
 
 /// Convert a hex u8 char into a u8 value.
 ///
@@ -27,6 +31,33 @@ pub fn decode_hex_u8(value: u8) -> Option<u8> {
     }
 }
 
+/// Parse an archive key.
+///
+/// Keys may be decimal, or hex if prefixed with "0x".
+pub fn parse_key(input: &str) -> Result<u32, String> {
+    let result = match input
+        .strip_prefix("0x")
+        .or_else(|| input.strip_prefix("0X"))
+    {
+        Some(input) => u32::from_str_radix(input, 16),
+        None => input.parse(),
+    };
+
+    result.map_err(|error| format!("invalid key \"{input}\": {error}"))
+}
+
+/// Compare file names in the order that the RPG Maker editor lists them.
+///
+/// The editor lists files in NTFS order,
+/// which compares names case-insensitively by their uppercase form.
+/// Names that only differ by case are ordered by their original form to keep the order total.
+pub fn compare_file_names(a: &str, b: &str) -> std::cmp::Ordering {
+    a.chars()
+        .flat_map(char::to_uppercase)
+        .cmp(b.chars().flat_map(char::to_uppercase))
+        .then_with(|| a.cmp(b))
+}
+
 /// Check if a file name is a map file name.
 ///
 /// # Arguments
@@ -46,27 +77,61 @@ pub fn is_map_file_name(file_name: &str, expected_extension: &str) -> bool {
         .is_some_and(|map_n| !map_n.is_empty() && map_n.chars().all(|c| c.is_ascii_digit()))
 }
 
-/// Percent-escape a file name.
+/// Check if a file name is a device name reserved by Windows, like "CON" or "nul.txt".
+fn is_reserved_file_name(file_name: &str) -> bool {
+    // Windows ignores the extension and trailing spaces.
+    let stem = file_name.split('.').next().unwrap_or_default();
+    let stem = stem.trim_end_matches(' ').to_ascii_uppercase();
+
+    match stem.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" => true,
+        _ => stem
+            .strip_prefix("COM")
+            .or_else(|| stem.strip_prefix("LPT"))
+            .is_some_and(|n| {
+                matches!(n, "¹" | "²" | "³") || (n.len() == 1 && n.as_bytes()[0].is_ascii_digit())
+            }),
+    }
+}
+
+/// Check if the char at the given index of a file name cannot be used on every platform.
+fn is_unportable_char(file_name: &str, index: usize, c: char) -> bool {
+    match c {
+        ':' | '*' | '/' | '\\' | '<' | '>' | '?' | '"' | '|' => true,
+        c if c.is_ascii_control() => true,
+        // Windows strips trailing dots and spaces.
+        '.' | ' ' => file_name[index..].trim_end_matches(['.', ' ']).is_empty(),
+        _ => index == 0 && is_reserved_file_name(file_name),
+    }
+}
+
+/// Check if a file name can be used as is on Windows, macOS, and Linux.
+pub fn is_portable_file_name(file_name: &str) -> bool {
+    !file_name.is_empty()
+        && !file_name
+            .char_indices()
+            .any(|(index, c)| is_unportable_char(file_name, index, c))
+}
+
+/// Percent-escape a file name, so that it can be used on Windows, macOS, and Linux.
 ///
 /// This will percent-escape the following:
 /// * '%'
-/// * ':'
-/// * '*'
-/// * '/'
-/// * '<'
-/// * '>'
-/// * '?'
+/// * ':', '*', '/', '\\', '<', '>', '?', '"', and '|'
+/// * ASCII control chars
+/// * Trailing dots and spaces
+/// * The first char of device names reserved by Windows, like "CON"
+///
+/// Escapes are the UTF-8 bytes of the char as lowercase hex.
 pub fn percent_escape_file_name(file_name: &str) -> String {
     let mut escaped = String::with_capacity(file_name.len());
-    for c in file_name.chars() {
-        match c {
-            '%' | ':' | '*' | '/' | '<' | '>' | '?' => {
-                let c = u32::from(c);
-                write!(&mut escaped, "%{c:02x}").unwrap();
-            }
-            _ => {
-                escaped.push(c);
+    for (index, c) in file_name.char_indices() {
+        if c == '%' || is_unportable_char(file_name, index, c) {
+            for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                write!(&mut escaped, "%{byte:02x}").unwrap();
             }
+        } else {
+            escaped.push(c);
         }
     }
     escaped
@@ -77,42 +142,298 @@ pub fn percent_escape_file_name(file_name: &str) -> String {
 /// # Returns
 /// Returns an error if the string cannot be unescaped.
 pub fn percent_unescape_file_name(file_name: &str) -> anyhow::Result<String> {
-    #[derive(PartialEq)]
-    enum State {
-        Normal,
-        ParsePercentEscape { index: usize, value: u8 },
+    let mut unescaped = Vec::with_capacity(file_name.len());
+    let mut bytes = file_name.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte != b'%' {
+            unescaped.push(byte);
+            continue;
+        }
+
+        let (Some(high), Some(low)) = (bytes.next(), bytes.next()) else {
+            bail!("incomplete percent escape");
+        };
+        let high = decode_hex_u8(high).context("invalid hex char")?;
+        let low = decode_hex_u8(low).context("invalid hex char")?;
+
+        unescaped.push((high << 4) | low);
     }
 
+    String::from_utf8(unescaped).context("percent escapes are not valid UTF-8")
+}
+
+/// Percent-unescape a file name, keeping invalid percent escapes as literal text.
+///
+/// This is for file names that may not have been escaped, like files added by hand.
+/// A run of escapes that is not valid UTF-8 is also kept as literal text.
+pub fn percent_unescape_file_name_lenient(file_name: &str) -> String {
+    let decode_escape = |input: &str| match input.as_bytes() {
+        [b'%', high, low, ..] => Some((decode_hex_u8(*high)? << 4) | decode_hex_u8(*low)?),
+        _ => None,
+    };
+
     let mut unescaped = String::with_capacity(file_name.len());
-    let mut state = State::Normal;
-    for c in file_name.chars() {
-        match (&mut state, c) {
-            (State::Normal, '%') => {
-                state = State::ParsePercentEscape { index: 0, value: 0 };
+    let mut rest = file_name;
+    while let Some(position) = rest.find('%') {
+        unescaped.push_str(&rest[..position]);
+        rest = &rest[position..];
+
+        // Escapes are ASCII, so this always splits at a char boundary.
+        let mut bytes = Vec::new();
+        while let Some(byte) = decode_escape(&rest[bytes.len() * 3..]) {
+            bytes.push(byte);
+        }
+        let run_len = std::cmp::max(bytes.len() * 3, 1);
+        match String::from_utf8(bytes) {
+            Ok(run) if !run.is_empty() => unescaped.push_str(&run),
+            _ => unescaped.push_str(&rest[..run_len]),
+        }
+        rest = &rest[run_len..];
+    }
+    unescaped.push_str(rest);
+
+    unescaped
+}
+
+/// A set of relative file paths, which escapes them so they can be extracted on every platform.
+///
+/// Windows and macOS file systems are case-insensitive, and macOS does not tell apart
+/// different Unicode normalization forms of a name,
+/// so paths that only differ in case or normalization would overwrite each other.
+#[derive(Debug, Default)]
+pub struct FilePathSet {
+    /// Folded escaped file paths, mapped to the escaped path.
+    files: HashMap<String, String>,
+
+    /// Folded escaped dir paths, mapped to the escaped path.
+    dirs: HashMap<String, String>,
+}
+
+impl FilePathSet {
+    /// Make a new, empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a file path, as its components.
+    ///
+    /// # Returns
+    /// Returns the percent-escaped components of the path.
+    /// Returns an error if the escaped path collides with another path
+    /// on case-insensitive or normalization-insensitive file systems.
+    pub fn insert(&mut self, components: &[&str]) -> anyhow::Result<Vec<String>> {
+        let components: Vec<String> = components
+            .iter()
+            .map(|component| percent_escape_file_name(component))
+            .collect();
+        ensure!(
+            components.iter().all(|component| !component.is_empty()),
+            "empty file name"
+        );
+
+        let path = components.join("/");
+        let key = fold_file_path(&path);
+        if let Some(other) = self.files.get(&key) {
+            ensure!(*other != path, "duplicate path");
+            bail!("collides with \"{other}\" on case-insensitive file systems");
+        }
+        if let Some(other) = self.dirs.get(&key) {
+            bail!("collides with the dir \"{other}\"");
+        }
+
+        for end in 1..components.len() {
+            let dir = components[..end].join("/");
+            let dir_key = fold_file_path(&dir);
+            if let Some(other) = self.files.get(&dir_key) {
+                bail!("collides with \"{other}\" on case-insensitive file systems");
             }
-            (State::Normal, c) => unescaped.push(c),
-            (State::ParsePercentEscape { index, value }, c) => {
-                let c = u8::try_from(c).context("invalid percent escape")?;
-                let c = crate::util::decode_hex_u8(c).context("invalid hex char")?;
+            self.dirs.entry(dir_key).or_insert(dir);
+        }
 
-                *value |= c << (4 - (4 * *index));
-                *index += 1;
+        self.files.insert(key, path);
 
-                if *index == 2 {
-                    let c = char::from(*value);
-                    unescaped.push(c);
+        Ok(components)
+    }
+}
+
+/// Fold a file path, so that paths that name the same file on every platform are equal.
+fn fold_file_path(path: &str) -> String {
+    path.nfc().collect::<String>().to_lowercase()
+}
 
-                    state = State::Normal;
+/// Find the json pointers of all byte strings in a json value.
+pub fn find_byte_strings(value: &serde_json::Value) -> Vec<String> {
+    fn find(value: &serde_json::Value, pointer: &mut String, pointers: &mut Vec<String>) {
+        let pointer_len = pointer.len();
+        match value {
+            serde_json::Value::String(value) if rpgm_common_types::is_byte_string(value) => {
+                pointers.push(pointer.clone());
+            }
+            serde_json::Value::Array(values) => {
+                for (index, value) in values.iter().enumerate() {
+                    write!(pointer, "/{index}").unwrap();
+                    find(value, pointer, pointers);
+                    pointer.truncate(pointer_len);
+                }
+            }
+            serde_json::Value::Object(map) => {
+                for (key, value) in map.iter() {
+                    pointer.push('/');
+                    pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                    find(value, pointer, pointers);
+                    pointer.truncate(pointer_len);
                 }
             }
+            _ => {}
+        }
+    }
+
+    let mut pointers = Vec::new();
+    find(value, &mut String::new(), &mut pointers);
+    pointers
+}
+
+/// A glob pattern, matched against normalized path components.
+///
+/// Components are separated by '/' or '\\'.
+/// The following are supported:
+/// * '**' as a whole component, which matches zero or more components
+/// * '*', which matches zero or more chars within a component
+/// * '?', which matches one char within a component
+/// * '[abc]', '[a-z]', and '[!abc]', which match one char from a set
+///
+/// Matching is case-sensitive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobPattern {
+    components: Vec<GlobComponent>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum GlobComponent {
+    AnyComponents,
+    Pattern(Vec<GlobToken>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum GlobToken {
+    Literal(char),
+    AnyChar,
+    AnyString,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl GlobPattern {
+    /// Check if the given path components match this pattern.
+    pub fn matches(&self, path_components: &[&str]) -> bool {
+        match_glob_components(&self.components, path_components)
+    }
+}
+
+impl FromStr for GlobPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut components = Vec::new();
+        for component in input.split(['/', '\\']) {
+            ensure!(
+                !component.is_empty(),
+                "glob \"{input}\" contains an empty component"
+            );
+
+            if component == "**" {
+                components.push(GlobComponent::AnyComponents);
+                continue;
+            }
+
+            let mut tokens = Vec::new();
+            let mut chars = component.chars();
+            while let Some(c) = chars.next() {
+                let token = match c {
+                    '*' => GlobToken::AnyString,
+                    '?' => GlobToken::AnyChar,
+                    '[' => {
+                        let mut negated = false;
+                        let mut ranges = Vec::new();
+                        let mut closed = false;
+                        while let Some(c) = chars.next() {
+                            match c {
+                                '!' if ranges.is_empty() && !negated => {
+                                    negated = true;
+                                }
+                                ']' if !ranges.is_empty() => {
+                                    closed = true;
+                                    break;
+                                }
+                                // A '-' before the closing ']' is a literal.
+                                '-' if !ranges.is_empty() && chars.clone().next() != Some(']') => {
+                                    let end = chars.next().with_context(|| {
+                                        format!("glob \"{input}\" has an incomplete range")
+                                    })?;
+                                    let (start, _) = ranges.pop().unwrap();
+                                    ensure!(start <= end, "glob \"{input}\" has an invalid range");
+                                    ranges.push((start, end));
+                                }
+                                c => ranges.push((c, c)),
+                            }
+                        }
+                        ensure!(closed, "glob \"{input}\" has an unclosed '['");
+
+                        GlobToken::Class { negated, ranges }
+                    }
+                    c => GlobToken::Literal(c),
+                };
+                tokens.push(token);
+            }
+
+            components.push(GlobComponent::Pattern(tokens));
         }
+
+        Ok(Self { components })
     }
+}
 
-    if state != State::Normal {
-        bail!("incomplete percent escape");
+fn match_glob_components(pattern: &[GlobComponent], path_components: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path_components.is_empty(),
+        Some((GlobComponent::AnyComponents, pattern)) => (0..=path_components.len())
+            .any(|skip| match_glob_components(pattern, &path_components[skip..])),
+        Some((GlobComponent::Pattern(tokens), pattern)) => match path_components.split_first() {
+            Some((component, path_components)) => {
+                let chars: Vec<char> = component.chars().collect();
+                match_glob_tokens(tokens, &chars) && match_glob_components(pattern, path_components)
+            }
+            None => false,
+        },
     }
+}
+
+fn match_glob_tokens(tokens: &[GlobToken], chars: &[char]) -> bool {
+    match tokens.split_first() {
+        None => chars.is_empty(),
+        Some((GlobToken::AnyString, tokens)) => {
+            (0..=chars.len()).any(|skip| match_glob_tokens(tokens, &chars[skip..]))
+        }
+        Some((token, tokens)) => {
+            let (c, chars) = match chars.split_first() {
+                Some(value) => value,
+                None => return false,
+            };
+
+            let matched = match token {
+                GlobToken::Literal(expected) => c == expected,
+                GlobToken::AnyChar => true,
+                GlobToken::Class { negated, ranges } => {
+                    ranges.iter().any(|(start, end)| (start..=end).contains(&c)) != *negated
+                }
+                GlobToken::AnyString => unreachable!(),
+            };
 
-    Ok(unescaped)
+            matched && match_glob_tokens(tokens, chars)
+        }
+    }
 }
 
 /// A trait to represent objects stored in *.rxdata files as elements of an array.
@@ -256,6 +577,39 @@ mod test {
         assert!(decode_hex_u8(b'G').is_none());
     }
 
+    #[test]
+    fn parse_key_sanity() {
+        assert!(parse_key("0xDEADCAFE") == Ok(0xDEADCAFE));
+        assert!(parse_key("0xdeadcafe") == Ok(0xDEADCAFE));
+        assert!(parse_key("3735931646") == Ok(0xDEADCAFE));
+        assert!(parse_key("0x1DEADCAFE").is_err());
+        assert!(parse_key("DEADCAFE").is_err());
+    }
+
+    #[test]
+    fn compare_file_names_sanity() {
+        let mut names = vec![
+            "map002.rxdata",
+            "Map001.rxdata",
+            "MapInfos.rxdata",
+            "Map_Extra.rxdata",
+            "Actors.rxdata",
+            "actors.rxdata",
+        ];
+        names.sort_by(|a, b| compare_file_names(a, b));
+        assert!(
+            names
+                == [
+                    "Actors.rxdata",
+                    "actors.rxdata",
+                    "Map001.rxdata",
+                    "map002.rxdata",
+                    "MapInfos.rxdata",
+                    "Map_Extra.rxdata",
+                ]
+        );
+    }
+
     #[test]
     fn is_map_file_name_sanity() {
         assert!(is_map_file_name("Map001.rxdata", "rxdata"));
@@ -269,7 +623,21 @@ mod test {
 
     #[test]
     fn percent_escape_round_trip() {
-        let tests = ["hello.txt", "%world.json", "foo:bar.rxdata"];
+        let tests = [
+            "hello.txt",
+            "%world.json",
+            "foo:bar.rxdata",
+            "a\\b|c\"d",
+            "tab\there",
+            "Wait...",
+            "trailing ",
+            "CON",
+            "nul.txt",
+            "com1",
+            "console",
+            "テスト",
+            "",
+        ];
 
         for test in tests {
             let escaped = percent_escape_file_name(test);
@@ -277,6 +645,113 @@ mod test {
                 percent_unescape_file_name(escaped.as_str()).expect("failed to percent unescape");
 
             assert!(test == unescaped, "{test} != {unescaped}");
+            assert!(
+                test.is_empty() || is_portable_file_name(&escaped),
+                "{escaped}"
+            );
         }
+
+        assert!(percent_escape_file_name("a|b") == "a%7cb");
+        assert!(percent_escape_file_name("Wait. .") == "Wait%2e%20%2e");
+        assert!(percent_escape_file_name("Aux.json") == "%41ux.json");
+        assert!(percent_escape_file_name("Map001.json") == "Map001.json");
+        assert!(percent_unescape_file_name("%e3%83%86").unwrap() == "テ");
+        assert!(percent_unescape_file_name("%E3%83%86").unwrap() == "テ");
+        assert!(percent_unescape_file_name("%e3%83").is_err());
+        assert!(percent_unescape_file_name("%2").is_err());
+        assert!(percent_unescape_file_name("%zz").is_err());
+    }
+
+    #[test]
+    fn percent_unescape_lenient_sanity() {
+        for test in ["hello.txt", "%world.json", "a|b", "CON", "テスト", "100%"] {
+            let escaped = percent_escape_file_name(test);
+            assert!(percent_unescape_file_name_lenient(&escaped) == test);
+        }
+
+        assert!(percent_unescape_file_name_lenient("50%.png") == "50%.png");
+        assert!(percent_unescape_file_name_lenient("%2") == "%2");
+        assert!(percent_unescape_file_name_lenient("%zz%7c") == "%zz|");
+        assert!(percent_unescape_file_name_lenient("%e3%83") == "%e3%83");
+        assert!(percent_unescape_file_name_lenient("a%e3%83%86b") == "aテb");
+        assert!(percent_unescape_file_name_lenient("%%41") == "%A");
+    }
+
+    #[test]
+    fn file_path_set_sanity() {
+        let mut paths = FilePathSet::new();
+        assert!(paths.insert(&["Graphics", "Pictures", "a.png"]).is_ok());
+        assert!(paths.insert(&["Graphics", "Pictures", "b.png"]).is_ok());
+        assert!(paths.insert(&["Graphics", "Pictures", "a.png"]).is_err());
+        assert!(paths.insert(&["graphics", "pictures", "A.png"]).is_err());
+        assert!(paths
+            .insert(&["Graphics", "Pictures", "a.png", "c.png"])
+            .is_err());
+        assert!(paths.insert(&["GRAPHICS"]).is_err());
+        assert!(paths.insert(&["Data", "CON.rxdata"]).unwrap() == ["Data", "%43ON.rxdata"]);
+        assert!(paths.insert(&["Data", "a?.rxdata"]).unwrap() == ["Data", "a%3f.rxdata"]);
+        assert!(paths.insert(&["Data", "A?.rxdata"]).is_err());
+        assert!(paths.insert(&["Data", "a%3f.rxdata"]).unwrap() == ["Data", "a%253f.rxdata"]);
+        assert!(paths.insert(&["Data", "100%.rxdata"]).unwrap() == ["Data", "100%25.rxdata"]);
+        assert!(paths.insert(&["Audio", "caf\u{e9}.ogg"]).is_ok());
+        assert!(paths.insert(&["Audio", "cafe\u{301}.ogg"]).is_err());
+        assert!(paths.insert(&["Audio", "CAFE\u{301}.ogg"]).is_err());
+    }
+
+    #[test]
+    fn find_byte_strings_sanity() {
+        let byte_string = format!("{}gQ==", rpgm_common_types::BYTE_STRING_PREFIX);
+        let value = serde_json::json!({
+            "name": byte_string,
+            "a/b~c": [1, "text", byte_string],
+            "list": [{ "name": "text" }],
+        });
+
+        assert!(find_byte_strings(&value) == ["/a~1b~0c/2", "/name"]);
+        assert!(find_byte_strings(&serde_json::json!("text")).is_empty());
+    }
+
+    #[test]
+    fn glob_pattern_sanity() {
+        let tests = [
+            ("Data/Map0*.rxdata", "Data/Map001.rxdata", true),
+            ("Data/Map0*.rxdata", "Data/Map101.rxdata", false),
+            ("Data/Map0*.rxdata", "Data/Maps/Map001.rxdata", false),
+            (
+                "Graphics/Characters/**",
+                "Graphics/Characters/001-Fighter01.png",
+                true,
+            ),
+            (
+                "Graphics/Characters/**",
+                "Graphics/Characters/a/b.png",
+                true,
+            ),
+            ("Graphics/Characters/**", "Graphics/Pictures/a.png", false),
+            ("**/*.png", "Graphics/Pictures/a.png", true),
+            ("**/*.png", "a.png", true),
+            ("Data/Map00?.rxdata", "Data/Map001.rxdata", true),
+            ("Data/Map00?.rxdata", "Data/Map0001.rxdata", false),
+            ("Data/Map[0-2]*", "Data/Map101.rxdata", true),
+            ("Data/Map[!0-2]*", "Data/Map101.rxdata", false),
+            ("Data\\*.rxdata", "Data/System.rxdata", true),
+            ("Data/[a-]*", "Data/-.rxdata", true),
+            ("Data/[a-]*", "Data/a.rxdata", true),
+            ("Data/[a-]*", "Data/b.rxdata", false),
+            ("Data/[!a-]*", "Data/b.rxdata", true),
+        ];
+
+        for (pattern, path, expected) in tests {
+            let glob: GlobPattern = pattern.parse().expect("failed to parse glob");
+            let path_components: Vec<&str> = path.split('/').collect();
+
+            assert!(
+                glob.matches(&path_components) == expected,
+                "{pattern} matching {path} != {expected}"
+            );
+        }
+
+        assert!("Data//Map001.rxdata".parse::<GlobPattern>().is_err());
+        assert!("Data/Map[0-".parse::<GlobPattern>().is_err());
     }
 }
@@ -1,3 +1,5 @@
+use crate::util::decode_string;
+use crate::util::string2ruby_string;
 use crate::Table;
 use ruby_marshal::FromValue;
 use ruby_marshal::FromValueContext;
@@ -130,9 +132,7 @@ impl<'a> FromValue<'a> for Actor {
                         return Err(FromValueError::DuplicateInstanceVariable { name: key.into() });
                     }
                     let value: &StringValue = ctx.from_value(value)?;
-                    let value = std::str::from_utf8(value.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let value = decode_string(value.value());
                     character_name_field = Some(value);
                 }
                 NAME_FIELD => {
@@ -140,9 +140,7 @@ impl<'a> FromValue<'a> for Actor {
                         return Err(FromValueError::DuplicateInstanceVariable { name: key.into() });
                     }
                     let value: &StringValue = ctx.from_value(value)?;
-                    let value = std::str::from_utf8(value.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let value = decode_string(value.value());
                     name_field = Some(value);
                 }
                 EXP_INFLATION_FIELD => {
@@ -255,9 +253,7 @@ impl<'a> FromValue<'a> for Actor {
                         return Err(FromValueError::DuplicateInstanceVariable { name: key.into() });
                     }
                     let value: &StringValue = ctx.from_value(value)?;
-                    let value = std::str::from_utf8(value.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let value = decode_string(value.value());
                     battler_name_field = Some(value);
                 }
                 ARMOR3_FIX_FIELD => {
@@ -410,8 +406,8 @@ impl IntoValue for Actor {
         let initial_level_field_value = self.initial_level.into_value(arena)?;
         let armor4_id_field_value = self.armor4_id.into_value(arena)?;
         let weapon_id_field_value = self.weapon_id.into_value(arena)?;
-        let character_name_field_value = arena.create_string(self.character_name.into()).into();
-        let name_field_value = arena.create_string(self.name.into()).into();
+        let character_name_field_value = string2ruby_string(self.character_name, arena)?;
+        let name_field_value = string2ruby_string(self.name, arena)?;
         let exp_inflation_field_value = self.exp_inflation.into_value(arena)?;
         let parameters_field_value = self.parameters.into_value(arena)?;
         let armor2_fix_field_value = self.armor2_fix.into_value(arena)?;
@@ -427,7 +423,7 @@ impl IntoValue for Actor {
         let armor1_id_field_value = self.armor1_id.into_value(arena)?;
         let id_field_value = self.id.into_value(arena)?;
         let character_hue_field_value = self.character_hue.into_value(arena)?;
-        let battler_name_field_value = arena.create_string(self.battler_name.into()).into();
+        let battler_name_field_value = string2ruby_string(self.battler_name, arena)?;
         let armor3_fix_field_value = self.armor3_fix.into_value(arena)?;
 
         let fields = vec![
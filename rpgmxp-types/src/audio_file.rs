@@ -1,3 +1,5 @@
+use crate::util::decode_string;
+use crate::util::string2ruby_string;
 use ruby_marshal::FromValue;
 use ruby_marshal::FromValueContext;
 use ruby_marshal::FromValueError;
@@ -65,8 +67,7 @@ impl<'a> FromValue<'a> for AudioFile {
                         });
                     }
                     let name: &StringValue = ctx.from_value(value)?;
-                    let name =
-                        std::str::from_utf8(name.value()).map_err(FromValueError::new_other)?;
+                    let name = decode_string(name.value());
                     name_field = Some(name);
                 }
                 PITCH_FIELD => {
@@ -95,7 +96,7 @@ impl<'a> FromValue<'a> for AudioFile {
 
         Ok(Self {
             volume,
-            name: name.into(),
+            name,
             pitch,
         })
     }
@@ -110,12 +111,12 @@ impl IntoValue for AudioFile {
         let pitch_field_key = arena.create_symbol(PITCH_FIELD.into());
 
         let volume_field_value = self.volume.into_value(arena)?;
-        let name_field_value = arena.create_string(self.name.into());
+        let name_field_value = string2ruby_string(self.name, arena)?;
         let pitch_field_value = self.pitch.into_value(arena)?;
 
         let instance_variables = vec![
             (volume_field_key, volume_field_value),
-            (name_field_key, name_field_value.into()),
+            (name_field_key, name_field_value),
             (pitch_field_key, pitch_field_value),
         ];
         let object = arena.create_object(object_name, instance_variables);
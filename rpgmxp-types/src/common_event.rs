@@ -1,3 +1,5 @@
+use crate::util::decode_string;
+use crate::util::string2ruby_string;
 use crate::EventCommand;
 use ruby_marshal::FromValue;
 use ruby_marshal::FromValueContext;
@@ -59,10 +61,9 @@ impl<'a> FromValue<'a> for CommonEvent {
                     }
 
                     let name: &StringValue = ctx.from_value(value)?;
-                    let name =
-                        std::str::from_utf8(name.value()).map_err(FromValueError::new_other)?;
+                    let name = decode_string(name.value());
 
-                    name_field = Some(name.into());
+                    name_field = Some(name);
                 }
                 LIST_FIELD => {
                     if list_field.is_some() {
@@ -142,7 +143,7 @@ impl IntoValue for CommonEvent {
         let switch_id_field_key = arena.create_symbol(SWITCH_ID_FIELD.into());
         let id_field_key = arena.create_symbol(ID_FIELD.into());
 
-        let name_field_value = arena.create_string(self.name.into()).into();
+        let name_field_value = string2ruby_string(self.name, arena)?;
         let list_field_value = self.list.into_value(arena)?;
         let trigger_field_value = self.trigger.into_value(arena)?;
         let switch_id_field_value = self.switch_id.into_value(arena)?;
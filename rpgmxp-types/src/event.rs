@@ -1,3 +1,5 @@
+use crate::util::decode_string;
+use crate::util::string2ruby_string;
 use crate::EventPage;
 use ruby_marshal::FromValue;
 use ruby_marshal::FromValueContext;
@@ -66,8 +68,7 @@ impl<'a> FromValue<'a> for Event {
                         });
                     }
                     let name: &StringValue = ctx.from_value(value)?;
-                    let name =
-                        std::str::from_utf8(name.value()).map_err(FromValueError::new_other)?;
+                    let name = decode_string(name.value());
                     name_field = Some(name);
                 }
                 Y_FIELD => {
@@ -106,11 +107,9 @@ impl<'a> FromValue<'a> for Event {
         let pages = pages_field.ok_or_else(|| FromValueError::MissingInstanceVariable {
             name: PAGES_FIELD.into(),
         })?;
-        let name = name_field
-            .ok_or_else(|| FromValueError::MissingInstanceVariable {
-                name: NAME_FIELD.into(),
-            })?
-            .to_string();
+        let name = name_field.ok_or_else(|| FromValueError::MissingInstanceVariable {
+            name: NAME_FIELD.into(),
+        })?;
         let y = y_field.ok_or_else(|| FromValueError::MissingInstanceVariable {
             name: Y_FIELD.into(),
         })?;
@@ -142,7 +141,7 @@ impl IntoValue for Event {
         let id_field_key = arena.create_symbol(ID_FIELD.into());
 
         let pages_field_value = self.pages.into_value(arena)?;
-        let name_field_value = arena.create_string(self.name.into()).into();
+        let name_field_value = string2ruby_string(self.name, arena)?;
         let y_field_value = self.y.into_value(arena)?;
         let x_field_value = self.x.into_value(arena)?;
         let id_field_value = self.id.into_value(arena)?;
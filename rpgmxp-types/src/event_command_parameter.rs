@@ -1,3 +1,5 @@
+use crate::util::decode_string;
+use crate::util::string2ruby_string;
 use crate::AudioFile;
 use crate::Color;
 use crate::MoveCommand;
@@ -72,9 +74,7 @@ impl<'a> FromValue<'a> for EventCommandParameter {
         match value {
             Value::String(value) => {
                 let value = value.value();
-                let value = std::str::from_utf8(value)
-                    .map_err(FromValueError::new_other)?
-                    .to_string();
+                let value = decode_string(value);
                 Ok(Self::String(value))
             }
             Value::Array(value) => {
@@ -92,9 +92,7 @@ impl<'a> FromValue<'a> for EventCommandParameter {
                         let mut new_value = Vec::with_capacity(value.len());
                         for value in value.iter().copied() {
                             let value: &StringValue = ctx.from_value(value)?;
-                            let value = std::str::from_utf8(value.value())
-                                .map_err(FromValueError::new_other)?
-                                .to_string();
+                            let value = decode_string(value.value());
                             new_value.push(value);
                         }
 
@@ -163,12 +161,12 @@ impl<'a> FromValue<'a> for EventCommandParameter {
 impl IntoValue for EventCommandParameter {
     fn into_value(self, arena: &mut ValueArena) -> Result<ValueHandle, IntoValueError> {
         match self {
-            Self::String(value) => Ok(arena.create_string(value.into()).into()),
+            Self::String(value) => string2ruby_string(value, arena),
             Self::StringArray(value) => {
-                let values: Vec<_> = value
+                let values = value
                     .into_iter()
-                    .map(|value| arena.create_string(value.into()).into())
-                    .collect();
+                    .map(|value| string2ruby_string(value, arena))
+                    .collect::<Result<_, _>>()?;
                 Ok(arena.create_array(values).into())
             }
             Self::Int(value) => value.into_value(arena),
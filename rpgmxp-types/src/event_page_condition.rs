@@ -1,3 +1,5 @@
+use crate::util::decode_string;
+use crate::util::string2ruby_string;
 use ruby_marshal::FromValue;
 use ruby_marshal::FromValueContext;
 use ruby_marshal::FromValueError;
@@ -79,11 +81,7 @@ impl<'a> FromValue<'a> for EventPageCondition {
                     }
 
                     let value: &StringValue = ctx.from_value(value)?;
-                    self_switch_ch_field = Some(
-                        std::str::from_utf8(value.value())
-                            .map_err(FromValueError::new_other)?
-                            .into(),
-                    );
+                    self_switch_ch_field = Some(decode_string(value.value()));
                 }
                 SWITCH1_ID_FIELD => {
                     if switch1_id_field.is_some() {
@@ -220,7 +218,7 @@ impl IntoValue for EventPageCondition {
         let switch2_id_field_key = arena.create_symbol(SWITCH2_ID_FIELD.into());
 
         let switch2_valid_field_value = self.switch2_valid.into_value(arena)?;
-        let self_switch_ch_field_value = arena.create_string(self.self_switch_ch.into()).into();
+        let self_switch_ch_field_value = string2ruby_string(self.self_switch_ch, arena)?;
         let switch1_id_field_value = self.switch1_id.into_value(arena)?;
         let switch1_valid_field_value = self.switch1_valid.into_value(arena)?;
         let variable_value_field_value = self.variable_value.into_value(arena)?;
@@ -1,3 +1,5 @@
+use crate::util::decode_string;
+use crate::util::string2ruby_string;
 use ruby_marshal::FromValue;
 use ruby_marshal::FromValueContext;
 use ruby_marshal::FromValueError;
@@ -75,10 +77,9 @@ impl<'a> FromValue<'a> for EventPageGraphic {
                     }
 
                     let character_name: &StringValue = ctx.from_value(value)?;
-                    let character_name = std::str::from_utf8(character_name.value())
-                        .map_err(FromValueError::new_other)?;
+                    let character_name = decode_string(character_name.value());
 
-                    character_name_field = Some(character_name.into());
+                    character_name_field = Some(character_name);
                 }
                 PATTERN_FIELD => {
                     if pattern_field.is_some() {
@@ -184,7 +185,7 @@ impl IntoValue for EventPageGraphic {
         let character_hue_field_key = arena.create_symbol(CHARACTER_HUE_FIELD.into());
 
         let opacity_field_value = self.opacity.into_value(arena)?;
-        let character_name_field_value = arena.create_string(self.character_name.into()).into();
+        let character_name_field_value = string2ruby_string(self.character_name, arena)?;
         let pattern_field_value = self.pattern.into_value(arena)?;
         let tile_id_field_value = self.tile_id.into_value(arena)?;
         let direction_field_value = self.direction.into_value(arena)?;
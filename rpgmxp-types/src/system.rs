@@ -1,3 +1,5 @@
+use crate::util::decode_string;
+use crate::util::string2ruby_string;
 use crate::AudioFile;
 use crate::SystemTestBattler;
 use crate::SystemWords;
@@ -153,18 +155,10 @@ impl<'a> FromValue<'a> for System {
                     }
 
                     let variables: Vec<Option<&StringValue>> = ctx.from_value(value)?;
-                    let variables = variables
+                    let variables: Vec<Option<String>> = variables
                         .into_iter()
-                        .map(|value| {
-                            value
-                                .map(|value| {
-                                    std::str::from_utf8(value.value())
-                                        .map(|value| value.to_string())
-                                })
-                                .transpose()
-                        })
-                        .collect::<Result<_, _>>()
-                        .map_err(FromValueError::new_other)?;
+                        .map(|value| value.map(|value| decode_string(value.value())))
+                        .collect();
 
                     variables_field = Some(variables);
                 }
@@ -222,9 +216,7 @@ impl<'a> FromValue<'a> for System {
                     }
 
                     let gameover_name: &StringValue = ctx.from_value(value)?;
-                    let gameover_name = std::str::from_utf8(gameover_name.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let gameover_name = decode_string(gameover_name.value());
                     gameover_name_field = Some(gameover_name);
                 }
                 WORDS_FIELD => {
@@ -241,18 +233,10 @@ impl<'a> FromValue<'a> for System {
                     }
 
                     let switches: Vec<Option<&StringValue>> = ctx.from_value(value)?;
-                    let switches = switches
+                    let switches: Vec<Option<String>> = switches
                         .into_iter()
-                        .map(|value| {
-                            value
-                                .map(|value| {
-                                    std::str::from_utf8(value.value())
-                                        .map(|value| value.to_string())
-                                })
-                                .transpose()
-                        })
-                        .collect::<Result<_, _>>()
-                        .map_err(FromValueError::new_other)?;
+                        .map(|value| value.map(|value| decode_string(value.value())))
+                        .collect();
 
                     switches_field = Some(switches);
                 }
@@ -310,9 +294,7 @@ impl<'a> FromValue<'a> for System {
                     }
 
                     let title_name: &StringValue = ctx.from_value(value)?;
-                    let title_name = std::str::from_utf8(title_name.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let title_name = decode_string(title_name.value());
                     title_name_field = Some(title_name);
                 }
                 ENEMY_COLLAPSE_SE_FIELD => {
@@ -337,13 +319,10 @@ impl<'a> FromValue<'a> for System {
                     }
 
                     let elements: Vec<&StringValue> = ctx.from_value(value)?;
-                    let elements = elements
+                    let elements: Vec<String> = elements
                         .into_iter()
-                        .map(|value| {
-                            std::str::from_utf8(value.value()).map(|value| value.to_string())
-                        })
-                        .collect::<Result<_, _>>()
-                        .map_err(FromValueError::new_other)?;
+                        .map(|value| decode_string(value.value()))
+                        .collect();
 
                     elements_field = Some(elements);
                 }
@@ -401,9 +380,7 @@ impl<'a> FromValue<'a> for System {
                     }
 
                     let windowskin_name: &StringValue = ctx.from_value(value)?;
-                    let windowskin_name = std::str::from_utf8(windowskin_name.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let windowskin_name = decode_string(windowskin_name.value());
                     windowskin_name_field = Some(windowskin_name);
                 }
                 TEST_BATTLERS_FIELD => {
@@ -420,9 +397,7 @@ impl<'a> FromValue<'a> for System {
                     }
 
                     let battleback_name: &StringValue = ctx.from_value(value)?;
-                    let battleback_name = std::str::from_utf8(battleback_name.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let battleback_name = decode_string(battleback_name.value());
                     battleback_name_field = Some(battleback_name);
                 }
                 PARTY_MEMBERS_FIELD => {
@@ -455,9 +430,7 @@ impl<'a> FromValue<'a> for System {
                     }
 
                     let battler_name: &StringValue = ctx.from_value(value)?;
-                    let battler_name = std::str::from_utf8(battler_name.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let battler_name = decode_string(battler_name.value());
                     battler_name_field = Some(battler_name);
                 }
                 SAVE_SE_FIELD => {
@@ -474,9 +447,7 @@ impl<'a> FromValue<'a> for System {
                     }
 
                     let battle_transition: &StringValue = ctx.from_value(value)?;
-                    let battle_transition = std::str::from_utf8(battle_transition.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let battle_transition = decode_string(battle_transition.value());
                     battle_transition_field = Some(battle_transition);
                 }
                 START_X_FIELD => {
@@ -709,7 +680,7 @@ impl IntoValue for System {
             let mut variables = Vec::with_capacity(self.variables.len());
             for variable in self.variables {
                 let handle = match variable {
-                    Some(variable) => arena.create_string(variable.into()).into(),
+                    Some(variable) => string2ruby_string(variable, arena)?,
                     None => arena.create_nil().into(),
                 };
                 variables.push(handle);
@@ -723,13 +694,13 @@ impl IntoValue for System {
         let battle_end_me_field_value = self.battle_end_me.into_value(arena)?;
         let start_map_id_field_value = self.start_map_id.into_value(arena)?;
         let shop_se_field_value = self.shop_se.into_value(arena)?;
-        let gameover_name_field_value = arena.create_string(self.gameover_name.into()).into();
+        let gameover_name_field_value = string2ruby_string(self.gameover_name, arena)?;
         let words_field_value = self.words.into_value(arena)?;
         let switches_field_value = {
             let mut switches = Vec::with_capacity(self.switches.len());
             for switch in self.switches {
                 let handle = match switch {
-                    Some(switch) => arena.create_string(switch.into()).into(),
+                    Some(switch) => string2ruby_string(switch, arena)?,
                     None => arena.create_nil().into(),
                 };
                 switches.push(handle);
@@ -743,13 +714,13 @@ impl IntoValue for System {
         let battle_bgm_field_value = self.battle_bgm.into_value(arena)?;
         let test_troop_id_field_value = self.test_troop_id.into_value(arena)?;
         let equip_se_field_value = self.equip_se.into_value(arena)?;
-        let title_name_field_value = arena.create_string(self.title_name.into()).into();
+        let title_name_field_value = string2ruby_string(self.title_name, arena)?;
         let enemy_collapse_se_field_value = self.enemy_collapse_se.into_value(arena)?;
         let cursor_se_field_value = self.cursor_se.into_value(arena)?;
         let elements_field_value = {
             let mut elements = Vec::with_capacity(self.elements.len());
             for element in self.elements {
-                let handle = arena.create_string(element.into()).into();
+                let handle = string2ruby_string(element, arena)?;
                 elements.push(handle);
             }
             arena.create_array(elements).into()
@@ -760,16 +731,15 @@ impl IntoValue for System {
         let load_se_field_value = self.load_se.into_value(arena)?;
         let title_bgm_field_value = self.title_bgm.into_value(arena)?;
         let buzzer_se_field_value = self.buzzer_se.into_value(arena)?;
-        let windowskin_name_field_value = arena.create_string(self.windowskin_name.into()).into();
+        let windowskin_name_field_value = string2ruby_string(self.windowskin_name, arena)?;
         let test_battlers_field_value = self.test_battlers.into_value(arena)?;
-        let battleback_name_field_value = arena.create_string(self.battleback_name.into()).into();
+        let battleback_name_field_value = string2ruby_string(self.battleback_name, arena)?;
         let party_members_field_value = self.party_members.into_value(arena)?;
         let actor_collapse_se_field_value = self.actor_collapse_se.into_value(arena)?;
         let gameover_me_field_value = self.gameover_me.into_value(arena)?;
-        let battler_name_field_value = arena.create_string(self.battler_name.into()).into();
+        let battler_name_field_value = string2ruby_string(self.battler_name, arena)?;
         let save_se_field_value = self.save_se.into_value(arena)?;
-        let battle_transition_field_value =
-            arena.create_string(self.battle_transition.into()).into();
+        let battle_transition_field_value = string2ruby_string(self.battle_transition, arena)?;
         let start_x_field_value = self.start_x.into_value(arena)?;
 
         let fields = vec![
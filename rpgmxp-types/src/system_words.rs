@@ -1,3 +1,5 @@
+use crate::util::decode_string;
+use crate::util::string2ruby_string;
 use ruby_marshal::FromValue;
 use ruby_marshal::FromValueContext;
 use ruby_marshal::FromValueError;
@@ -102,9 +104,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let str_: &StringValue = ctx.from_value(value)?;
-                    let str_ = std::str::from_utf8(str_.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let str_ = decode_string(str_.value());
                     str_field = Some(str_);
                 }
                 ARMOR3_FIELD => {
@@ -113,9 +113,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let armor3: &StringValue = ctx.from_value(value)?;
-                    let armor3 = std::str::from_utf8(armor3.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let armor3 = decode_string(armor3.value());
                     armor3_field = Some(armor3);
                 }
                 MDEF_FIELD => {
@@ -124,9 +122,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let mdef: &StringValue = ctx.from_value(value)?;
-                    let mdef = std::str::from_utf8(mdef.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let mdef = decode_string(mdef.value());
                     mdef_field = Some(mdef);
                 }
                 GOLD_FIELD => {
@@ -135,9 +131,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let gold: &StringValue = ctx.from_value(value)?;
-                    let gold = std::str::from_utf8(gold.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let gold = decode_string(gold.value());
                     gold_field = Some(gold);
                 }
                 SP_FIELD => {
@@ -146,9 +140,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let sp: &StringValue = ctx.from_value(value)?;
-                    let sp = std::str::from_utf8(sp.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let sp = decode_string(sp.value());
                     sp_field = Some(sp);
                 }
                 SKILL_FIELD => {
@@ -157,9 +149,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let skill: &StringValue = ctx.from_value(value)?;
-                    let skill = std::str::from_utf8(skill.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let skill = decode_string(skill.value());
                     skill_field = Some(skill);
                 }
                 INT_FIELD => {
@@ -168,9 +158,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let int: &StringValue = ctx.from_value(value)?;
-                    let int = std::str::from_utf8(int.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let int = decode_string(int.value());
                     int_field = Some(int);
                 }
                 ARMOR2_FIELD => {
@@ -179,9 +167,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let armor2: &StringValue = ctx.from_value(value)?;
-                    let armor2 = std::str::from_utf8(armor2.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let armor2 = decode_string(armor2.value());
                     armor2_field = Some(armor2);
                 }
                 EQUIP_FIELD => {
@@ -190,9 +176,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let equip: &StringValue = ctx.from_value(value)?;
-                    let equip = std::str::from_utf8(equip.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let equip = decode_string(equip.value());
                     equip_field = Some(equip);
                 }
                 HP_FIELD => {
@@ -201,9 +185,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let hp: &StringValue = ctx.from_value(value)?;
-                    let hp = std::str::from_utf8(hp.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let hp = decode_string(hp.value());
                     hp_field = Some(hp);
                 }
                 PDEF_FIELD => {
@@ -212,9 +194,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let pdef: &StringValue = ctx.from_value(value)?;
-                    let pdef = std::str::from_utf8(pdef.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let pdef = decode_string(pdef.value());
                     pdef_field = Some(pdef);
                 }
                 ATTACK_FIELD => {
@@ -223,9 +203,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let attack: &StringValue = ctx.from_value(value)?;
-                    let attack = std::str::from_utf8(attack.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let attack = decode_string(attack.value());
                     attack_field = Some(attack);
                 }
                 AGI_FIELD => {
@@ -234,9 +212,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let agi: &StringValue = ctx.from_value(value)?;
-                    let agi = std::str::from_utf8(agi.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let agi = decode_string(agi.value());
                     agi_field = Some(agi);
                 }
                 ARMOR1_FIELD => {
@@ -245,9 +221,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let armor1: &StringValue = ctx.from_value(value)?;
-                    let armor1 = std::str::from_utf8(armor1.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let armor1 = decode_string(armor1.value());
                     armor1_field = Some(armor1);
                 }
                 ATK_FIELD => {
@@ -256,9 +230,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let atk: &StringValue = ctx.from_value(value)?;
-                    let atk = std::str::from_utf8(atk.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let atk = decode_string(atk.value());
                     atk_field = Some(atk);
                 }
                 ITEM_FIELD => {
@@ -267,9 +239,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let item: &StringValue = ctx.from_value(value)?;
-                    let item = std::str::from_utf8(item.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let item = decode_string(item.value());
                     item_field = Some(item);
                 }
                 DEX_FIELD => {
@@ -278,9 +248,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let dex: &StringValue = ctx.from_value(value)?;
-                    let dex = std::str::from_utf8(dex.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let dex = decode_string(dex.value());
                     dex_field = Some(dex);
                 }
                 ARMOR4_FIELD => {
@@ -289,9 +257,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let armor4: &StringValue = ctx.from_value(value)?;
-                    let armor4 = std::str::from_utf8(armor4.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let armor4 = decode_string(armor4.value());
                     armor4_field = Some(armor4);
                 }
                 WEAPON_FIELD => {
@@ -300,9 +266,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let weapon: &StringValue = ctx.from_value(value)?;
-                    let weapon = std::str::from_utf8(weapon.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let weapon = decode_string(weapon.value());
                     weapon_field = Some(weapon);
                 }
                 GUARD_FIELD => {
@@ -311,9 +275,7 @@ impl<'a> FromValue<'a> for SystemWords {
                     }
 
                     let guard: &StringValue = ctx.from_value(value)?;
-                    let guard = std::str::from_utf8(guard.value())
-                        .map_err(FromValueError::new_other)?
-                        .to_string();
+                    let guard = decode_string(guard.value());
                     guard_field = Some(guard);
                 }
                 _ => {
@@ -434,26 +396,26 @@ impl IntoValue for SystemWords {
         let weapon_field_key = arena.create_symbol(WEAPON_FIELD.into());
         let guard_field_key = arena.create_symbol(GUARD_FIELD.into());
 
-        let str_field_value = arena.create_string(self.str_.into()).into();
-        let armor3_field_value = arena.create_string(self.armor3.into()).into();
-        let mdef_field_value = arena.create_string(self.mdef.into()).into();
-        let gold_field_value = arena.create_string(self.gold.into()).into();
-        let sp_field_value = arena.create_string(self.sp.into()).into();
-        let skill_field_value = arena.create_string(self.skill.into()).into();
-        let int_field_value = arena.create_string(self.int.into()).into();
-        let armor2_field_value = arena.create_string(self.armor2.into()).into();
-        let equip_field_value = arena.create_string(self.equip.into()).into();
-        let hp_field_value = arena.create_string(self.hp.into()).into();
-        let pdef_field_value = arena.create_string(self.pdef.into()).into();
-        let attack_field_value = arena.create_string(self.attack.into()).into();
-        let agi_field_value = arena.create_string(self.agi.into()).into();
-        let armor1_field_value = arena.create_string(self.armor1.into()).into();
-        let atk_field_value = arena.create_string(self.atk.into()).into();
-        let item_field_value = arena.create_string(self.item.into()).into();
-        let dex_field_value = arena.create_string(self.dex.into()).into();
-        let armor4_field_value = arena.create_string(self.armor4.into()).into();
-        let weapon_field_value = arena.create_string(self.weapon.into()).into();
-        let guard_field_value = arena.create_string(self.guard.into()).into();
+        let str_field_value = string2ruby_string(self.str_, arena)?;
+        let armor3_field_value = string2ruby_string(self.armor3, arena)?;
+        let mdef_field_value = string2ruby_string(self.mdef, arena)?;
+        let gold_field_value = string2ruby_string(self.gold, arena)?;
+        let sp_field_value = string2ruby_string(self.sp, arena)?;
+        let skill_field_value = string2ruby_string(self.skill, arena)?;
+        let int_field_value = string2ruby_string(self.int, arena)?;
+        let armor2_field_value = string2ruby_string(self.armor2, arena)?;
+        let equip_field_value = string2ruby_string(self.equip, arena)?;
+        let hp_field_value = string2ruby_string(self.hp, arena)?;
+        let pdef_field_value = string2ruby_string(self.pdef, arena)?;
+        let attack_field_value = string2ruby_string(self.attack, arena)?;
+        let agi_field_value = string2ruby_string(self.agi, arena)?;
+        let armor1_field_value = string2ruby_string(self.armor1, arena)?;
+        let atk_field_value = string2ruby_string(self.atk, arena)?;
+        let item_field_value = string2ruby_string(self.item, arena)?;
+        let dex_field_value = string2ruby_string(self.dex, arena)?;
+        let armor4_field_value = string2ruby_string(self.armor4, arena)?;
+        let weapon_field_value = string2ruby_string(self.weapon, arena)?;
+        let guard_field_value = string2ruby_string(self.guard, arena)?;
 
         let fields = vec![
             (str_field_key, str_field_value),
@@ -1,3 +1,4 @@
+pub(crate) use rpgm_common_types::decode_string;
 pub(crate) use rpgm_common_types::ruby_string2string;
 pub(crate) use rpgm_common_types::string2ruby_string;
 use ruby_marshal::FromValue;